@@ -0,0 +1,236 @@
+//! Files too large for one QR code, sent as a sequence of codes that each carry a
+//! [`fountain`](crate::fountain) chunk, so that a camera can join at any time and miss codes.
+//!
+//! The animated GIF and PNG loop over a fixed set of chunks, the full-window view shows new
+//! chunks without end. [`frames::decode`](crate::frames::decode) reads them back.
+
+use image::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, GrayImage, Luma, Rgba, RgbaImage};
+use qrcode::bits::Bits;
+use qrcode::types::Mode;
+use qrcode::{EcLevel, Version};
+
+use crate::encode::{self, EncodeError, Options, Pin};
+use crate::fountain::{self, Encoder};
+use crate::symbol::Symbol;
+
+pub const VERSION: i16 = 15;
+pub const FPS: u32 = 5;
+/// Pixels per module in the animated GIF and PNG.
+const MODULE_SIZE: u32 = 4;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AnimationError {
+    #[error("Bitte für die animierte Übertragung eine Version von {min} bis 40 wählen.")]
+    InvalidVersion { version: i16, min: i16 },
+    #[error("Bitte 1 bis 30 Codes pro Sekunde wählen.")]
+    InvalidFps(u32),
+    #[error(
+        "Die Datei bräuchte {blocks} Codes, höchstens {max} sind möglich. Bitte eine größere \
+         Version wählen."
+    )]
+    TooManyBlocks { blocks: usize, max: usize },
+    #[error(transparent)]
+    Encode(#[from] EncodeError),
+    #[error("Das animierte GIF konnte nicht erzeugt werden: {0}")]
+    Image(#[from] image::ImageError),
+    #[error("Das animierte PNG konnte nicht erzeugt werden: {0}")]
+    Png(#[from] png::EncodingError),
+}
+
+#[derive(Debug)]
+pub struct Animation {
+    encoder: Encoder,
+    version: i16,
+    ec_level: EcLevel,
+    fps: u32,
+}
+
+impl Animation {
+    pub fn new(
+        data: Vec<u8>,
+        version: i16,
+        ec_level: EcLevel,
+        fps: u32,
+    ) -> Result<Self, AnimationError> {
+        if !(1..=30).contains(&fps) {
+            return Err(AnimationError::InvalidFps(fps));
+        }
+        let Some(block_len) = block_len(version, ec_level) else {
+            let min = (1..=40)
+                .find(|&version| block_len(version, ec_level).is_some())
+                .unwrap_or(40);
+            return Err(AnimationError::InvalidVersion { version, min });
+        };
+        let encoder = Encoder::new(data, block_len);
+        if encoder.blocks() > fountain::MAX_BLOCKS {
+            return Err(AnimationError::TooManyBlocks {
+                blocks: encoder.blocks(),
+                max: fountain::MAX_BLOCKS,
+            });
+        }
+        Ok(Self {
+            encoder,
+            version,
+            ec_level,
+            fps,
+        })
+    }
+
+    pub fn blocks(&self) -> usize {
+        self.encoder.blocks()
+    }
+
+    pub fn fps(&self) -> u32 {
+        self.fps
+    }
+
+    /// The number of codes in one loop of the GIF and PNG: all blocks as they are, then half as many
+    /// mixes again for receivers that missed some.
+    pub fn frames(&self) -> usize {
+        self.blocks() + self.blocks() / 2 + 10
+    }
+
+    /// The bytes of the chunk with `seed`.
+    pub fn chunk(&self, seed: u32) -> Vec<u8> {
+        self.encoder.chunk(seed).to_bytes()
+    }
+
+    /// The code of the chunk with `seed`, which may be any number.
+    pub fn symbol(&self, seed: u32) -> Result<Symbol, AnimationError> {
+        let options = Options {
+            eci: false,
+            version: Some(self.version),
+            pin: Pin::Exact,
+            ..Options::default()
+        };
+        Ok(encode::encode_bytes(&self.chunk(seed), self.ec_level, &options)?.symbol)
+    }
+
+    /// One loop of codes as an endlessly repeating GIF.
+    pub fn gif(&self) -> Result<Vec<u8>, AnimationError> {
+        let mut gif = Vec::new();
+        let mut encoder = GifEncoder::new_with_speed(&mut gif, 30);
+        encoder.set_repeat(Repeat::Infinite)?;
+        let delay = Delay::from_numer_denom_ms(1000, self.fps);
+        for seed in 0..self.frames() as u32 {
+            let image = self.frame(seed)?;
+            let image = RgbaImage::from_fn(image.width(), image.height(), |x, y| {
+                let Luma([luma]) = *image.get_pixel(x, y);
+                Rgba([luma, luma, luma, 255])
+            });
+            encoder.encode_frame(Frame::from_parts(image, 0, 0, delay))?;
+        }
+        drop(encoder);
+        Ok(gif)
+    }
+
+    /// One loop of codes as an endlessly repeating animated PNG.
+    pub fn apng(&self) -> Result<Vec<u8>, AnimationError> {
+        // every code has the same version, so all frames have the same size
+        let first = self.frame(0)?;
+        let mut apng = Vec::new();
+        let mut encoder = png::Encoder::new(&mut apng, first.width(), first.height());
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::One);
+        encoder.set_compression(png::Compression::Best);
+        encoder.set_animated(self.frames() as u32, 0)?;
+        encoder.set_frame_delay(1, self.fps as u16)?;
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&one_bit(&first))?;
+        for seed in 1..self.frames() as u32 {
+            writer.write_image_data(&one_bit(&self.frame(seed)?))?;
+        }
+        writer.finish()?;
+        Ok(apng)
+    }
+
+    /// The code of the chunk with `seed` as it appears in the GIF and PNG.
+    pub fn frame(&self, seed: u32) -> Result<GrayImage, AnimationError> {
+        Ok(self
+            .symbol(seed)?
+            .render::<Luma<u8>>()
+            .module_dimensions(MODULE_SIZE, MODULE_SIZE)
+            .build())
+    }
+}
+
+/// The rows of a black and white image with eight pixels per byte, light ones as set bits.
+fn one_bit(image: &GrayImage) -> Vec<u8> {
+    image
+        .rows()
+        .flat_map(|row| {
+            let pixels: Vec<_> = row.map(|&Luma([luma])| luma >= 128).collect();
+            pixels
+                .chunks(8)
+                .map(|byte| {
+                    (0..8).fold(0, |acc, i| acc << 1 | u8::from(byte.get(i) == Some(&true)))
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// The bytes of a chunk that fit into one code of `version` in a byte segment.
+fn block_len(version: i16, ec_level: EcLevel) -> Option<usize> {
+    if !(1..=40).contains(&version) {
+        return None;
+    }
+    let version = Version::Normal(version);
+    let capacity = Bits::new(version).max_len(ec_level).ok()?;
+    let header = version.mode_bits_count() + Mode::Byte.length_bits_count(version);
+    let len = (capacity.checked_sub(header)? / 8).checked_sub(fountain::HEADER_LEN)?;
+    (len > 0).then_some(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frames;
+    use crate::payload::file::MAX_ANIMATED_LEN;
+
+    #[test]
+    fn chunk_sizes() {
+        // ISO/IEC 18004, table 7: 17 bytes for 1-L, 32 bytes for 2-L, 1273 bytes for 40-H
+        assert_eq!(block_len(1, EcLevel::L), None);
+        assert_eq!(block_len(2, EcLevel::L), Some(32 - fountain::HEADER_LEN));
+        assert_eq!(block_len(40, EcLevel::H), Some(1273 - fountain::HEADER_LEN));
+        assert!(matches!(
+            Animation::new(vec![1], 1, EcLevel::H, FPS),
+            Err(AnimationError::InvalidVersion { version: 1, min: 3 })
+        ));
+        assert!(matches!(
+            Animation::new(vec![1], VERSION, EcLevel::M, 0),
+            Err(AnimationError::InvalidFps(0))
+        ));
+        let largest = vec![0; MAX_ANIMATED_LEN];
+        assert!(matches!(
+            Animation::new(largest.clone(), 2, EcLevel::L, FPS),
+            Err(AnimationError::TooManyBlocks { blocks: 17477, .. })
+        ));
+        assert!(Animation::new(largest, VERSION, EcLevel::H, FPS).is_ok());
+
+        let animation = Animation::new(vec![0xff; 8000], 40, EcLevel::L, FPS).unwrap();
+        assert_eq!(animation.blocks(), 3);
+        for seed in [0, 2, 3, 1000] {
+            assert_eq!(animation.symbol(seed).unwrap().width(), 177);
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let data: Vec<u8> = (0..3000_u32).map(|i| (i * 31 % 256) as u8).collect();
+        let animation = Animation::new(data.clone(), 10, EcLevel::M, FPS).unwrap();
+        for (suffix, bytes) in [
+            (".gif", animation.gif().unwrap()),
+            (".png", animation.apng().unwrap()),
+        ] {
+            let file = tempfile::Builder::new().suffix(suffix).tempfile().unwrap();
+            std::fs::write(file.path(), bytes).unwrap();
+            let recovered = frames::decode(file.path()).unwrap();
+            assert_eq!(recovered.data, data, "{suffix}");
+            assert_eq!(recovered.kind, "fountain");
+            assert_eq!(recovered.frames, animation.blocks());
+        }
+    }
+}
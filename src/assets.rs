@@ -1,12 +1,50 @@
 use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
 
 use image::png::{CompressionType, FilterType, PngEncoder};
-use image::{ColorType, LumaA};
-use qrcode::render::svg;
-use qrcode::{EcLevel, QrCode};
-use serde::Deserialize;
+use image::{ColorType, ImageBuffer, LumaA};
+use parking_lot::Mutex;
+use pdf_writer::{Content, Finish, Name, PdfWriter, Rect, Ref};
+use qrcode::render::{svg, Pixel, Renderer};
+use qrcode::{EcLevel, Version};
+use serde::{Deserialize, Serialize};
+use tinyfiledialogs::{open_file_dialog, save_file_dialog};
+use usvg::TreeParsing;
 use wry::http::{Request, Response};
 
+use crate::animation::{Animation, AnimationError};
+use crate::budget::Budget;
+use crate::compact::compact_url;
+use crate::datamatrix::{self, DataMatrix, DataMatrixError};
+use crate::decode::{self, Decoded};
+use crate::encode::{self, Charset, EncodeError, Encoded, Options, Pin};
+use crate::frames::{self, Recovered};
+use crate::overlay::Overlay;
+use crate::payload::provider::{self, url_text};
+use crate::payload::{otp, Payload, PayloadError, Symbology};
+use crate::phishing::{self, Warning};
+use crate::print_size::{Camera, PrintSize};
+use crate::signature::{self, SignatureError};
+use crate::verify::{self, VerifyError};
+use crate::{batch, clipboard, metadata, robustness, symbol};
+
+/// Resolution of `kurzlink.pdf` and `kurzlink.png` in dots per inch.
+const DPI: f64 = 300.0;
+/// Width of `kurzlink.png` in pixels if no print width is given, A4 at 300 dpi.
+const PNG_SIZE: u32 = 2480;
+/// Minimum width of `kurzlink.svg` and `kurzlink.pdf` in pixels if no print width is given.
+const SVG_SIZE: u32 = 1000;
+const MM_PER_INCH: f64 = 25.4;
+/// Scan distance in millimetres if none is given, a phone held in the hand.
+const DISTANCE: f64 = 300.0;
+
+/// The last animated transfer, with the kind and form fields of its query, since the full-window
+/// view asks for several frames per second.
+static ANIMATION: Mutex<Option<(String, Arc<Animation>)>> = Mutex::new(None);
+
 pub fn asset_handler(req: &Request<Vec<u8>>) -> Result<Response<Cow<'static, [u8]>>, wry::Error> {
     let uri = req.uri();
 
@@ -14,10 +52,18 @@ pub fn asset_handler(req: &Request<Vec<u8>>) -> Result<Response<Cow<'static, [u8
     let query = uri.query().unwrap_or_default().trim();
     if let Some(content_type) = mimetype(path) {
         if let Some(bytes) = content(path, query) {
+            // imports open a file dialog every time, OTP secrets and codes change, so do keys
+            let volatile = ["/import/", "/otp/", "/signature/"];
+            let volatile = volatile.iter().any(|prefix| path.starts_with(prefix));
+            // and passwords must not end up in the cache on disk, not even in its keys
+            let cache_control = match volatile || is_secret(query) {
+                true => "no-store",
+                false => "max-age=3600,private,immutable",
+            };
             let response = Response::builder()
                 .status(200)
                 .header("content-type", content_type)
-                .header("cache-control", "max-age=3600,private,immutable")
+                .header("cache-control", cache_control)
                 .body(bytes)?;
             return Ok(response);
         }
@@ -30,11 +76,20 @@ pub fn asset_handler(req: &Request<Vec<u8>>) -> Result<Response<Cow<'static, [u8
     Ok(response)
 }
 
+/// Whether the query holds the form of a kind with secrets, like Wi-Fi passwords.
+fn is_secret(query: &str) -> bool {
+    form_urlencoded::parse(query.as_bytes())
+        .find(|(key, _)| key == "t")
+        .is_some_and(|(_, kind)| provider::provider(&kind).is_secret())
+}
+
 fn mimetype(path: &str) -> Option<&'static str> {
     match path {
         path if path.ends_with(".css") => Some("text/css; charset=utf-8"),
+        path if path.ends_with(".gif") => Some("image/gif"),
         path if path.ends_with(".html") => Some("text/html; charset=utf-8"),
         path if path.ends_with(".js") => Some("text/javascript; charset=utf-8"),
+        path if path.ends_with(".json") => Some("application/json"),
         path if path.ends_with(".pdf") => Some("application/pdf"),
         path if path.ends_with(".png") => Some("image/png"),
         path if path.ends_with(".svg") => Some("image/svg+xml; charset=utf-8"),
@@ -56,98 +111,1297 @@ fn content(path: &str, query: &str) -> Option<Cow<'static, [u8]>> {
         "/kurzlink.svg" => svg(query),
         "/kurzlink.pdf" => pdf(query),
         "/kurzlink.png" => png(query),
+        "/document.pdf" => document(query),
+        "/batch.pdf" => batch(query),
+        "/info.json" => info(query),
+        "/compact.json" => compact(query),
+        "/url-check.json" => url_check(query),
+        "/verify.json" => verification(query),
+        "/robustness.json" => robustness(query),
+        "/budget.json" => budget(query),
+        "/budget.svg" => budget_svg(query),
+        "/print-size.json" => print_size(query),
+        "/animation.json" => animation_info(query),
+        "/animation.gif" => animation_gif(query),
+        "/animation.png" => animation_png(query),
+        "/animation/frame.svg" => animation_frame(query),
+        "/otp/secret.json" => otp_secret(query),
+        "/otp/code.json" => otp_code(query),
+        "/import/form.json" => import_form(query),
+        "/import/image.json" => import_image(),
+        "/import/clipboard.json" => import_clipboard(),
+        "/import/export.json" => import_export(),
+        "/import/frames.json" => import_frames(),
+        "/signature/key.json" => signature_key(query),
+        "/signature/trust.json" => signature_trust(query),
+        "/signature/verify.json" => signature_verify(query),
         _ => None,
     }
 }
 
-#[derive(Default, Deserialize)]
+#[derive(Deserialize)]
+#[serde(default)]
 struct Query {
+    /// The kind of payload, whose form fields are parsed by its provider.
+    t: String,
     q: String,
+    eci: bool,
+    charset: Charset,
+    version: Option<i16>,
+    pin: Pin,
+    mask: Option<u8>,
+    sign: bool,
+    sign_encoding: signature::Encoding,
+    /// Imported forms of the kind, each becoming a page of `batch.pdf`.
+    items: Vec<BTreeMap<String, String>>,
+    /// Width of the exported PDF and PNG including the quiet zone in millimetres.
+    print_width: Option<f64>,
+    /// Expected distance between camera and code in centimetres.
+    distance: Option<f64>,
+    fov: Option<f64>,
+    resolution: Option<f64>,
+    pixels_per_module: Option<f64>,
+    #[serde(skip)]
+    raw: String,
+}
+
+impl Default for Query {
+    fn default() -> Self {
+        let options = Options::default();
+        Self {
+            t: String::new(),
+            q: String::new(),
+            eci: options.eci,
+            charset: options.charset,
+            version: options.version,
+            pin: options.pin,
+            mask: options.mask,
+            sign: false,
+            sign_encoding: signature::Encoding::default(),
+            items: Vec::new(),
+            print_width: None,
+            distance: None,
+            fov: None,
+            resolution: None,
+            pixels_per_module: None,
+            raw: String::new(),
+        }
+    }
+}
+
+impl Query {
+    fn parse(query: &str) -> Self {
+        Self {
+            raw: query.trim().to_owned(),
+            ..provider::parse(query)
+        }
+    }
+
+    fn text(&self) -> &str {
+        url_text(&self.q)
+    }
+
+    /// The print width of the PDF and PNG in pixels, if one is given.
+    fn print_pixels(&self) -> Option<u32> {
+        let width = self.print_width.filter(|&width| width > 0.0)?;
+        Some((width / MM_PER_INCH * DPI).round().clamp(1.0, 20_000.0) as u32)
+    }
+
+    /// The expected scan distance in millimetres.
+    fn distance(&self) -> f64 {
+        self.distance
+            .map_or(DISTANCE, |distance| distance.max(0.0) * 10.0)
+    }
+
+    fn camera(&self) -> Camera {
+        let camera = Camera::default();
+        Camera {
+            field_of_view: self
+                .fov
+                .map_or(camera.field_of_view, |fov| fov.clamp(1.0, 179.0)),
+            resolution: self.resolution.map_or(camera.resolution, |r| r.max(1.0)),
+            pixels_per_module: self
+                .pixels_per_module
+                .map_or(camera.pixels_per_module, |p| p.max(0.1)),
+        }
+    }
+
+    fn payload(&self) -> Result<Payload, PayloadError> {
+        let payload = self.unsigned_payload()?;
+        match self.sign {
+            // EPC, Wi-Fi and the like would be corrupted by the appended signature
+            true if !provider::provider(&self.t).is_signable() => {
+                Err(SignatureError::Unsupported.into())
+            },
+            true => Ok(signature::sign_payload(payload, self.sign_encoding)?),
+            false => Ok(payload),
+        }
+    }
+
+    fn unsigned_payload(&self) -> Result<Payload, PayloadError> {
+        provider::payload(&self.t, &self.raw)
+    }
+
+    fn encode(&self, payload: &Payload) -> Result<Encoded, EncodeError> {
+        let options = Options {
+            eci: self.eci,
+            charset: self.charset,
+            version: self.version,
+            pin: self.pin,
+            mask: self.mask,
+            max_version: payload.max_version,
+            fnc1: payload.fnc1,
+        };
+        match &payload.bytes {
+            Some(bytes) => encode::encode_bytes(bytes, payload.ec_level, &options),
+            None => encode::encode(&payload.text, payload.ec_level, &options),
+        }
+    }
+
+    fn code(&self, payload: &Payload) -> Result<Code, CodeError> {
+        match payload.symbology {
+            Symbology::Qr => Ok(Code::Qr(self.encode(payload)?, payload.overlay)),
+            Symbology::DataMatrix => Ok(Code::DataMatrix(DataMatrix::gs1(&payload.text)?)),
+        }
+    }
+
+    /// The codes of the imported items with their labels, completed by the current form.
+    fn items(&self) -> Vec<(String, Code, Payload)> {
+        let kind = provider::provider(&self.t).kind();
+        let mut codes = Vec::with_capacity(self.items.len());
+        for (index, item) in self.items.iter().enumerate() {
+            let query = provider::item_query(&self.raw, kind, item);
+            let code = provider::payload(kind, &query)
+                .map_err(CodeError::from)
+                .and_then(|payload| Ok((self.code(&payload)?, payload)));
+            match code {
+                Ok((code, payload)) => {
+                    codes.push((payload.label.clone().unwrap_or_default(), code, payload));
+                },
+                Err(err) => log::warn!("skipping item {index} of {kind}: {err}"),
+            }
+        }
+        codes
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+enum CodeError {
+    #[error(transparent)]
+    Payload(#[from] PayloadError),
+    #[error(transparent)]
+    Encode(#[from] EncodeError),
+    #[error(transparent)]
+    DataMatrix(#[from] DataMatrixError),
+}
+
+enum Code {
+    Qr(Encoded, Option<Overlay>),
+    DataMatrix(DataMatrix),
+}
+
+impl Code {
+    fn render<P: Pixel>(&self) -> Renderer<'_, P> {
+        match self {
+            Self::Qr(encoded, _) => encoded.symbol.render(),
+            Self::DataMatrix(data_matrix) => data_matrix.render(),
+        }
+    }
+
+    fn width(&self) -> usize {
+        match self {
+            Self::Qr(encoded, _) => encoded.symbol.width(),
+            Self::DataMatrix(data_matrix) => data_matrix.width(),
+        }
+    }
+
+    fn quiet_zone(&self) -> u32 {
+        match self {
+            Self::Qr(..) => symbol::QUIET_ZONE,
+            Self::DataMatrix(_) => datamatrix::QUIET_ZONE,
+        }
+    }
+
+    fn overlay(&self) -> Option<Overlay> {
+        match self {
+            Self::Qr(_, overlay) => *overlay,
+            Self::DataMatrix(_) => None,
+        }
+    }
+
+    fn svg(&self, quiet_zone: bool) -> String {
+        let mut svg = self
+            .render()
+            .quiet_zone(quiet_zone)
+            .min_dimensions(SVG_SIZE, SVG_SIZE)
+            .light_color(svg::Color("#ffffff00"))
+            .build();
+        if let Some(overlay) = self.overlay() {
+            let quiet_zone = if quiet_zone { self.quiet_zone() } else { 0 };
+            overlay.svg(&mut svg, self.width(), quiet_zone);
+        }
+        svg
+    }
+
+    /// The symbol as an image with a transparent background, e.g. 2480 pixels wide for
+    /// `kurzlink.png`.
+    fn raster(&self, size: u32) -> ImageBuffer<LumaA<u8>, Vec<u8>> {
+        let mut raster = self
+            .render()
+            .min_dimensions(size, size)
+            .light_color(LumaA([255_u8, 0_u8]))
+            .build();
+        if let Some(overlay) = self.overlay() {
+            overlay.paint(&mut raster, self.width(), self.quiet_zone());
+        }
+        raster
+    }
+
+    /// Number of modules per side, including the quiet zone.
+    fn modules(&self) -> usize {
+        self.width() + 2 * self.quiet_zone() as usize
+    }
+
+    /// The width in pixels when rendered at least `min` pixels wide.
+    fn pixels(&self, min: u32) -> u32 {
+        let modules = self.modules() as u32;
+        min.div_ceil(modules) * modules
+    }
+}
+
+fn code_and_payload(query: &str) -> Result<(Code, Payload), CodeError> {
+    let query = Query::parse(query);
+    let payload = query.payload()?;
+    Ok((query.code(&payload)?, payload))
+}
+
+fn symbol(query: &str) -> Option<(Code, Payload)> {
+    match code_and_payload(query) {
+        Ok(code) => Some(code),
+        Err(err) => {
+            log::error!("could not generate code: {err}");
+            None
+        },
+    }
 }
 
 fn svg(query: &str) -> Option<Cow<'static, [u8]>> {
-    let Query { q } = serde_qs::from_str(query.trim()).ok().unwrap_or_default();
-    let q = match q.trim() {
-        "" => "https://www.vetmed.fu-berlin.de/",
-        q => q,
-    };
-    let qr = match QrCode::with_error_correction_level(q.as_bytes(), EcLevel::L) {
-        Ok(qr) => qr,
+    let (qr, payload) = symbol(query)?;
+    let mut svg = qr.svg(true);
+    metadata::svg(&mut svg, query, &payload.text);
+    Some(Cow::Owned(svg.into_bytes()))
+}
+
+fn pdf(query: &str) -> Option<Cow<'static, [u8]>> {
+    let (qr, payload) = symbol(query)?;
+    let tree = match usvg::Tree::from_str(&qr.svg(true), &usvg::Options::default()) {
+        Ok(tree) => tree,
         Err(err) => {
-            log::error!("could not generate qr code for {q:?}: {err}");
+            log::error!("could not convert qr code to pdf: {err}");
             return None;
         },
     };
-    let qr = qr
-        .render()
-        .min_dimensions(1000, 1000)
-        .light_color(svg::Color("#ffffff00"))
-        .build()
-        .into_bytes();
-    Some(Cow::Owned(qr))
+    let pixels = Query::parse(query)
+        .print_pixels()
+        .unwrap_or_else(|| qr.pixels(SVG_SIZE));
+    let size = (f64::from(pixels) / DPI * 72.0) as f32;
+
+    let catalog_id = Ref::new(1);
+    let page_tree_id = Ref::new(2);
+    let page_id = Ref::new(3);
+    let content_id = Ref::new(4);
+    let metadata_id = Ref::new(5);
+    let code_id = Ref::new(6);
+
+    let mut writer = PdfWriter::new();
+    writer
+        .catalog(catalog_id)
+        .pages(page_tree_id)
+        .metadata(metadata_id);
+    writer.pages(page_tree_id).kids([page_id]).count(1);
+    let mut page = writer.page(page_id);
+    page.media_box(Rect::new(0.0, 0.0, size, size));
+    page.parent(page_tree_id);
+    page.contents(content_id);
+    page.resources().x_objects().pair(Name(b"C1"), code_id);
+    page.finish();
+    metadata::pdf(&mut writer, metadata_id, query, &payload.text);
+    svg2pdf::convert_tree_into(&tree, svg2pdf::Options::default(), &mut writer, code_id);
+
+    let mut content = Content::new();
+    content
+        .transform([size, 0.0, 0.0, size, 0.0, 0.0])
+        .x_object(Name(b"C1"));
+    writer.stream(content_id, &content.finish());
+    Some(Cow::Owned(writer.finish()))
 }
 
-fn pdf(query: &str) -> Option<Cow<'static, [u8]>> {
-    let Query { q } = serde_qs::from_str(query.trim()).ok().unwrap_or_default();
-    let q = match q.trim() {
-        "" => "https://www.vetmed.fu-berlin.de/",
-        q => q,
+fn png(query: &str) -> Option<Cow<'static, [u8]>> {
+    let size = Query::parse(query).print_pixels().unwrap_or(PNG_SIZE);
+    let (qr, payload) = symbol(query)?;
+    let qr = qr.raster(size);
+    let (height, width) = qr.dimensions();
+    let mut img = Vec::new();
+    let err = PngEncoder::new_with_quality(&mut img, CompressionType::Fast, FilterType::Up).encode(
+        qr.as_raw(),
+        width,
+        height,
+        ColorType::La8,
+    );
+    if let Err(err) = err {
+        log::error!("could not generate png: {err}");
+        return None;
+    }
+    metadata::png(&mut img, query, &payload.text, DPI);
+    Some(Cow::Owned(img))
+}
+
+/// The document of the kind that lays out the code, like the payment part of a Swiss QR-bill.
+fn document(query: &str) -> Option<Cow<'static, [u8]>> {
+    let (code, _) = symbol(query)?;
+    let provider = provider::provider(&Query::parse(query).t);
+    match provider.document(query, &code.svg(false)) {
+        Some(Ok(pdf)) => Some(Cow::Owned(pdf)),
+        Some(Err(err)) => {
+            log::error!("could not generate document of {}: {err}", provider.kind());
+            None
+        },
+        None => {
+            log::error!("{} has no document", provider.kind());
+            None
+        },
+    }
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Info<'a> {
+    Qr {
+        symbology: &'static str,
+        label: Option<String>,
+        version: i16,
+        width: usize,
+        ec_level: &'static str,
+        mask: u8,
+        charset: &'static str,
+        eci: Option<u32>,
+        bits: usize,
+        capacity: usize,
+        segments: Vec<SegmentInfo<'a>>,
+    },
+    DataMatrix {
+        symbology: &'static str,
+        label: Option<String>,
+        width: usize,
+        codewords: usize,
+        capacity: usize,
+    },
+    Err {
+        error: String,
+    },
+}
+
+#[derive(Serialize)]
+struct SegmentInfo<'a> {
+    mode: &'static str,
+    text: &'a str,
+    bits: usize,
+}
+
+/// The imported items as a PDF with one code per page.
+fn batch(query: &str) -> Option<Cow<'static, [u8]>> {
+    let query = Query::parse(query);
+    let codes = query.items();
+    if codes.is_empty() {
+        log::error!("no valid items to export");
+        return None;
+    }
+    let codes: Vec<_> = codes
+        .into_iter()
+        .map(|(name, code, _)| (name, code.svg(true)))
+        .collect();
+    let names: Vec<&str> = codes.iter().map(|(name, _)| name.as_str()).collect();
+    match batch::pdf(&codes, &query.raw, &names.join(", ")) {
+        Ok(pdf) => Some(Cow::Owned(pdf)),
+        Err(err) => {
+            log::error!("could not convert items to pdf: {err}");
+            None
+        },
+    }
+}
+
+fn info(query: &str) -> Option<Cow<'static, [u8]>> {
+    let query = Query::parse(query);
+    let code = query
+        .payload()
+        .map_err(CodeError::from)
+        .and_then(|payload| Ok((query.code(&payload)?, payload.label)));
+    let info = match &code {
+        Ok((Code::Qr(encoded, _), label)) => {
+            let version = encoded.symbol.version();
+            Info::Qr {
+                symbology: "qr",
+                label: label.clone(),
+                version: version_number(version),
+                width: encoded.symbol.width(),
+                ec_level: ec_level_name(encoded.symbol.error_correction_level()),
+                mask: encoded.symbol.mask(),
+                charset: encoded.charset.name(),
+                eci: encoded.eci,
+                bits: encoded.bits,
+                capacity: encoded.capacity,
+                segments: encoded
+                    .segments
+                    .iter()
+                    .map(|segment| SegmentInfo {
+                        mode: encode::mode_name(segment.mode),
+                        text: &segment.text,
+                        bits: segment.encoded_len(version),
+                    })
+                    .collect(),
+            }
+        },
+        Ok((Code::DataMatrix(data_matrix), label)) => Info::DataMatrix {
+            symbology: "datamatrix",
+            label: label.clone(),
+            width: data_matrix.width(),
+            codewords: data_matrix.codewords,
+            capacity: data_matrix.capacity,
+        },
+        Err(err) => Info::Err {
+            error: err.to_string(),
+        },
     };
-    let qr = match QrCode::with_error_correction_level(q.as_bytes(), EcLevel::L) {
-        Ok(qr) => qr,
+    match serde_json::to_vec(&info) {
+        Ok(json) => Some(Cow::Owned(json)),
         Err(err) => {
-            log::error!("could not generate qr code for {q:?}: {err}");
+            log::error!("could not serialize info: {err}");
+            None
+        },
+    }
+}
+
+/// The checks of everything that can be downloaded for a query.
+#[derive(Serialize)]
+struct Verifications {
+    #[serde(flatten)]
+    code: Verification,
+    /// The codes in `batch.pdf`, if items were imported.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    batch: Option<Check>,
+    /// Frames of the animated transfer, if the kind has one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    animation: Option<Check>,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Verification {
+    /// The PDF is converted from the SVG and not checked separately, and neither are documents
+    /// like the payment part, which draw the same SVG without the quiet zone.
+    Qr {
+        png: Check,
+        svg: Check,
+    },
+    Skipped {
+        skipped: &'static str,
+    },
+    Err {
+        error: String,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Check {
+    Ok { text: String, corrected: usize },
+    Err { error: String },
+}
+
+impl Check {
+    fn new(format: &str, result: Result<Decoded, VerifyError>) -> Self {
+        match result {
+            Ok(decoded) => Self::Ok {
+                text: decoded.text,
+                corrected: decoded.corrected,
+            },
+            Err(err) => {
+                log::warn!("the {format} could not be read back: {err}");
+                Self::Err {
+                    error: err.to_string(),
+                }
+            },
+        }
+    }
+}
+
+/// Decodes the rendered PNG and SVG and compares them to the payload.
+fn verification(query: &str) -> Option<Cow<'static, [u8]>> {
+    let parsed = Query::parse(query);
+    let code = match &code_and_payload(query) {
+        Ok((code @ Code::Qr(..), payload)) => Verification::Qr {
+            png: Check::new(
+                "png",
+                verify::verify(
+                    &decode::flatten(&code.raster(parsed.print_pixels().unwrap_or(PNG_SIZE))),
+                    payload,
+                ),
+            ),
+            svg: Check::new(
+                "svg",
+                verify::verify_svg(&code.svg(true), code.modules(), payload),
+            ),
+        },
+        Ok((Code::DataMatrix(_), _)) => Verification::Skipped {
+            skipped: "Data-Matrix-Codes können nicht gegengelesen werden.",
+        },
+        Err(err) => Verification::Err {
+            error: err.to_string(),
+        },
+    };
+    let verifications = Verifications {
+        code,
+        batch: verify_batch(&parsed),
+        animation: animation(query)
+            .ok()
+            .map(|animation| verify_animation(&animation)),
+    };
+    match serde_json::to_vec(&verifications) {
+        Ok(json) => Some(Cow::Owned(json)),
+        Err(err) => {
+            log::error!("could not serialize verification: {err}");
+            None
+        },
+    }
+}
+
+/// Decodes the codes of `batch.pdf`, and reports the first that fails.
+fn verify_batch(query: &Query) -> Option<Check> {
+    let items = query.items();
+    if items.is_empty() {
+        return None;
+    }
+    let mut corrected = 0;
+    for (name, code, payload) in &items {
+        if let Code::Qr(..) = code {
+            match verify::verify_svg(&code.svg(true), code.modules(), payload) {
+                Ok(decoded) => corrected = corrected.max(decoded.corrected),
+                Err(err) => {
+                    log::warn!("the item {name:?} could not be read back: {err}");
+                    return Some(Check::Err {
+                        error: format!("{name}: {err}"),
+                    });
+                },
+            }
+        }
+    }
+    Some(Check::Ok {
+        text: format!("{} Codes", items.len()),
+        corrected,
+    })
+}
+
+/// Decodes a frame with a block as it is and one with a mix of blocks.
+fn verify_animation(animation: &Animation) -> Check {
+    let mut corrected = 0;
+    for seed in [0, animation.blocks() as u32] {
+        let image = match animation.frame(seed) {
+            Ok(image) => image,
+            Err(err) => {
+                log::warn!("could not render frame {seed}: {err}");
+                return Check::Err {
+                    error: err.to_string(),
+                };
+            },
+        };
+        let payload = Payload {
+            bytes: Some(animation.chunk(seed)),
+            ..Payload::text("")
+        };
+        match verify::verify(&image, &payload) {
+            Ok(decoded) => corrected = corrected.max(decoded.corrected),
+            Err(err) => return Check::new("animation", Err(err)),
+        }
+    }
+    Check::Ok {
+        text: format!("Blöcke: {}", animation.blocks()),
+        corrected,
+    }
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Analysis<T> {
+    Ok(T),
+    Skipped { skipped: &'static str },
+    Err { error: String },
+}
+
+/// Distorts the symbol in several ways until it can no longer be read.
+fn robustness(query: &str) -> Option<Cow<'static, [u8]>> {
+    let analysis = match &code_and_payload(query) {
+        Ok((code @ Code::Qr(..), payload)) => {
+            let size = code.modules() as u32 * robustness::SCALE;
+            let image = decode::flatten(&code.raster(size));
+            Analysis::Ok(robustness::analyse(&image, code.quiet_zone(), payload))
+        },
+        Ok((Code::DataMatrix(_), _)) => Analysis::Skipped {
+            skipped: "Data-Matrix-Codes können nicht analysiert werden.",
+        },
+        Err(err) => Analysis::Err {
+            error: err.to_string(),
+        },
+    };
+    match serde_json::to_vec(&analysis) {
+        Ok(json) => Some(Cow::Owned(json)),
+        Err(err) => {
+            log::error!("could not serialize robustness analysis: {err}");
+            None
+        },
+    }
+}
+
+/// How many codewords may be lost per block, and how many the overlay takes.
+fn budget(query: &str) -> Option<Cow<'static, [u8]>> {
+    let analysis = match code_and_payload(query) {
+        Ok((Code::Qr(encoded, overlay), _)) => Analysis::Ok(Budget::new(&encoded.symbol, overlay)),
+        Ok((Code::DataMatrix(_), _)) => Analysis::Skipped {
+            skipped: "Für Data-Matrix-Codes gibt es keine Übersicht der Codewörter.",
+        },
+        Err(err) => Analysis::Err {
+            error: err.to_string(),
+        },
+    };
+    match serde_json::to_vec(&analysis) {
+        Ok(json) => Some(Cow::Owned(json)),
+        Err(err) => {
+            log::error!("could not serialize damage budget: {err}");
+            None
+        },
+    }
+}
+
+/// The symbol with its codewords coloured by their kind.
+fn budget_svg(query: &str) -> Option<Cow<'static, [u8]>> {
+    match symbol(query)?.0 {
+        Code::Qr(encoded, overlay) => {
+            let budget = Budget::new(&encoded.symbol, overlay);
+            Some(Cow::Owned(budget.svg(&encoded.symbol).into_bytes()))
+        },
+        Code::DataMatrix(_) => None,
+    }
+}
+
+/// Compares the printed size of the exports to the smallest size that can be scanned from the
+/// expected distance.
+fn print_size(query: &str) -> Option<Cow<'static, [u8]>> {
+    let query = Query::parse(query);
+    let code = query
+        .payload()
+        .map_err(CodeError::from)
+        .and_then(|payload| query.code(&payload));
+    let analysis = match code {
+        Ok(code) => {
+            let width = |pixels: u32| f64::from(pixels) / DPI * MM_PER_INCH;
+            let print_pixels = query.print_pixels();
+            let pdf = width(print_pixels.unwrap_or_else(|| code.pixels(SVG_SIZE)));
+            let png = width(code.pixels(print_pixels.unwrap_or(PNG_SIZE)));
+            Analysis::Ok(PrintSize::new(
+                code.modules(),
+                query.distance(),
+                &query.camera(),
+                &[("pdf", pdf), ("png", png)],
+            ))
+        },
+        Err(err) => Analysis::Err {
+            error: err.to_string(),
+        },
+    };
+    match serde_json::to_vec(&analysis) {
+        Ok(json) => Some(Cow::Owned(json)),
+        Err(err) => {
+            log::error!("could not serialize print size: {err}");
+            None
+        },
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+enum TransferError {
+    #[error("Dieser Inhalt kann nicht animiert übertragen werden.")]
+    Unsupported,
+    #[error(transparent)]
+    Payload(#[from] PayloadError),
+    #[error(transparent)]
+    Animation(#[from] AnimationError),
+}
+
+/// The form of the kind as an animated sequence of codes.
+fn animation(query: &str) -> Result<Arc<Animation>, TransferError> {
+    let provider = provider::provider(&Query::parse(query).t);
+    let prefix = format!("{}[", provider.kind());
+    let fields =
+        form_urlencoded::parse(query.as_bytes()).filter(|(key, _)| key.starts_with(&prefix));
+    let key = form_urlencoded::Serializer::new(String::new())
+        .append_pair("t", provider.kind())
+        .extend_pairs(fields)
+        .finish();
+    if let Some((cached, animation)) = &*ANIMATION.lock() {
+        if *cached == key {
+            return Ok(Arc::clone(animation));
+        }
+    }
+
+    let transfer = provider
+        .transfer(query)
+        .ok_or(TransferError::Unsupported)??;
+    let animation = Arc::new(Animation::new(
+        transfer.bytes,
+        transfer.version,
+        transfer.ec_level,
+        transfer.fps,
+    )?);
+    *ANIMATION.lock() = Some((key, Arc::clone(&animation)));
+    Ok(animation)
+}
+
+#[derive(Serialize)]
+struct AnimationInfo {
+    blocks: usize,
+    /// Codes in one loop of the GIF.
+    frames: usize,
+    fps: u32,
+}
+
+fn animation_info(query: &str) -> Option<Cow<'static, [u8]>> {
+    let analysis = match animation(query) {
+        Ok(animation) => Analysis::Ok(AnimationInfo {
+            blocks: animation.blocks(),
+            frames: animation.frames(),
+            fps: animation.fps(),
+        }),
+        Err(err) => Analysis::Err {
+            error: err.to_string(),
+        },
+    };
+    match serde_json::to_vec(&analysis) {
+        Ok(json) => Some(Cow::Owned(json)),
+        Err(err) => {
+            log::error!("could not serialize animation info: {err}");
+            None
+        },
+    }
+}
+
+fn animation_gif(query: &str) -> Option<Cow<'static, [u8]>> {
+    match animation(query).and_then(|animation| Ok(animation.gif()?)) {
+        Ok(gif) => Some(Cow::Owned(gif)),
+        Err(err) => {
+            log::error!("could not render animation: {err}");
+            None
+        },
+    }
+}
+
+fn animation_png(query: &str) -> Option<Cow<'static, [u8]>> {
+    match animation(query).and_then(|animation| Ok(animation.apng()?)) {
+        Ok(apng) => Some(Cow::Owned(apng)),
+        Err(err) => {
+            log::error!("could not render animation: {err}");
+            None
+        },
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct FrameQuery {
+    /// The seed of the chunk, counting up without end in the full-window view.
+    frame: u32,
+}
+
+fn animation_frame(query: &str) -> Option<Cow<'static, [u8]>> {
+    let FrameQuery { frame } = provider::parse(query);
+    let symbol = match animation(query).and_then(|animation| Ok(animation.symbol(frame)?)) {
+        Ok(symbol) => symbol,
+        Err(err) => {
+            log::error!("could not render frame {frame}: {err}");
             return None;
         },
     };
-    let svg = qr
-        .render()
-        .min_dimensions(1000, 1000)
-        .light_color(svg::Color("#ffffff00"))
+    let svg = symbol
+        .render::<svg::Color<'_>>()
+        .min_dimensions(SVG_SIZE, SVG_SIZE)
         .build();
-    let pdf = svg2pdf::convert_str(&svg, svg2pdf::Options {
-        dpi: 300.0,
-        ..svg2pdf::Options::default()
+    Some(Cow::Owned(svg.into_bytes()))
+}
+
+#[derive(Serialize)]
+struct Compact {
+    url: String,
+    version_before: i16,
+    width_before: usize,
+    version_after: i16,
+    width_after: usize,
+}
+
+/// Proposes an upper-cased variant of the URL if it results in a smaller symbol.
+fn compact(query: &str) -> Option<Cow<'static, [u8]>> {
+    let query = Query::parse(query);
+    let url = compact_url(query.text()).filter(|_| provider::provider(&query.t).kind() == "url");
+    let compact = url.and_then(|url| {
+        let before = query.encode(&Payload::text(query.text())).ok()?.symbol;
+        let after = query.encode(&Payload::text(url.as_str())).ok()?.symbol;
+        (after.width() < before.width()).then(|| Compact {
+            url,
+            version_before: version_number(before.version()),
+            width_before: before.width(),
+            version_after: version_number(after.version()),
+            width_after: after.width(),
+        })
     });
-    let pdf = match pdf {
-        Ok(pdf) => pdf,
+    match serde_json::to_vec(&compact) {
+        Ok(json) => Some(Cow::Owned(json)),
         Err(err) => {
-            log::error!("could not convert qr code to {q:?}: {err}");
-            return None;
+            log::error!("could not serialize compact url: {err}");
+            None
         },
+    }
+}
+
+/// Warnings about the target of a URL, like lookalike host names.
+fn url_check(query: &str) -> Option<Cow<'static, [u8]>> {
+    let query = Query::parse(query);
+    let warnings: Vec<String> = match provider::provider(&query.t).kind() == "url" {
+        true => phishing::check(query.text())
+            .iter()
+            .map(Warning::message)
+            .collect(),
+        false => Vec::new(),
     };
-    Some(Cow::Owned(pdf))
+    match serde_json::to_vec(&warnings) {
+        Ok(json) => Some(Cow::Owned(json)),
+        Err(err) => {
+            log::error!("could not serialize url warnings: {err}");
+            None
+        },
+    }
 }
 
-fn png(query: &str) -> Option<Cow<'static, [u8]>> {
-    let Query { q } = serde_qs::from_str(query.trim()).ok().unwrap_or_default();
-    let q = match q.trim() {
-        "" => "https://www.vetmed.fu-berlin.de/",
-        q => q,
+#[derive(Serialize)]
+#[serde(untagged)]
+enum OtpSecret {
+    Ok { secret: String },
+    Err { error: String },
+}
+
+fn otp_secret(query: &str) -> Option<Cow<'static, [u8]>> {
+    let form: otp::Form = provider::form(query, "otp");
+    let secret = match otp::random_secret(form.algorithm) {
+        Ok(secret) => OtpSecret::Ok { secret },
+        Err(err) => OtpSecret::Err {
+            error: err.to_string(),
+        },
     };
-    let qr = match QrCode::with_error_correction_level(q.as_bytes(), EcLevel::L) {
-        Ok(qr) => qr,
+    match serde_json::to_vec(&secret) {
+        Ok(json) => Some(Cow::Owned(json)),
         Err(err) => {
-            log::error!("could not generate qr code for {q:?}: {err}");
-            return None;
+            log::error!("could not serialize OTP secret: {err}");
+            None
+        },
+    }
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum OtpCode {
+    Ok(otp::Code),
+    Err { error: String },
+}
+
+fn otp_code(query: &str) -> Option<Cow<'static, [u8]>> {
+    let form: otp::Form = provider::form(query, "otp");
+    let code = match form.code() {
+        Ok(code) => OtpCode::Ok(code),
+        Err(err) => OtpCode::Err {
+            error: err.to_string(),
         },
     };
-    let qr = qr
-        .render()
-        .min_dimensions(2480, 2480)
-        .light_color(LumaA([255_u8, 0_u8]))
-        .build();
-    let (height, width) = qr.dimensions();
-    let mut img = Vec::new();
-    let err = PngEncoder::new_with_quality(&mut img, CompressionType::Fast, FilterType::Up).encode(
-        qr.as_raw(),
-        width,
-        height,
-        ColorType::La8,
-    );
-    if let Err(err) = err {
-        log::error!("could not generate png for {q:?}: {err}");
+    match serde_json::to_vec(&code) {
+        Ok(json) => Some(Cow::Owned(json)),
+        Err(err) => {
+            log::error!("could not serialize OTP code: {err}");
+            None
+        },
+    }
+}
+
+/// Imported form values, or none if the file dialog was cancelled.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Import<T> {
+    Ok { items: Vec<T> },
+    Err { error: String },
+}
+
+/// Forms of the kind read from a file by its provider.
+fn import_form(query: &str) -> Option<Cow<'static, [u8]>> {
+    let provider = provider::provider(&Query::parse(query).t);
+    let Some(import) = provider.import() else {
+        log::error!("{} has no import", provider.kind());
         return None;
+    };
+    import_bytes(import.title, import.filter, |path, bytes| {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        (import.read)(&name, bytes)
+    })
+}
+
+#[derive(Serialize)]
+struct Restored {
+    /// The query string of the editor when the file was exported.
+    settings: String,
+}
+
+/// The editor settings embedded into a previously exported file.
+fn import_export() -> Option<Cow<'static, [u8]>> {
+    let filter: (&[&str], &str) = (&["*.svg", "*.pdf", "*.png"], "Exportierte Codes");
+    import_bytes(
+        "Export öffnen",
+        Some(filter),
+        |_, bytes| match metadata::read(bytes) {
+            Ok(settings) => Ok(vec![Restored { settings }]),
+            Err(err) => Err(err.to_string()),
+        },
+    )
+}
+
+#[derive(Serialize)]
+struct Scanned {
+    text: String,
+    version: i16,
+    ec_level: &'static str,
+    fnc1: bool,
+}
+
+/// The contents of all QR codes in an image file.
+fn import_image() -> Option<Cow<'static, [u8]>> {
+    let filter: (&[&str], &str) = (&["*.png", "*.jpg", "*.jpeg", "*.webp"], "Bilder");
+    import_bytes("Bild öffnen", Some(filter), |_, bytes| scan(bytes))
+}
+
+/// The contents of all QR codes in the image in the clipboard.
+fn import_clipboard() -> Option<Cow<'static, [u8]>> {
+    let import = match clipboard::image() {
+        Ok(bytes) => match scan(&bytes) {
+            Ok(items) => Import::Ok { items },
+            Err(error) => Import::Err { error },
+        },
+        Err(err) => {
+            log::warn!("could not read the clipboard: {err}");
+            Import::Err {
+                error: err.to_string(),
+            }
+        },
+    };
+    match serde_json::to_vec(&import) {
+        Ok(json) => Some(Cow::Owned(json)),
+        Err(err) => {
+            log::error!("could not serialize import: {err}");
+            None
+        },
+    }
+}
+
+#[derive(Serialize)]
+struct Received {
+    /// Where the recovered data was saved.
+    path: String,
+    len: usize,
+    #[serde(flatten)]
+    recovered: Recovered,
+}
+
+/// Puts together a transfer over many codes from an animated GIF or PNG, or from a directory of
+/// images, and saves the recovered data.
+fn import_frames() -> Option<Cow<'static, [u8]>> {
+    let filter: (&[&str], &str) = (
+        &["*.gif", "*.png", "*.jpg", "*.jpeg", "*.webp", "*.bmp"],
+        "Animierte GIFs und PNGs oder ein Bild der Folge",
+    );
+    let import = match open_file_dialog("Bildfolge öffnen", "", Some(filter)) {
+        None => Import::Ok { items: Vec::new() },
+        Some(path) => match frames::decode(Path::new(&path)) {
+            Ok(recovered) => save_received(Path::new(&path), recovered),
+            Err(err) => {
+                log::warn!("could not decode the frames of {path:?}: {err}");
+                Import::Err {
+                    error: err.to_string(),
+                }
+            },
+        },
+    };
+    match serde_json::to_vec(&import) {
+        Ok(json) => Some(Cow::Owned(json)),
+        Err(err) => {
+            log::error!("could not serialize import: {err}");
+            None
+        },
+    }
+}
+
+fn save_received(source: &Path, recovered: Recovered) -> Import<Received> {
+    let extension = match std::str::from_utf8(&recovered.data) {
+        Ok(_) => "txt",
+        Err(_) => "bin",
+    };
+    let is_gif = source
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("gif"));
+    let name = match is_gif {
+        true => source.file_stem(),
+        // named after the directory of the frames
+        false => source.parent().and_then(Path::file_name),
+    };
+    let name = format!("{}.{extension}", name.unwrap_or_default().to_string_lossy());
+    let default = dirs::download_dir().unwrap_or_default().join(name);
+    let Some(path) = save_file_dialog("Empfangene Daten speichern", &default.to_string_lossy())
+    else {
+        return Import::Ok { items: Vec::new() };
+    };
+    match fs::write(&path, &recovered.data) {
+        Ok(()) => Import::Ok {
+            items: vec![Received {
+                path,
+                len: recovered.data.len(),
+                recovered,
+            }],
+        },
+        Err(err) => {
+            log::error!("could not write {path:?}: {err}");
+            Import::Err {
+                error: format!("Die Datei konnte nicht gespeichert werden: {err}"),
+            }
+        },
+    }
+}
+
+fn scan(bytes: &[u8]) -> Result<Vec<Scanned>, String> {
+    let decoded = decode::decode_file(bytes).map_err(|err| err.to_string())?;
+    let scanned = decoded.into_iter().map(|decoded| Scanned {
+        text: decoded.text,
+        version: decoded.version,
+        ec_level: ec_level_name(decoded.ec_level),
+        fnc1: decoded.fnc1,
+    });
+    Ok(scanned.collect())
+}
+
+fn import_bytes<T: Serialize>(
+    title: &str,
+    filter: Option<(&[&str], &str)>,
+    parse: impl FnOnce(&Path, &[u8]) -> Result<Vec<T>, String>,
+) -> Option<Cow<'static, [u8]>> {
+    let import = match open_file_dialog(title, "", filter) {
+        None => Import::Ok { items: Vec::new() },
+        Some(path) => match fs::read(&path) {
+            Ok(bytes) => match parse(Path::new(&path), &bytes) {
+                Ok(items) => Import::Ok { items },
+                Err(error) => Import::Err { error },
+            },
+            Err(err) => {
+                log::error!("could not read {path:?}: {err}");
+                Import::Err {
+                    error: format!("Die Datei konnte nicht gelesen werden: {err}"),
+                }
+            },
+        },
+    };
+    match serde_json::to_vec(&import) {
+        Ok(json) => Some(Cow::Owned(json)),
+        Err(err) => {
+            log::error!("could not serialize import: {err}");
+            None
+        },
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct SignatureQuery {
+    generate: bool,
+    name: String,
+    key: String,
+    text: String,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum SignatureResult<T> {
+    Ok(T),
+    Err { error: String },
+}
+
+impl<T: Serialize> SignatureResult<T> {
+    fn json(result: Result<T, SignatureError>) -> Option<Cow<'static, [u8]>> {
+        let result = match result {
+            Ok(value) => Self::Ok(value),
+            Err(err) => {
+                log::error!("signature operation failed: {err}");
+                Self::Err {
+                    error: err.to_string(),
+                }
+            },
+        };
+        match serde_json::to_vec(&result) {
+            Ok(json) => Some(Cow::Owned(json)),
+            Err(err) => {
+                log::error!("could not serialize signature result: {err}");
+                None
+            },
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct SignatureKey {
+    public_key: Option<String>,
+    trusted_keys: Vec<String>,
+}
+
+/// The own public key, created on request, and the names of the trusted keys.
+fn signature_status(generate: bool) -> Result<SignatureKey, SignatureError> {
+    let public_key = signature::public_key(generate)?;
+    let config = crate::config::load()?;
+    let trusted_keys = config
+        .trusted_keys
+        .into_iter()
+        .map(|key| key.name)
+        .collect();
+    Ok(SignatureKey {
+        public_key,
+        trusted_keys,
+    })
+}
+
+fn signature_key(query: &str) -> Option<Cow<'static, [u8]>> {
+    let query = provider::parse::<SignatureQuery>(query);
+    SignatureResult::json(signature_status(query.generate))
+}
+
+fn signature_trust(query: &str) -> Option<Cow<'static, [u8]>> {
+    let query = provider::parse::<SignatureQuery>(query);
+    let result = signature::trust(&query.name, &query.key);
+    SignatureResult::json(result.and_then(|_| signature_status(false)))
+}
+
+fn signature_verify(query: &str) -> Option<Cow<'static, [u8]>> {
+    let query = provider::parse::<SignatureQuery>(query);
+    SignatureResult::json(signature::verify(&query.text))
+}
+
+fn version_number(version: Version) -> i16 {
+    match version {
+        Version::Normal(v) | Version::Micro(v) => v,
+    }
+}
+
+fn ec_level_name(ec_level: EcLevel) -> &'static str {
+    match ec_level {
+        EcLevel::L => "L",
+        EcLevel::M => "M",
+        EcLevel::Q => "Q",
+        EcLevel::H => "H",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secret_queries() {
+        assert!(is_secret(
+            "t=wifi&wifi%5Bssid%5D=Gast&wifi%5Bpassword%5D=geheim123"
+        ));
+        assert!(is_secret("q=&t=otp&otp%5Bsecret%5D=JBSWY3DPEHPK3PXP"));
+        assert!(!is_secret("t=url&q=https%3A%2F%2Fwww.fu-berlin.de%2F"));
+        assert!(!is_secret("q=t%3Dwifi"));
+        assert!(!is_secret(""));
+    }
+
+    #[test]
+    fn verifies_every_download() {
+        let verify = |query: &str| -> serde_json::Value {
+            serde_json::from_slice(&verification(query).unwrap()).unwrap()
+        };
+        let url = verify("t=url&q=https%3A%2F%2Fwww.fu-berlin.de%2F&print_width=20");
+        assert_eq!(url["png"]["text"], "https://www.fu-berlin.de/");
+        assert_eq!(url["svg"]["text"], "https://www.fu-berlin.de/");
+        assert!(url.get("batch").is_none() && url.get("animation").is_none());
+
+        let contacts = verify(
+            "t=contact&contact%5Bformat%5D=mecard&items%5B0%5D%5Bgiven%5D=Anna\
+             &items%5B1%5D%5Bgiven%5D=Ben",
+        );
+        assert_eq!(contacts["batch"]["text"], "2 Codes");
+
+        let file = verify("t=file&file%5Bname%5D=a.txt&file%5Bdata%5D=SGFsbG8gV2VsdA%3D%3D");
+        assert_eq!(file["animation"]["text"], "Blöcke: 1");
+    }
+
+    #[test]
+    fn signs_only_free_text() {
+        for query in [
+            "t=epc&sign=true&epc%5Bname%5D=Kasse&epc%5Biban%5D=DE02120300000000202051",
+            "t=wifi&sign=true&wifi%5Bssid%5D=Gast&wifi%5Bpassword%5D=geheim123",
+        ] {
+            assert!(
+                matches!(
+                    Query::parse(query).payload(),
+                    Err(PayloadError::Signature(SignatureError::Unsupported))
+                ),
+                "{query}"
+            );
+        }
+    }
+
+    #[test]
+    fn items_take_the_form() {
+        let query = Query::parse(
+            "t=contact&contact%5Bformat%5D=mecard&contact%5Bgiven%5D=Carla\
+             &items%5B0%5D%5Bgiven%5D=Anna&items%5B0%5D%5Bfamily%5D=Berg&items%5B1%5D%5Bgiven%5D=",
+        );
+        let items = query.items();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].0, "Anna Berg");
+        assert!(items[0].2.text.starts_with("MECARD:N:Berg,Anna;"));
+    }
+
+    #[test]
+    fn documents_of_the_kind() {
+        let swiss =
+            "t=swiss&swiss%5Biban%5D=CH4431999123000889012&swiss%5Bname%5D=Robert%20Schneider\
+                     &swiss%5Bzip%5D=2501&swiss%5Btown%5D=Biel\
+                     &swiss%5Breference%5D=210000000003139471430009017";
+        assert!(document(swiss).unwrap().starts_with(b"%PDF"));
+        assert!(document("t=url&q=x").is_none());
+        assert!(matches!(
+            animation("t=url&q=x"),
+            Err(TransferError::Unsupported)
+        ));
+    }
+
+    #[test]
+    fn cached_animation() {
+        let query = "t=file&file%5Bname%5D=a.txt&file%5Bdata%5D=SGFsbG8gV2VsdA%3D%3D";
+        let first = animation(query).unwrap();
+        for frame in ["frame=1", "frame=2&q=x"] {
+            let cached = animation(&format!("{query}&{frame}")).unwrap();
+            assert!(Arc::ptr_eq(&first, &cached));
+        }
+        let other = animation(&format!("{query}&file%5Bfps%5D=10")).unwrap();
+        assert!(!Arc::ptr_eq(&first, &other));
+        assert_eq!(other.fps(), 10);
     }
-    Some(Cow::Owned(img))
 }
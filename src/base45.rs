@@ -0,0 +1,61 @@
+//! Base45 (RFC 9285), which uses exactly the characters of the alphanumeric mode of QR codes.
+
+const ALPHABET: &[u8; 45] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+pub fn encode(data: &[u8]) -> String {
+    let mut text = String::with_capacity(data.len().div_ceil(2) * 3);
+    for chunk in data.chunks(2) {
+        let (mut value, digits) = match *chunk {
+            [a, b] => (usize::from(a) * 256 + usize::from(b), 3),
+            [a] => (usize::from(a), 2),
+            _ => unreachable!(),
+        };
+        for _ in 0..digits {
+            text.push(char::from(ALPHABET[value % 45]));
+            value /= 45;
+        }
+    }
+    text
+}
+
+pub fn decode(text: &str) -> Option<Vec<u8>> {
+    let digits: Vec<_> = text
+        .bytes()
+        .map(|c| ALPHABET.iter().position(|&a| a == c))
+        .collect::<Option<_>>()?;
+    let mut data = Vec::with_capacity(digits.len() / 3 * 2 + 1);
+    for chunk in digits.chunks(3) {
+        let value = chunk
+            .iter()
+            .rev()
+            .fold(0, |value, &digit| value * 45 + digit);
+        match chunk.len() {
+            3 if value <= 0xffff => data.extend_from_slice(&(value as u16).to_be_bytes()),
+            2 if value <= 0xff => data.push(value as u8),
+            _ => return None,
+        }
+    }
+    Some(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rfc_9285_examples() {
+        let examples: [(&[u8], &str); 4] = [
+            (b"AB", "BB8"),
+            (b"Hello!!", "%69 VD92EX0"),
+            (b"base-45", "UJCLQE7W581"),
+            (b"ietf!", "QED8WEX0"),
+        ];
+        for (data, text) in examples {
+            assert_eq!(encode(data), text);
+            assert_eq!(decode(text).unwrap(), data);
+        }
+        assert_eq!(decode("GGW"), None);
+        assert_eq!(decode("ZZZ"), None);
+        assert_eq!(decode("abc"), None);
+    }
+}
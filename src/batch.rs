@@ -0,0 +1,96 @@
+//! Many codes in one PDF, one per page with its caption below, e.g. for imported contacts.
+
+use pdf_writer::{Content, Finish, Name, PdfWriter, Rect, Ref, Str};
+use usvg::TreeParsing;
+
+use crate::helvetica::{text_width, win_ansi, wrap};
+use crate::metadata;
+
+/// Points per millimeter.
+const MM: f32 = 72.0 / 25.4;
+
+const MARGIN: f32 = 2.0;
+/// The size of a code including its quiet zone.
+const CODE_SIZE: f32 = 56.0;
+const WIDTH: f32 = CODE_SIZE + 2.0 * MARGIN;
+const HEIGHT: f32 = WIDTH + 12.0;
+
+const FONT_SIZE: f32 = 9.0;
+
+const FONT: Name<'static> = Name(b"F1");
+const CODE: Name<'static> = Name(b"C1");
+
+/// Lays out the captioned codes, whose SVGs must include the quiet zone, and embeds the editor
+/// settings and a description.
+pub fn pdf(
+    codes: &[(String, String)],
+    settings: &str,
+    description: &str,
+) -> Result<Vec<u8>, usvg::Error> {
+    let catalog_id = Ref::new(1);
+    let page_tree_id = Ref::new(2);
+    let font_id = Ref::new(3);
+    let metadata_id = Ref::new(4);
+
+    let mut writer = PdfWriter::new();
+    writer
+        .catalog(catalog_id)
+        .pages(page_tree_id)
+        .metadata(metadata_id);
+    let mut next_id = metadata::pdf(&mut writer, metadata_id, settings, description);
+    writer
+        .type1_font(font_id)
+        .base_font(Name(b"Helvetica"))
+        .encoding_predefined(Name(b"WinAnsiEncoding"));
+
+    let mut page_ids = Vec::with_capacity(codes.len());
+    for (caption, svg) in codes {
+        let tree = usvg::Tree::from_str(svg, &usvg::Options::default())?;
+        let page_id = next_id;
+        let content_id = Ref::new(page_id.get() + 1);
+        let code_id = Ref::new(page_id.get() + 2);
+        next_id =
+            svg2pdf::convert_tree_into(&tree, svg2pdf::Options::default(), &mut writer, code_id);
+        page_ids.push(page_id);
+
+        let mut page = writer.page(page_id);
+        page.media_box(Rect::new(0.0, 0.0, WIDTH * MM, HEIGHT * MM));
+        page.parent(page_tree_id);
+        page.contents(content_id);
+        let mut resources = page.resources();
+        resources.x_objects().pair(CODE, code_id);
+        resources.fonts().pair(FONT, font_id);
+        resources.finish();
+        page.finish();
+
+        let mut content = Content::new();
+        content
+            .save_state()
+            .transform([
+                CODE_SIZE * MM,
+                0.0,
+                0.0,
+                CODE_SIZE * MM,
+                MARGIN * MM,
+                (HEIGHT - MARGIN - CODE_SIZE) * MM,
+            ])
+            .x_object(CODE)
+            .restore_state();
+        let mut baseline = (HEIGHT - MARGIN - CODE_SIZE) * MM - FONT_SIZE;
+        for line in wrap(caption, CODE_SIZE * MM, FONT_SIZE).iter().take(2) {
+            let x = (WIDTH * MM - text_width(line, FONT_SIZE)) / 2.0;
+            content
+                .begin_text()
+                .set_font(FONT, FONT_SIZE)
+                .next_line(x.max(MARGIN * MM), baseline)
+                .show(Str(&win_ansi(line)))
+                .end_text();
+            baseline -= FONT_SIZE * 1.2;
+        }
+        writer.stream(content_id, &content.finish());
+    }
+
+    let count = page_ids.len() as i32;
+    writer.pages(page_tree_id).kids(page_ids).count(count);
+    Ok(writer.finish())
+}
@@ -0,0 +1,169 @@
+//! How many codewords of a QR code may be lost, and how many of them a graphic on top of the
+//! symbol already takes.
+
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use qrcode::EcLevel;
+use serde::Serialize;
+
+use crate::decode::{self, Codeword};
+use crate::overlay::Overlay;
+use crate::symbol::{Symbol, QUIET_ZONE};
+
+/// Fill colours of dark and light modules in the SVG.
+const FUNCTION: (&str, &str) = ("#000", "#fff");
+const DATA: (&str, &str) = ("#1d4e89", "#c5d8ee");
+const EC: (&str, &str) = ("#8a4b08", "#f2dcc0");
+const COVERED: (&str, &str) = ("#b00020", "#f4c3c9");
+
+#[derive(Debug, Serialize)]
+pub struct Block {
+    /// Number of data codewords.
+    pub data: usize,
+    /// Number of error correction codewords.
+    pub ec: usize,
+    /// Codewords that may be wrong without the block becoming unreadable.
+    pub correctable: usize,
+    /// Codewords that the overlay hides at least partly.
+    pub covered: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Budget {
+    pub blocks: Vec<Block>,
+    /// The codeword of each module, row by row.
+    #[serde(skip)]
+    layout: Vec<Option<Codeword>>,
+    #[serde(skip)]
+    covered: HashSet<Codeword>,
+}
+
+impl Budget {
+    pub fn new(symbol: &Symbol, overlay: Option<Overlay>) -> Self {
+        let width = symbol.width();
+        let version = (width - 17) / 4;
+        let ec_level = symbol.error_correction_level();
+        let layout = decode::layout(version, ec_level);
+        let covered: HashSet<Codeword> = match overlay {
+            Some(overlay) => layout
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| overlay.covers(width, i % width, i / width))
+                .filter_map(|(_, codeword)| *codeword)
+                .collect(),
+            None => HashSet::new(),
+        };
+        let (lens, ec_len) = decode::blocks(version, ec_level);
+        let correctable = (ec_len - misdecode_protection(version, ec_level)) / 2;
+        let blocks = lens
+            .into_iter()
+            .enumerate()
+            .map(|(block, data)| Block {
+                data,
+                ec: ec_len,
+                correctable,
+                covered: covered.iter().filter(|c| c.block == block).count(),
+            })
+            .collect();
+        Self {
+            blocks,
+            layout,
+            covered,
+        }
+    }
+
+    /// The symbol with data, error correction and covered codewords in different colours.
+    pub fn svg(&self, symbol: &Symbol) -> String {
+        let width = symbol.width();
+        let size = width + 2 * QUIET_ZONE as usize;
+        let mut paths: Vec<(&str, String)> = Vec::new();
+        for (i, codeword) in self.layout.iter().enumerate() {
+            let (x, y) = (i % width, i / width);
+            let (dark, light) = match codeword {
+                None => FUNCTION,
+                Some(codeword) if self.covered.contains(codeword) => COVERED,
+                Some(Codeword { ec: true, .. }) => EC,
+                Some(Codeword { ec: false, .. }) => DATA,
+            };
+            let fill = if symbol.is_dark(x, y) { dark } else { light };
+            let path = match paths.iter_mut().find(|(f, _)| *f == fill) {
+                Some((_, path)) => path,
+                None => {
+                    paths.push((fill, String::new()));
+                    &mut paths.last_mut().expect("just pushed").1
+                },
+            };
+            let (x, y) = (x + QUIET_ZONE as usize, y + QUIET_ZONE as usize);
+            let _ = write!(path, "M{x} {y}h1v1h-1z");
+        }
+
+        let mut svg = format!(
+            r##"<?xml version="1.0" standalone="yes"?><svg xmlns="http://www.w3.org/2000/svg" version="1.1" width="1000" height="1000" viewBox="0 0 {size} {size}" shape-rendering="crispEdges"><rect width="{size}" height="{size}" fill="#fff"/>"##
+        );
+        for (fill, path) in paths {
+            let _ = write!(svg, r#"<path fill="{fill}" d="{path}"/>"#);
+        }
+        svg.push_str("</svg>");
+        svg
+    }
+}
+
+/// Error correction codewords of the smallest symbols that only guard against misreading
+/// (ISO/IEC 18004, table 9).
+fn misdecode_protection(version: usize, ec_level: EcLevel) -> usize {
+    match (version, ec_level) {
+        (1, EcLevel::L) => 3,
+        (1, EcLevel::M) | (2, EcLevel::L) => 2,
+        (1, _) | (3, EcLevel::L) => 1,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use qrcode::Version;
+
+    use super::*;
+
+    fn symbol(version: i16, ec_level: EcLevel) -> Symbol {
+        let capacity = qrcode::bits::Bits::new(Version::Normal(version))
+            .max_len(ec_level)
+            .unwrap();
+        Symbol::new(
+            &vec![0b1110_1100; capacity / 8],
+            Version::Normal(version),
+            ec_level,
+            None,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn blocks_and_correction() {
+        let budget = Budget::new(&symbol(1, EcLevel::L), None);
+        assert_eq!(budget.blocks.len(), 1);
+        assert_eq!(budget.blocks[0].data, 19);
+        assert_eq!(budget.blocks[0].correctable, 2);
+        assert_eq!(budget.blocks[0].covered, 0);
+
+        let budget = Budget::new(&symbol(5, EcLevel::Q), None);
+        let lens: Vec<_> = budget.blocks.iter().map(|b| (b.data, b.ec)).collect();
+        assert_eq!(lens, [(15, 18), (15, 18), (16, 18), (16, 18)]);
+        assert!(budget.blocks.iter().all(|b| b.correctable == 9));
+    }
+
+    #[test]
+    fn overlay_covers_codewords() {
+        let symbol = symbol(10, EcLevel::M);
+        let budget = Budget::new(&symbol, Some(Overlay::SwissCross));
+        let covered: usize = budget.blocks.iter().map(|b| b.covered).sum();
+        assert!(covered > 0);
+        assert!(budget.blocks.iter().all(|b| b.covered <= b.correctable));
+
+        let svg = budget.svg(&symbol);
+        for (dark, light) in [FUNCTION, DATA, EC, COVERED] {
+            assert!(svg.contains(dark) && svg.contains(light));
+        }
+    }
+}
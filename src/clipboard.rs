@@ -0,0 +1,171 @@
+//! Reading images from the system clipboard, with the tools that come with the platform.
+
+use std::io;
+use std::process::{Command, Stdio};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ClipboardError {
+    #[error("Die Zwischenablage enthält kein Bild.")]
+    NoImage,
+    #[error("Zum Lesen der Zwischenablage wird das Programm „{0}“ benötigt.")]
+    MissingTool(&'static str),
+    #[error("Die Zwischenablage konnte nicht gelesen werden: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// The image in the clipboard as a PNG file.
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn image() -> Result<Vec<u8>, ClipboardError> {
+    match std::env::var_os("WAYLAND_DISPLAY") {
+        Some(_) => run("wl-paste", &["--no-newline", "--type", "image/png"]),
+        None => run("xclip", &[
+            "-selection",
+            "clipboard",
+            "-target",
+            "image/png",
+            "-out",
+        ]),
+    }
+}
+
+/// The image in the clipboard as a PNG file.
+#[cfg(target_os = "macos")]
+pub fn image() -> Result<Vec<u8>, ClipboardError> {
+    let output = run("osascript", &["-e", "the clipboard as «class PNGf»"])?;
+    parse_png_data(&output)
+}
+
+/// The image in the clipboard as a PNG file.
+#[cfg(windows)]
+pub fn image() -> Result<Vec<u8>, ClipboardError> {
+    // prints the PNG file in Base64, since the console would mangle binary data
+    let script = "Add-Type -AssemblyName System.Windows.Forms, System.Drawing; \
+                  $image = [System.Windows.Forms.Clipboard]::GetImage(); \
+                  if ($image -eq $null) { exit 1 }; \
+                  $stream = New-Object System.IO.MemoryStream; \
+                  $image.Save($stream, [System.Drawing.Imaging.ImageFormat]::Png); \
+                  [Console]::Out.Write([Convert]::ToBase64String($stream.ToArray()))";
+    let output = run("powershell", &[
+        "-NoProfile",
+        "-NonInteractive",
+        "-STA",
+        "-Command",
+        script,
+    ])?;
+    parse_base64(&output)
+}
+
+/// Parses the output of the PowerShell script, the PNG file in Base64.
+#[cfg(any(windows, test))]
+fn parse_base64(output: &[u8]) -> Result<Vec<u8>, ClipboardError> {
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+
+    STANDARD
+        .decode(output.trim_ascii())
+        .map_err(|_| ClipboardError::NoImage)
+}
+
+/// Parses the output of AppleScript for PNG data, which looks like `«data PNGf89504E47…»`.
+#[cfg(any(target_os = "macos", test))]
+fn parse_png_data(output: &[u8]) -> Result<Vec<u8>, ClipboardError> {
+    let output = String::from_utf8_lossy(output);
+    let hex = output
+        .trim()
+        .strip_prefix("«data PNGf")
+        .and_then(|hex| hex.strip_suffix('»'))
+        .ok_or(ClipboardError::NoImage)?;
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            hex.get(i..i + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+        })
+        .collect::<Option<_>>()
+        .ok_or(ClipboardError::NoImage)
+}
+
+/// Runs a program and returns its output.
+fn run(program: &'static str, args: &[&str]) -> Result<Vec<u8>, ClipboardError> {
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null());
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+
+        // no console window flashing up
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+    let output = match command.output() {
+        Ok(output) => output,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            return Err(ClipboardError::MissingTool(program));
+        },
+        Err(err) => return Err(err.into()),
+    };
+    log::debug!("{program} exited with {}", output.status);
+    image_output(output.status.success(), output.stdout)
+}
+
+/// The tools fail, or print nothing, if the clipboard holds no image.
+fn image_output(success: bool, stdout: Vec<u8>) -> Result<Vec<u8>, ClipboardError> {
+    match success && !stdout.is_empty() {
+        true => Ok(stdout),
+        false => Err(ClipboardError::NoImage),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apple_script_data() {
+        let output = "«data PNGf89504E470d0a1A0A»\n".as_bytes();
+        assert_eq!(parse_png_data(output).unwrap(), b"\x89PNG\r\n\x1a\n");
+        for output in [
+            "",
+            "Hallo Welt",
+            "«data TIFF4D4D002A»",
+            "«data PNGf89504»",
+            "«data PNGf89504E47",
+            "«data PNGf8950XY»",
+            "«data PNGf89äö»",
+        ] {
+            assert!(
+                matches!(
+                    parse_png_data(output.as_bytes()),
+                    Err(ClipboardError::NoImage)
+                ),
+                "{output}"
+            );
+        }
+    }
+
+    #[test]
+    fn powershell_output() {
+        assert_eq!(
+            parse_base64(b"iVBORw0KGgo=\r\n").unwrap(),
+            b"\x89PNG\r\n\x1a\n"
+        );
+        assert!(matches!(
+            parse_base64(b"Ausnahme: kein Bild"),
+            Err(ClipboardError::NoImage)
+        ));
+    }
+
+    #[test]
+    fn exit_status() {
+        assert_eq!(image_output(true, b"PNG".to_vec()).unwrap(), b"PNG");
+        for (success, stdout) in [(false, b"PNG".to_vec()), (false, vec![]), (true, vec![])] {
+            assert!(matches!(
+                image_output(success, stdout),
+                Err(ClipboardError::NoImage)
+            ));
+        }
+    }
+}
@@ -0,0 +1,124 @@
+/// URI schemes without an authority, which are recognized without a following `//`.
+const SCHEMES: &[&str] = &[
+    "bitcoin",
+    "data",
+    "geo",
+    "javascript",
+    "lightning",
+    "mailto",
+    "news",
+    "sms",
+    "tel",
+    "urn",
+];
+
+/// Upper-cases the case-insensitive parts of a URL, so that more of it fits alphanumeric mode.
+///
+/// Only the scheme, an ASCII host and the hex digits of percent-encoded octets are changed. The
+/// result is equivalent to the input according to RFC 3986, section 6.2.2.1. Returns `None` if
+/// the input is not a URL or would not change.
+pub fn compact_url(url: &str) -> Option<String> {
+    let (scheme, rest) = split_scheme(url)?;
+
+    let mut result = String::with_capacity(url.len());
+    result.push_str(&scheme.to_ascii_uppercase());
+    result.push(':');
+
+    let rest = match rest.strip_prefix("//") {
+        Some(rest) => {
+            let end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+            let (authority, rest) = rest.split_at(end);
+            let (userinfo, host) = match authority.rsplit_once('@') {
+                Some((userinfo, host)) => (Some(userinfo), host),
+                None => (None, authority),
+            };
+
+            result.push_str("//");
+            if let Some(userinfo) = userinfo {
+                push_percent_encoded(&mut result, userinfo);
+                result.push('@');
+            }
+            match host.is_ascii() {
+                true => result.push_str(&host.to_ascii_uppercase()),
+                false => result.push_str(host),
+            }
+            rest
+        },
+        None => rest,
+    };
+    push_percent_encoded(&mut result, rest);
+
+    (result != url).then_some(result)
+}
+
+/// Splits a URL into its scheme and the rest.
+///
+/// Text before a colon only counts as scheme if `//` follows or it is one of [`SCHEMES`], so
+/// that plain text like `Hinweis: bitte hier klicken` is no URL.
+pub fn split_scheme(url: &str) -> Option<(&str, &str)> {
+    let (scheme, rest) = url.split_once(':')?;
+    let known = SCHEMES
+        .iter()
+        .any(|known| scheme.eq_ignore_ascii_case(known));
+    (is_scheme(scheme) && (known || rest.starts_with("//"))).then_some((scheme, rest))
+}
+
+fn is_scheme(scheme: &str) -> bool {
+    let mut chars = scheme.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+}
+
+/// Copies `s` verbatim, except for the hex digits of percent-encoded octets, which are upper-cased.
+fn push_percent_encoded(result: &mut String, mut s: &str) {
+    while let Some(index) = s.find('%') {
+        let (head, tail) = s.split_at(index + 1);
+        result.push_str(head);
+        s = tail;
+
+        let hex = tail.as_bytes();
+        if hex.len() >= 2 && hex[0].is_ascii_hexdigit() && hex[1].is_ascii_hexdigit() {
+            result.push_str(&tail[..2].to_ascii_uppercase());
+            s = &tail[2..];
+        }
+    }
+    result.push_str(s);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compacts_urls() {
+        assert_eq!(
+            compact_url("https://www.fu-berlin.de/%c3%a4?q=%7e").as_deref(),
+            Some("HTTPS://WWW.FU-BERLIN.DE/%C3%A4?q=%7E"),
+        );
+        assert_eq!(
+            compact_url("http://user%3a@bücher.de/").as_deref(),
+            Some("HTTP://user%3A@bücher.de/"),
+        );
+        assert_eq!(
+            compact_url("mailto:info@fu-berlin.de").as_deref(),
+            Some("MAILTO:info@fu-berlin.de"),
+        );
+        assert_eq!(compact_url("HTTPS://WWW.FU-BERLIN.DE/"), None);
+    }
+
+    #[test]
+    fn keeps_plain_text() {
+        for text in [
+            "Hinweis: bitte hier klicken",
+            "Raum: 101",
+            "WIFI:S:Gast;P:%ab;;",
+            "Kein Doppelpunkt",
+            "1a://example.com",
+        ] {
+            assert_eq!(compact_url(text), None, "{text}");
+        }
+        assert_eq!(split_scheme("Raum: 101"), None);
+        assert_eq!(split_scheme("Tel:+49301234"), Some(("Tel", "+49301234")));
+        assert_eq!(split_scheme("ftp://x"), Some(("ftp", "//x")));
+    }
+}
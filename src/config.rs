@@ -0,0 +1,73 @@
+//! Settings that outlive a session, stored as JSON in the configuration directory of the user.
+
+use std::io::Write as _;
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
+use std::{fs, io};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("Das Konfigurationsverzeichnis konnte nicht ermittelt werden.")]
+    NoConfigDir,
+    #[error("Die Konfiguration {0:?} konnte nicht gelesen werden.")]
+    Read(PathBuf, #[source] io::Error),
+    #[error("Die Konfiguration {0:?} ist ungültig: {1}")]
+    Parse(PathBuf, #[source] serde_json::Error),
+    #[error("Die Konfiguration {0:?} konnte nicht gespeichert werden.")]
+    Write(PathBuf, #[source] io::Error),
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Config {
+    /// The Base64url encoded seed of the Ed25519 key that signs payloads.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signing_key: Option<String>,
+    /// Public keys whose signatures are accepted, besides the own one.
+    pub trusted_keys: Vec<TrustedKey>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TrustedKey {
+    pub name: String,
+    /// The Base64url encoded Ed25519 public key.
+    pub key: String,
+}
+
+/// The config file, e.g. `~/.config/qr-code/config.json` on Linux.
+pub fn path() -> Result<PathBuf, ConfigError> {
+    let dir = dirs::config_dir().ok_or(ConfigError::NoConfigDir)?;
+    Ok(dir.join("qr-code").join("config.json"))
+}
+
+/// Reads the configuration, which is empty if it was never saved.
+pub fn load() -> Result<Config, ConfigError> {
+    let path = path()?;
+    match fs::read(&path) {
+        Ok(json) => serde_json::from_slice(&json).map_err(|err| ConfigError::Parse(path, err)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+        Err(err) => Err(ConfigError::Read(path, err)),
+    }
+}
+
+pub fn save(config: &Config) -> Result<(), ConfigError> {
+    let path = path()?;
+    let json =
+        serde_json::to_vec_pretty(config).map_err(|err| ConfigError::Parse(path.clone(), err))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|err| ConfigError::Write(path.clone(), err))?;
+    }
+    log::info!("saving config to {path:?}");
+    // the signing key must stay private
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    let result = options
+        .open(&path)
+        .and_then(|mut file| file.write_all(&json));
+    result.map_err(|err| ConfigError::Write(path, err))
+}
@@ -0,0 +1,509 @@
+//! A minimal Data Matrix (ECC 200) encoder for square symbols, as used for GS1 Data Matrix.
+
+use qrcode::render::{Pixel, Renderer};
+use qrcode::Color;
+
+/// Codeword for FNC1, which marks GS1 data when first and separates variable length fields.
+const FNC1: u8 = 232;
+
+/// Codeword to pad unused data capacity.
+const PAD: u8 = 129;
+
+/// Group separator, the textual representation of FNC1 in GS1 element strings.
+pub const GS: char = '\x1d';
+
+/// Width of the quiet zone in modules.
+pub const QUIET_ZONE: u32 = 1;
+
+struct Size {
+    /// Modules per side, including the finder patterns.
+    width: usize,
+    /// Modules per side of one data region.
+    region: usize,
+    data: usize,
+    ecc: usize,
+    blocks: usize,
+}
+
+#[rustfmt::skip]
+const SIZES: [Size; 23] = [
+    Size { width: 10, region: 8, data: 3, ecc: 5, blocks: 1 },
+    Size { width: 12, region: 10, data: 5, ecc: 7, blocks: 1 },
+    Size { width: 14, region: 12, data: 8, ecc: 10, blocks: 1 },
+    Size { width: 16, region: 14, data: 12, ecc: 12, blocks: 1 },
+    Size { width: 18, region: 16, data: 18, ecc: 14, blocks: 1 },
+    Size { width: 20, region: 18, data: 22, ecc: 18, blocks: 1 },
+    Size { width: 22, region: 20, data: 30, ecc: 20, blocks: 1 },
+    Size { width: 24, region: 22, data: 36, ecc: 24, blocks: 1 },
+    Size { width: 26, region: 24, data: 44, ecc: 28, blocks: 1 },
+    Size { width: 32, region: 14, data: 62, ecc: 36, blocks: 1 },
+    Size { width: 36, region: 16, data: 86, ecc: 42, blocks: 1 },
+    Size { width: 40, region: 18, data: 114, ecc: 48, blocks: 1 },
+    Size { width: 44, region: 20, data: 144, ecc: 56, blocks: 1 },
+    Size { width: 48, region: 22, data: 174, ecc: 68, blocks: 1 },
+    Size { width: 52, region: 24, data: 204, ecc: 84, blocks: 2 },
+    Size { width: 64, region: 14, data: 280, ecc: 112, blocks: 2 },
+    Size { width: 72, region: 16, data: 368, ecc: 144, blocks: 4 },
+    Size { width: 80, region: 18, data: 456, ecc: 192, blocks: 4 },
+    Size { width: 88, region: 20, data: 576, ecc: 224, blocks: 4 },
+    Size { width: 96, region: 22, data: 696, ecc: 272, blocks: 4 },
+    Size { width: 104, region: 24, data: 816, ecc: 336, blocks: 6 },
+    Size { width: 120, region: 18, data: 1050, ecc: 408, blocks: 6 },
+    Size { width: 132, region: 20, data: 1304, ecc: 496, blocks: 8 },
+];
+
+#[derive(Debug, thiserror::Error)]
+pub enum DataMatrixError {
+    #[error("Die Daten sind zu lang für einen Data-Matrix-Code.")]
+    DataTooLong,
+    #[error("Das Zeichen {0:?} kann nicht in einem GS1-Data-Matrix-Code stehen.")]
+    InvalidCharacter(char),
+}
+
+#[derive(Debug, Clone)]
+pub struct DataMatrix {
+    content: Vec<Color>,
+    width: usize,
+    /// Number of used data codewords, without padding.
+    pub codewords: usize,
+    /// Number of available data codewords in the chosen size.
+    pub capacity: usize,
+}
+
+impl DataMatrix {
+    /// Encodes a GS1 element string, with [`GS`] separating variable length fields.
+    pub fn gs1(text: &str) -> Result<Self, DataMatrixError> {
+        let mut codewords = vec![FNC1];
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match (c, chars.peek()) {
+                ('0'..='9', Some(&d @ '0'..='9')) => {
+                    chars.next();
+                    let value = (c as u8 - b'0') * 10 + (d as u8 - b'0');
+                    codewords.push(130 + value);
+                },
+                (GS, _) => codewords.push(FNC1),
+                (c, _) if c.is_ascii() => codewords.push(c as u8 + 1),
+                (c, _) => return Err(DataMatrixError::InvalidCharacter(c)),
+            }
+        }
+        Self::new(codewords)
+    }
+
+    fn new(mut codewords: Vec<u8>) -> Result<Self, DataMatrixError> {
+        let used = codewords.len();
+        let size = SIZES
+            .iter()
+            .find(|size| size.data >= used)
+            .ok_or(DataMatrixError::DataTooLong)?;
+
+        for position in used + 1..=size.data {
+            codewords.push(match position == used + 1 {
+                true => PAD,
+                false => randomized_pad(position),
+            });
+        }
+        let codewords = add_ecc(&codewords, size);
+
+        let mapping = size.width / (size.region + 2) * size.region;
+        let placement = placement(mapping, mapping);
+        let regions = size.width / (size.region + 2);
+        let block = size.region + 2;
+        let mut content = Vec::with_capacity(size.width * size.width);
+        for y in 0..size.width {
+            for x in 0..size.width {
+                let (row, col) = (y % block, x % block);
+                let dark = if col == 0 || row == block - 1 {
+                    true
+                } else if row == 0 {
+                    col % 2 == 0
+                } else if col == block - 1 {
+                    row % 2 == 1
+                } else {
+                    let row = y / block * size.region + row - 1;
+                    let col = x / block * size.region + col - 1;
+                    match placement[row * mapping + col] {
+                        Place::Dark => true,
+                        Place::Light => false,
+                        Place::Bit(index, bit) => codewords[index] & (0x80 >> bit) != 0,
+                    }
+                };
+                content.push(match dark {
+                    true => Color::Dark,
+                    false => Color::Light,
+                });
+            }
+        }
+        debug_assert_eq!(regions * block, size.width);
+
+        Ok(Self {
+            content,
+            width: size.width,
+            codewords: used,
+            capacity: size.data,
+        })
+    }
+
+    /// Number of modules per side, without the quiet zone.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn render<P: Pixel>(&self) -> Renderer<'_, P> {
+        Renderer::new(&self.content, self.width, QUIET_ZONE)
+    }
+}
+
+/// The pseudo-random pad codewords after the first one, "253-state algorithm".
+fn randomized_pad(position: usize) -> u8 {
+    let value = usize::from(PAD) + (149 * position) % 253 + 1;
+    match value {
+        0..=254 => value as u8,
+        _ => (value - 254) as u8,
+    }
+}
+
+/// Computes and interleaves the Reed-Solomon error correction codewords.
+fn add_ecc(data: &[u8], size: &Size) -> Vec<u8> {
+    let ecc_len = size.ecc / size.blocks;
+    let generator = generator(ecc_len);
+
+    let mut result = data.to_vec();
+    result.resize(size.data + size.ecc, 0);
+    for block in 0..size.blocks {
+        let block_data = data
+            .iter()
+            .skip(block)
+            .step_by(size.blocks)
+            .copied()
+            .collect::<Vec<_>>();
+        let mut remainder = vec![0; ecc_len];
+        for &byte in &block_data {
+            let factor = byte ^ remainder[0];
+            remainder.rotate_left(1);
+            remainder[ecc_len - 1] = 0;
+            for (r, &g) in remainder.iter_mut().zip(&generator) {
+                *r ^= gf_mul(g, factor);
+            }
+        }
+        for (index, byte) in remainder.into_iter().enumerate() {
+            result[size.data + block + index * size.blocks] = byte;
+        }
+    }
+    result
+}
+
+/// Coefficients of the generator polynomial with roots α¹ … αⁿ, without the leading 1.
+fn generator(len: usize) -> Vec<u8> {
+    let mut poly = vec![1_u8];
+    let mut root = 1_u8;
+    for _ in 0..len {
+        root = gf_mul(root, 2);
+        let mut next = vec![0; poly.len() + 1];
+        for (index, &coeff) in poly.iter().enumerate() {
+            next[index] ^= coeff;
+            next[index + 1] ^= gf_mul(coeff, root);
+        }
+        poly = next;
+    }
+    poly.remove(0);
+    poly
+}
+
+/// Multiplication in GF(256) with the Data Matrix polynomial x⁸ + x⁵ + x³ + x² + 1.
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut result = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            result ^= a;
+        }
+        let carry = a & 0x80 != 0;
+        a <<= 1;
+        if carry {
+            a ^= 0x2d;
+        }
+        b >>= 1;
+    }
+    result
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Place {
+    Light,
+    Dark,
+    /// Bit (0 = most significant) of a codeword.
+    Bit(usize, u8),
+}
+
+/// The ECC 200 placement of codeword bits in the mapping matrix, ISO/IEC 16022 annex F.
+fn placement(nrow: usize, ncol: usize) -> Vec<Place> {
+    let mut placement = Placement {
+        nrow: nrow as isize,
+        ncol: ncol as isize,
+        array: vec![None; nrow * ncol],
+    };
+    placement.run();
+    placement
+        .array
+        .into_iter()
+        .map(|place| place.unwrap_or(Place::Light))
+        .collect()
+}
+
+struct Placement {
+    nrow: isize,
+    ncol: isize,
+    array: Vec<Option<Place>>,
+}
+
+impl Placement {
+    fn run(&mut self) {
+        let (nrow, ncol) = (self.nrow, self.ncol);
+        let mut index = 0;
+        let (mut row, mut col) = (4, 0);
+        loop {
+            if row == nrow && col == 0 {
+                self.corner(index, &CORNER1);
+                index += 1;
+            }
+            if row == nrow - 2 && col == 0 && ncol % 4 != 0 {
+                self.corner(index, &CORNER2);
+                index += 1;
+            }
+            if row == nrow - 2 && col == 0 && ncol % 8 == 4 {
+                self.corner(index, &CORNER3);
+                index += 1;
+            }
+            if row == nrow + 4 && col == 2 && ncol % 8 == 0 {
+                self.corner(index, &CORNER4);
+                index += 1;
+            }
+
+            loop {
+                if row < nrow && col >= 0 && self.is_empty(row, col) {
+                    self.utah(row, col, index);
+                    index += 1;
+                }
+                row -= 2;
+                col += 2;
+                if row < 0 || col >= ncol {
+                    break;
+                }
+            }
+            row += 1;
+            col += 3;
+
+            loop {
+                if row >= 0 && col < ncol && self.is_empty(row, col) {
+                    self.utah(row, col, index);
+                    index += 1;
+                }
+                row += 2;
+                col -= 2;
+                if row >= nrow || col < 0 {
+                    break;
+                }
+            }
+            row += 3;
+            col += 1;
+
+            if row >= nrow && col >= ncol {
+                break;
+            }
+        }
+
+        if self.is_empty(nrow - 1, ncol - 1) {
+            self.set(nrow - 1, ncol - 1, Place::Dark);
+            self.set(nrow - 2, ncol - 2, Place::Dark);
+        }
+    }
+
+    fn is_empty(&self, row: isize, col: isize) -> bool {
+        self.array[(row * self.ncol + col) as usize].is_none()
+    }
+
+    fn set(&mut self, row: isize, col: isize, place: Place) {
+        self.array[(row * self.ncol + col) as usize] = Some(place);
+    }
+
+    fn module(&mut self, mut row: isize, mut col: isize, index: usize, bit: u8) {
+        if row < 0 {
+            row += self.nrow;
+            col += 4 - (self.nrow + 4) % 8;
+        }
+        if col < 0 {
+            col += self.ncol;
+            row += 4 - (self.ncol + 4) % 8;
+        }
+        self.set(row, col, Place::Bit(index, bit));
+    }
+
+    fn utah(&mut self, row: isize, col: isize, index: usize) {
+        for (bit, (dr, dc)) in UTAH.into_iter().enumerate() {
+            self.module(row + dr, col + dc, index, bit as u8);
+        }
+    }
+
+    fn corner(&mut self, index: usize, corner: &[(isize, isize); 8]) {
+        for (bit, &(row, col)) in corner.iter().enumerate() {
+            let row = if row < 0 { self.nrow + row } else { row };
+            let col = if col < 0 { self.ncol + col } else { col };
+            self.module(row, col, index, bit as u8);
+        }
+    }
+}
+
+/// Offsets of the bits in the regular, "utah"-shaped codeword.
+const UTAH: [(isize, isize); 8] = [
+    (-2, -2),
+    (-2, -1),
+    (-1, -2),
+    (-1, -1),
+    (-1, 0),
+    (0, -2),
+    (0, -1),
+    (0, 0),
+];
+
+/// Positions of the bits in the corner cases, negative values count from the end.
+const CORNER1: [(isize, isize); 8] = [
+    (-1, 0),
+    (-1, 1),
+    (-1, 2),
+    (0, -2),
+    (0, -1),
+    (1, -1),
+    (2, -1),
+    (3, -1),
+];
+const CORNER2: [(isize, isize); 8] = [
+    (-3, 0),
+    (-2, 0),
+    (-1, 0),
+    (0, -4),
+    (0, -3),
+    (0, -2),
+    (0, -1),
+    (1, -1),
+];
+const CORNER3: [(isize, isize); 8] = [
+    (-3, 0),
+    (-2, 0),
+    (-1, 0),
+    (0, -2),
+    (0, -1),
+    (1, -1),
+    (2, -1),
+    (3, -1),
+];
+const CORNER4: [(isize, isize); 8] = [
+    (-1, 0),
+    (-1, -1),
+    (0, -3),
+    (0, -2),
+    (0, -1),
+    (1, -3),
+    (1, -2),
+    (1, -1),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The value of the codeword polynomial at αⁱ, which is 0 for i = 1 … n if the n error
+    /// correction codewords are right.
+    fn syndrome(codewords: &[u8], i: usize) -> u8 {
+        let root = (0..i).fold(1, |acc, _| gf_mul(acc, 2));
+        codewords
+            .iter()
+            .fold(0, |acc, &codeword| gf_mul(acc, root) ^ codeword)
+    }
+
+    #[test]
+    fn error_correction() {
+        // ISO/IEC 16022, annex O: "123456" in a symbol of 10×10 modules
+        assert_eq!(add_ecc(&[142, 164, 186], &SIZES[0]), [
+            142, 164, 186, 114, 25, 5, 88, 102
+        ]);
+
+        // symbols of 52×52 modules and larger interleave their blocks
+        for size in SIZES.iter().filter(|size| size.blocks > 1) {
+            let data: Vec<u8> = (0..size.data).map(|i| (i * 37 % 256) as u8).collect();
+            let codewords = add_ecc(&data, size);
+            assert_eq!(codewords[..size.data], data);
+            for block in 0..size.blocks {
+                let block: Vec<u8> = codewords
+                    .iter()
+                    .skip(block)
+                    .step_by(size.blocks)
+                    .copied()
+                    .collect();
+                assert_eq!(block.len(), (size.data + size.ecc) / size.blocks);
+                for i in 1..=size.ecc / size.blocks {
+                    assert_eq!(syndrome(&block, i), 0, "{}×{}", size.width, size.width);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn pads() {
+        // ISO/IEC 16022, 5.2.3: the pad codewords after the first one depend on their position
+        assert_eq!(randomized_pad(2), 175);
+        assert_eq!(randomized_pad(3), 70);
+        assert!((2..2000)
+            .map(randomized_pad)
+            .all(|pad| (1..=254).contains(&pad)));
+    }
+
+    #[test]
+    fn places_every_bit_once() {
+        for size in &SIZES {
+            let mapping = size.width / (size.region + 2) * size.region;
+            let codewords = size.data + size.ecc;
+            let placement = placement(mapping, mapping);
+            let mut bits: Vec<_> = placement
+                .iter()
+                .filter_map(|place| match *place {
+                    Place::Bit(index, bit) => Some(index * 8 + usize::from(bit)),
+                    _ => None,
+                })
+                .collect();
+            bits.sort_unstable();
+            assert!(bits.iter().copied().eq(0..8 * codewords), "{}", size.width);
+            // only the fixed pattern in the bottom right corner of some sizes is left over
+            let fixed = mapping * mapping - bits.len();
+            assert!(fixed == 0 || fixed == 4, "{}", size.width);
+        }
+
+        // the least significant bit of the first codeword is in row 4, column 0
+        assert_eq!(placement(8, 8)[4 * 8], Place::Bit(0, 7));
+    }
+
+    #[test]
+    fn gs1() {
+        let symbol = DataMatrix::gs1("0104012345678901\x1d10ABC").unwrap();
+        // FNC1, 8 digit pairs, FNC1, and "10ABC" as one pair and 3 characters
+        assert_eq!(
+            (symbol.codewords, symbol.capacity, symbol.width()),
+            (14, 18, 18)
+        );
+        assert!(matches!(
+            DataMatrix::gs1("Straße"),
+            Err(DataMatrixError::InvalidCharacter('ß'))
+        ));
+        assert!(matches!(
+            DataMatrix::gs1(&"1".repeat(3000)),
+            Err(DataMatrixError::DataTooLong)
+        ));
+
+        // the finder and timing patterns of the two data regions per side
+        let symbol = DataMatrix::gs1(&"1".repeat(100)).unwrap();
+        assert_eq!(symbol.width(), 32);
+        let dark = |x: usize, y: usize| symbol.content[y * 32 + x] == Color::Dark;
+        assert!((0..32).all(|i| dark(0, i) && dark(16, i) && dark(i, 31) && dark(i, 15)));
+        assert!((0..32).all(|i| dark(i, 0) == (i % 16 % 2 == 0)));
+        assert!((0..32).all(|i| dark(31, i) == (i % 16 % 2 == 1)));
+    }
+}
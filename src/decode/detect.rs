@@ -0,0 +1,700 @@
+//! Locating QR codes in images: adaptive binarization, finder patterns, an alignment pattern and
+//! a perspective transform to sample the modules, roughly as the ZXing library does it.
+
+use image::GrayImage;
+
+use super::matrix::Matrix;
+use super::perspective::Perspective;
+use super::{decode_matrix, DecodeError, Decoded};
+
+/// Candidates of finder patterns per image, the most often seen first.
+const MAX_FINDERS: usize = 40;
+/// Triples of finder patterns tried per image, the most plausible first.
+const MAX_ATTEMPTS: usize = 40;
+
+/// Dark modules as `true`.
+struct Bitmap {
+    width: usize,
+    height: usize,
+    dark: Vec<bool>,
+}
+
+impl Bitmap {
+    /// Thresholds each 8×8 block at the mean of the 5×5 blocks around it, which copes with
+    /// shadows and gradients in photos.
+    fn binarize(image: &GrayImage) -> Self {
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        let luma = |x: usize, y: usize| image.get_pixel(x as u32, y as u32).0[0];
+        if width < 40 || height < 40 {
+            let (min, max) = image
+                .pixels()
+                .fold((255, 0), |(min, max), p| (p.0[0].min(min), p.0[0].max(max)));
+            let threshold = (u16::from(min) + u16::from(max)) / 2;
+            let dark = image
+                .pixels()
+                .map(|p| u16::from(p.0[0]) <= threshold)
+                .collect();
+            return Self {
+                width,
+                height,
+                dark,
+            };
+        }
+
+        const BLOCK: usize = 8;
+        let (blocks_x, blocks_y) = (width.div_ceil(BLOCK), height.div_ceil(BLOCK));
+        let offset = |block: usize, len: usize| (block * BLOCK).min(len - BLOCK);
+        let mut black_points = vec![0_u32; blocks_x * blocks_y];
+        for by in 0..blocks_y {
+            for bx in 0..blocks_x {
+                let (x0, y0) = (offset(bx, width), offset(by, height));
+                let (mut sum, mut min, mut max) = (0_u32, 255_u8, 0_u8);
+                for y in y0..y0 + BLOCK {
+                    for x in x0..x0 + BLOCK {
+                        let l = luma(x, y);
+                        sum += u32::from(l);
+                        (min, max) = (min.min(l), max.max(l));
+                    }
+                }
+                let mut average = sum / (BLOCK * BLOCK) as u32;
+                if max - min <= 24 {
+                    // a flat block: assume it is light unless its neighbours say otherwise
+                    average = u32::from(min) / 2;
+                    if bx > 0 && by > 0 {
+                        let neighbours = (black_points[(by - 1) * blocks_x + bx]
+                            + 2 * black_points[by * blocks_x + bx - 1]
+                            + black_points[(by - 1) * blocks_x + bx - 1])
+                            / 4;
+                        if u32::from(min) < neighbours {
+                            average = neighbours;
+                        }
+                    }
+                }
+                black_points[by * blocks_x + bx] = average;
+            }
+        }
+
+        let mut dark = vec![false; width * height];
+        for by in 0..blocks_y {
+            for bx in 0..blocks_x {
+                let cy = by.clamp(2, blocks_y.saturating_sub(3).max(2));
+                let cx = bx.clamp(2, blocks_x.saturating_sub(3).max(2));
+                let (mut sum, mut count) = (0, 0);
+                for ny in cy.saturating_sub(2)..(cy + 3).min(blocks_y) {
+                    for nx in cx.saturating_sub(2)..(cx + 3).min(blocks_x) {
+                        sum += black_points[ny * blocks_x + nx];
+                        count += 1;
+                    }
+                }
+                let threshold = sum / count;
+                let (x0, y0) = (offset(bx, width), offset(by, height));
+                for y in y0..y0 + BLOCK {
+                    for x in x0..x0 + BLOCK {
+                        dark[y * width + x] = u32::from(luma(x, y)) <= threshold;
+                    }
+                }
+            }
+        }
+        Self {
+            width,
+            height,
+            dark,
+        }
+    }
+
+    fn get(&self, x: usize, y: usize) -> bool {
+        self.dark[y * self.width + x]
+    }
+
+    /// The module at a point, or light outside of the image.
+    fn at(&self, (x, y): (f64, f64)) -> bool {
+        let inside = x >= 0.0 && y >= 0.0 && x < self.width as f64 && y < self.height as f64;
+        inside && self.get(x as usize, y as usize)
+    }
+
+    fn inverted(&self) -> Self {
+        Self {
+            width: self.width,
+            height: self.height,
+            dark: self.dark.iter().map(|&dark| !dark).collect(),
+        }
+    }
+}
+
+/// The centre of a finder pattern, seen `count` times.
+#[derive(Debug, Clone, Copy)]
+struct Finder {
+    x: f64,
+    y: f64,
+    module: f64,
+    count: usize,
+}
+
+impl Finder {
+    fn point(self) -> (f64, f64) {
+        (self.x, self.y)
+    }
+}
+
+/// Decodes all QR codes in an image, dark on light or light on dark.
+pub fn decode_image(image: &GrayImage) -> Result<Vec<Decoded>, DecodeError> {
+    let bitmap = Bitmap::binarize(image);
+    match decode_bitmap(&bitmap) {
+        Err(DecodeError::NotFound) => decode_bitmap(&bitmap.inverted()),
+        result => result,
+    }
+}
+
+fn decode_bitmap(bitmap: &Bitmap) -> Result<Vec<Decoded>, DecodeError> {
+    let mut finders = find_finders(bitmap);
+    finders.sort_by_key(|finder| std::cmp::Reverse(finder.count));
+    if finders.iter().filter(|f| f.count >= 2).count() >= 3 {
+        finders.retain(|f| f.count >= 2);
+    }
+    finders.truncate(MAX_FINDERS);
+
+    let mut triples = Vec::new();
+    for i in 0..finders.len() {
+        for j in i + 1..finders.len() {
+            for k in j + 1..finders.len() {
+                if let Some(triple) = triple(&finders, [i, j, k]) {
+                    triples.push(triple);
+                }
+            }
+        }
+    }
+    triples.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut used = vec![false; finders.len()];
+    let mut decoded = Vec::new();
+    let mut error = DecodeError::NotFound;
+    for (_, [bottom_left, top_left, top_right]) in triples.into_iter().take(MAX_ATTEMPTS) {
+        if [bottom_left, top_left, top_right].iter().any(|&i| used[i]) {
+            continue;
+        }
+        let corners = [finders[bottom_left], finders[top_left], finders[top_right]];
+        match detect(bitmap, corners) {
+            Ok(symbol) => {
+                for i in [bottom_left, top_left, top_right] {
+                    used[i] = true;
+                }
+                decoded.push(symbol);
+            },
+            Err(err) => {
+                if matches!(error, DecodeError::NotFound) {
+                    error = err;
+                }
+            },
+        }
+    }
+    match decoded.is_empty() {
+        true => Err(error),
+        false => Ok(decoded),
+    }
+}
+
+/// Three finder patterns that could belong to one symbol, ordered as bottom left, top left and
+/// top right, with a score where lower is better.
+fn triple(finders: &[Finder], indices: [usize; 3]) -> Option<(f64, [usize; 3])> {
+    let modules = indices.map(|i| finders[i].module);
+    let (min, max) = modules.iter().fold((f64::MAX, 0.0_f64), |(min, max), &m| {
+        (min.min(m), max.max(m))
+    });
+    if max > 1.5 * min {
+        return None;
+    }
+    let [a, b, c] = indices;
+    let distance = |i: usize, j: usize| distance(finders[i].point(), finders[j].point());
+    // the top left one is opposite of the longest side
+    let (ab, bc, ac) = (distance(a, b), distance(b, c), distance(a, c));
+    let (mut bottom_left, top_left, mut top_right) = match () {
+        _ if bc >= ab && bc >= ac => (b, a, c),
+        _ if ac >= ab && ac >= bc => (a, b, c),
+        _ => (a, c, b),
+    };
+    let (p, q, r) = (
+        finders[bottom_left].point(),
+        finders[top_left].point(),
+        finders[top_right].point(),
+    );
+    if (r.0 - q.0) * (p.1 - q.1) - (r.1 - q.1) * (p.0 - q.0) < 0.0 {
+        std::mem::swap(&mut bottom_left, &mut top_right);
+    }
+
+    let (top, left) = (
+        distance(top_left, top_right),
+        distance(top_left, bottom_left),
+    );
+    let diagonal = distance(bottom_left, top_right);
+    let module = modules.iter().sum::<f64>() / 3.0;
+    let squares = top * top + left * left;
+    let skew = (diagonal * diagonal - squares).abs() / squares;
+    let aspect = top.max(left) / top.min(left);
+    let modules_between = top.min(left) / module;
+    if skew > 0.35 || aspect > 1.6 || !(10.0..=200.0).contains(&modules_between) {
+        return None;
+    }
+    Some((skew + aspect - 1.0, [bottom_left, top_left, top_right]))
+}
+
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    (a.0 - b.0).hypot(a.1 - b.1)
+}
+
+/// Whether runs have the ratio 1:1:3:1:1 of a finder pattern.
+fn is_finder(state: &[usize; 5]) -> bool {
+    let total: usize = state.iter().sum();
+    if total < 7 {
+        return false;
+    }
+    let module = total as f64 / 7.0;
+    let variance = module / 2.0;
+    state
+        .iter()
+        .zip([1.0, 1.0, 3.0, 1.0, 1.0])
+        .all(|(&count, ratio)| (module * ratio - count as f64).abs() < variance * ratio)
+}
+
+fn center_from_end(state: &[usize; 5], end: usize) -> f64 {
+    end as f64 - state[4] as f64 - state[3] as f64 - state[2] as f64 / 2.0
+}
+
+fn find_finders(bitmap: &Bitmap) -> Vec<Finder> {
+    let mut finders = Vec::new();
+    let skip = (bitmap.height / 600).clamp(1, 4);
+    for y in (skip - 1..bitmap.height).step_by(skip) {
+        let mut state = [0; 5];
+        let mut current = 0;
+        for x in 0..bitmap.width {
+            if bitmap.get(x, y) {
+                if current % 2 == 1 {
+                    current += 1;
+                }
+                state[current] += 1;
+            } else if current % 2 == 1 {
+                state[current] += 1;
+            } else if current < 4 {
+                current += 1;
+                state[current] += 1;
+            } else {
+                if is_finder(&state) && possible_center(bitmap, &state, x, y, &mut finders) {
+                    state = [0; 5];
+                    current = 0;
+                } else {
+                    state = [state[2], state[3], state[4], 1, 0];
+                    current = 3;
+                }
+            }
+        }
+        if is_finder(&state) {
+            possible_center(bitmap, &state, bitmap.width, y, &mut finders);
+        }
+    }
+    finders
+}
+
+/// Checks a horizontal candidate vertically and horizontally through its centre.
+fn possible_center(
+    bitmap: &Bitmap,
+    state: &[usize; 5],
+    end: usize,
+    y: usize,
+    finders: &mut Vec<Finder>,
+) -> bool {
+    let total: usize = state.iter().sum();
+    let x = center_from_end(state, end);
+    let Some(y) = cross_check(bitmap, x as usize, y, true, state[2], total) else { return false };
+    let Some(x) = cross_check(bitmap, x as usize, y as usize, false, state[2], total) else {
+        return false;
+    };
+    let module = total as f64 / 7.0;
+    let same = finders.iter_mut().find(|f| {
+        (f.x - x).abs() <= module && (f.y - y).abs() <= module && {
+            let difference = (f.module - module).abs();
+            difference <= 1.0 || difference <= f.module
+        }
+    });
+    match same {
+        Some(finder) => {
+            let count = finder.count as f64;
+            *finder = Finder {
+                x: (count * finder.x + x) / (count + 1.0),
+                y: (count * finder.y + y) / (count + 1.0),
+                module: (count * finder.module + module) / (count + 1.0),
+                count: finder.count + 1,
+            };
+        },
+        None => finders.push(Finder {
+            x,
+            y,
+            module,
+            count: 1,
+        }),
+    }
+    true
+}
+
+/// Measures the runs of a finder pattern through a point, returning the coordinate of its centre
+/// along the axis.
+fn cross_check(
+    bitmap: &Bitmap,
+    x: usize,
+    y: usize,
+    vertical: bool,
+    max_count: usize,
+    total: usize,
+) -> Option<f64> {
+    let (len, start) = match vertical {
+        true => (bitmap.height, y),
+        false => (bitmap.width, x),
+    };
+    let dark = |i: usize| match vertical {
+        true => bitmap.get(x, i),
+        false => bitmap.get(i, y),
+    };
+    let mut state = [0; 5];
+
+    let mut i = start as isize;
+    while i >= 0 && dark(i as usize) {
+        state[2] += 1;
+        i -= 1;
+    }
+    while i >= 0 && !dark(i as usize) && state[1] <= max_count {
+        state[1] += 1;
+        i -= 1;
+    }
+    if i < 0 || state[1] > max_count {
+        return None;
+    }
+    while i >= 0 && dark(i as usize) && state[0] <= max_count {
+        state[0] += 1;
+        i -= 1;
+    }
+    if state[0] > max_count {
+        return None;
+    }
+
+    let mut i = start + 1;
+    while i < len && dark(i) {
+        state[2] += 1;
+        i += 1;
+    }
+    while i < len && !dark(i) && state[3] < max_count {
+        state[3] += 1;
+        i += 1;
+    }
+    if i == len || state[3] >= max_count {
+        return None;
+    }
+    while i < len && dark(i) && state[4] < max_count {
+        state[4] += 1;
+        i += 1;
+    }
+    if state[4] >= max_count {
+        return None;
+    }
+
+    let sum: usize = state.iter().sum();
+    if 5 * sum.abs_diff(total) >= 2 * total || !is_finder(&state) {
+        return None;
+    }
+    Some(center_from_end(&state, i))
+}
+
+/// Samples and decodes the symbol of three finder patterns, trying neighbouring sizes if the
+/// estimated one is wrong.
+fn detect(
+    bitmap: &Bitmap,
+    [bottom_left, top_left, top_right]: [Finder; 3],
+) -> Result<Decoded, DecodeError> {
+    let (bl, tl, tr) = (bottom_left.point(), top_left.point(), top_right.point());
+    let module = (module_size(bitmap, tl, tr) + module_size(bitmap, tl, bl)) / 2.0;
+    if !module.is_finite() || module < 1.0 {
+        return Err(DecodeError::NotFound);
+    }
+    let modules = |a, b| (distance(a, b) / module).round() as usize;
+    let width = (modules(tl, tr) + modules(tl, bl)) / 2 + 7;
+    let width = match width % 4 {
+        0 => width + 1,
+        2 => width - 1,
+        3 => width + 2,
+        _ => width,
+    };
+
+    let mut error = DecodeError::NotFound;
+    let mut widths = vec![width, width + 4, width.saturating_sub(4)];
+    let mut tried = Vec::new();
+    while let Some(width) = widths.first().copied() {
+        widths.remove(0);
+        if !(21..=177).contains(&width) || tried.contains(&width) {
+            continue;
+        }
+        tried.push(width);
+        let transform = transform(bitmap, bl, tl, tr, width, module);
+        let matrix = Matrix {
+            width,
+            modules: (0..width * width)
+                .map(|i| {
+                    bitmap.at(transform.apply((i % width) as f64 + 0.5, (i / width) as f64 + 0.5))
+                })
+                .collect(),
+        };
+        match decode_matrix(&matrix) {
+            Ok(decoded) => return Ok(decoded),
+            Err(DecodeError::VersionMismatch { encoded, .. }) => {
+                widths.insert(0, 17 + 4 * encoded as usize);
+            },
+            Err(err) => error = err,
+        }
+    }
+    Err(error)
+}
+
+/// The module size measured along the line between two finder patterns.
+fn module_size(bitmap: &Bitmap, a: (f64, f64), b: (f64, f64)) -> f64 {
+    let one = run_both_ways(bitmap, a, b);
+    let other = run_both_ways(bitmap, b, a);
+    match (one.is_nan(), other.is_nan()) {
+        (true, _) => other / 7.0,
+        (_, true) => one / 7.0,
+        _ => (one + other) / 14.0,
+    }
+}
+
+/// The width of a finder pattern from its centre towards `to` and the opposite direction.
+fn run_both_ways(bitmap: &Bitmap, from: (f64, f64), to: (f64, f64)) -> f64 {
+    let mut result = run(bitmap, from, to);
+    let (max_x, max_y) = ((bitmap.width - 1) as f64, (bitmap.height - 1) as f64);
+    let mut other = (2.0 * from.0 - to.0, 2.0 * from.1 - to.1);
+    let mut scale = 1.0;
+    if other.0 < 0.0 {
+        scale = from.0 / (from.0 - other.0);
+    } else if other.0 > max_x {
+        scale = (max_x - from.0) / (other.0 - from.0);
+    }
+    other.1 = from.1 - (to.1 - from.1) * scale;
+    other.0 = from.0 - (to.0 - from.0) * scale;
+    scale = 1.0;
+    if other.1 < 0.0 {
+        scale = from.1 / (from.1 - other.1);
+    } else if other.1 > max_y {
+        scale = (max_y - from.1) / (other.1 - from.1);
+    }
+    other.0 = from.0 + (other.0 - from.0) * scale;
+    other.1 = from.1 + (other.1 - from.1) * scale;
+    result += run(bitmap, from, other);
+    // the centre pixel was counted twice
+    result - 1.0
+}
+
+/// The distance from a point in a dark module to the far end of the next dark run, along a line.
+fn run(bitmap: &Bitmap, from: (f64, f64), to: (f64, f64)) -> f64 {
+    let (mut from_x, mut from_y) = (from.0 as isize, from.1 as isize);
+    let (mut to_x, mut to_y) = (to.0 as isize, to.1 as isize);
+    let steep = (to_y - from_y).abs() > (to_x - from_x).abs();
+    if steep {
+        (from_x, from_y) = (from_y, from_x);
+        (to_x, to_y) = (to_y, to_x);
+    }
+    let (dx, dy) = ((to_x - from_x).abs(), (to_y - from_y).abs());
+    let (step_x, step_y) = ((to_x - from_x).signum(), (to_y - from_y).signum());
+    let mut error = -dx / 2;
+    let mut state = 0;
+    let (mut x, mut y) = (from_x, from_y);
+    let limit = to_x + step_x;
+    while x != limit {
+        let (real_x, real_y) = if steep { (y, x) } else { (x, y) };
+        let dark = bitmap.at((real_x as f64, real_y as f64));
+        // dark to light, light to dark, and dark to light again
+        if (state == 1) == dark {
+            if state == 2 {
+                return distance((x as f64, y as f64), (from_x as f64, from_y as f64));
+            }
+            state += 1;
+        }
+        error += dy;
+        if error > 0 {
+            if y == to_y {
+                break;
+            }
+            y += step_y;
+            error -= dx;
+        }
+        x += step_x;
+    }
+    match state == 2 {
+        true => distance(
+            ((to_x + step_x) as f64, to_y as f64),
+            (from_x as f64, from_y as f64),
+        ),
+        false => f64::NAN,
+    }
+}
+
+/// The transform from module coordinates to the image, using the alignment pattern closest to
+/// the bottom right corner if the symbol has one.
+fn transform(
+    bitmap: &Bitmap,
+    bl: (f64, f64),
+    tl: (f64, f64),
+    tr: (f64, f64),
+    width: usize,
+    module: f64,
+) -> Perspective {
+    let far = width as f64 - 3.5;
+    let bottom_right = (tr.0 - tl.0 + bl.0, tr.1 - tl.1 + bl.1);
+    let (source, target) = match width > 21 {
+        true => {
+            let corner = width as f64 - 6.5;
+            let correction = 1.0 - 3.0 / (width as f64 - 7.0);
+            let estimate = (
+                tl.0 + correction * (bottom_right.0 - tl.0),
+                tl.1 + correction * (bottom_right.1 - tl.1),
+            );
+            let axes = (
+                (
+                    (tr.0 - tl.0) / (width as f64 - 7.0),
+                    (tr.1 - tl.1) / (width as f64 - 7.0),
+                ),
+                (
+                    (bl.0 - tl.0) / (width as f64 - 7.0),
+                    (bl.1 - tl.1) / (width as f64 - 7.0),
+                ),
+            );
+            match find_alignment(bitmap, estimate, axes, module) {
+                Some(alignment) => ((corner, corner), alignment),
+                None => ((far, far), bottom_right),
+            }
+        },
+        false => ((far, far), bottom_right),
+    };
+    Perspective::quad_to_quad([(3.5, 3.5), (far, 3.5), source, (3.5, far)], [
+        tl, tr, target, bl,
+    ])
+}
+
+/// Searches the 5×5 pattern of concentric squares around the estimated position.
+fn find_alignment(
+    bitmap: &Bitmap,
+    estimate: (f64, f64),
+    (u, v): ((f64, f64), (f64, f64)),
+    module: f64,
+) -> Option<(f64, f64)> {
+    let at = |c: (f64, f64), dx: f64, dy: f64| {
+        bitmap.at((c.0 + dx * u.0 + dy * v.0, c.1 + dx * u.1 + dy * v.1))
+    };
+    let score = |c: (f64, f64)| {
+        let mut score = 0;
+        for dy in -2..=2_i32 {
+            for dx in -2..=2_i32 {
+                let dark = dx.abs().max(dy.abs()) != 1;
+                score += usize::from(at(c, f64::from(dx), f64::from(dy)) == dark);
+            }
+        }
+        score
+    };
+    let step = (module / 3.0).max(1.0);
+    for allowance in [4.0, 8.0, 16.0] {
+        let steps = (allowance * module / step) as i32;
+        let mut best: Option<(usize, f64, (f64, f64))> = None;
+        for j in -steps..=steps {
+            for i in -steps..=steps {
+                let c = (
+                    estimate.0 + f64::from(i) * step,
+                    estimate.1 + f64::from(j) * step,
+                );
+                let score = score(c);
+                let distance = distance(c, estimate);
+                let better = best.is_none_or(|(s, d, _)| score > s || score == s && distance < d);
+                if score >= 23 && better {
+                    best = Some((score, distance, c));
+                }
+            }
+        }
+        if let Some((_, _, c)) = best {
+            return Some(center_of_dark_module(bitmap, c, u, v));
+        }
+    }
+    None
+}
+
+/// The middle of the dark module around a point, along both axes of the symbol.
+fn center_of_dark_module(
+    bitmap: &Bitmap,
+    c: (f64, f64),
+    u: (f64, f64),
+    v: (f64, f64),
+) -> (f64, f64) {
+    let mut center = c;
+    for axis in [u, v] {
+        let len = axis.0.hypot(axis.1);
+        let unit = (axis.0 / len, axis.1 / len);
+        let extent = |sign: f64| {
+            let mut t = 0.0;
+            while t < 2.0 * len
+                && bitmap.at((
+                    center.0 + sign * (t + 1.0) * unit.0,
+                    center.1 + sign * (t + 1.0) * unit.1,
+                ))
+            {
+                t += 1.0;
+            }
+            t
+        };
+        let (forwards, backwards) = (extent(1.0), extent(-1.0));
+        let shift = (forwards - backwards) / 2.0;
+        center = (center.0 + shift * unit.0, center.1 + shift * unit.1);
+    }
+    center
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{imageops, Luma};
+    use qrcode::{EcLevel, QrCode};
+
+    use super::*;
+
+    fn render(text: &str, ec_level: EcLevel) -> GrayImage {
+        QrCode::with_error_correction_level(text, ec_level)
+            .unwrap()
+            .render::<Luma<u8>>()
+            .module_dimensions(4, 4)
+            .build()
+    }
+
+    #[test]
+    fn finds_codes() {
+        let text = "https://www.fu-berlin.de/";
+        let decoded = decode_image(&render(text, EcLevel::M)).unwrap();
+        assert_eq!(decoded.len(), 1);
+        assert_eq!(decoded[0].text, text);
+
+        let long = "Veterinärmedizin ".repeat(20);
+        let image = render(&long, EcLevel::Q);
+        assert_eq!(decode_image(&image).unwrap()[0].text, long);
+        let rotated = imageops::rotate90(&image);
+        assert_eq!(decode_image(&rotated).unwrap()[0].text, long);
+    }
+
+    #[test]
+    fn finds_several_codes() {
+        let (a, b) = (render("ERSTER", EcLevel::L), render("zweiter", EcLevel::H));
+        let mut image = GrayImage::from_pixel(a.width() + b.width() + 40, 300, Luma([255]));
+        imageops::replace(&mut image, &a, 10, 20);
+        imageops::replace(&mut image, &b, a.width() + 30, 60);
+        let mut texts: Vec<_> = decode_image(&image)
+            .unwrap()
+            .into_iter()
+            .map(|decoded| decoded.text)
+            .collect();
+        texts.sort();
+        assert_eq!(texts, ["ERSTER", "zweiter"]);
+
+        let mut inverted = image.clone();
+        imageops::invert(&mut inverted);
+        assert_eq!(decode_image(&inverted).unwrap().len(), 2);
+        let blank = GrayImage::from_pixel(100, 100, Luma([255]));
+        assert!(matches!(decode_image(&blank), Err(DecodeError::NotFound)));
+    }
+}
@@ -0,0 +1,424 @@
+//! Reading the codewords from the modules of a QR code (ISO/IEC 18004, sections 7.7 to 7.9).
+
+use qrcode::EcLevel;
+
+use super::{reed_solomon, DecodeError};
+
+/// Error correction codewords per block, number of blocks in the first group, data codewords
+/// per block in the first group, and number of blocks in the second group, which have one data
+/// codeword more. Indexed by version and `EcLevel as usize`.
+#[rustfmt::skip]
+const BLOCKS: [[(usize, usize, usize, usize); 4]; 40] = [
+    [(7, 1, 19, 0), (10, 1, 16, 0), (13, 1, 13, 0), (17, 1, 9, 0)], // 1
+    [(10, 1, 34, 0), (16, 1, 28, 0), (22, 1, 22, 0), (28, 1, 16, 0)], // 2
+    [(15, 1, 55, 0), (26, 1, 44, 0), (18, 2, 17, 0), (22, 2, 13, 0)], // 3
+    [(20, 1, 80, 0), (18, 2, 32, 0), (26, 2, 24, 0), (16, 4, 9, 0)], // 4
+    [(26, 1, 108, 0), (24, 2, 43, 0), (18, 2, 15, 2), (22, 2, 11, 2)], // 5
+    [(18, 2, 68, 0), (16, 4, 27, 0), (24, 4, 19, 0), (28, 4, 15, 0)], // 6
+    [(20, 2, 78, 0), (18, 4, 31, 0), (18, 2, 14, 4), (26, 4, 13, 1)], // 7
+    [(24, 2, 97, 0), (22, 2, 38, 2), (22, 4, 18, 2), (26, 4, 14, 2)], // 8
+    [(30, 2, 116, 0), (22, 3, 36, 2), (20, 4, 16, 4), (24, 4, 12, 4)], // 9
+    [(18, 2, 68, 2), (26, 4, 43, 1), (24, 6, 19, 2), (28, 6, 15, 2)], // 10
+    [(20, 4, 81, 0), (30, 1, 50, 4), (28, 4, 22, 4), (24, 3, 12, 8)], // 11
+    [(24, 2, 92, 2), (22, 6, 36, 2), (26, 4, 20, 6), (28, 7, 14, 4)], // 12
+    [(26, 4, 107, 0), (22, 8, 37, 1), (24, 8, 20, 4), (22, 12, 11, 4)], // 13
+    [(30, 3, 115, 1), (24, 4, 40, 5), (20, 11, 16, 5), (24, 11, 12, 5)], // 14
+    [(22, 5, 87, 1), (24, 5, 41, 5), (30, 5, 24, 7), (24, 11, 12, 7)], // 15
+    [(24, 5, 98, 1), (28, 7, 45, 3), (24, 15, 19, 2), (30, 3, 15, 13)], // 16
+    [(28, 1, 107, 5), (28, 10, 46, 1), (28, 1, 22, 15), (28, 2, 14, 17)], // 17
+    [(30, 5, 120, 1), (26, 9, 43, 4), (28, 17, 22, 1), (28, 2, 14, 19)], // 18
+    [(28, 3, 113, 4), (26, 3, 44, 11), (26, 17, 21, 4), (26, 9, 13, 16)], // 19
+    [(28, 3, 107, 5), (26, 3, 41, 13), (30, 15, 24, 5), (28, 15, 15, 10)], // 20
+    [(28, 4, 116, 4), (26, 17, 42, 0), (28, 17, 22, 6), (30, 19, 16, 6)], // 21
+    [(28, 2, 111, 7), (28, 17, 46, 0), (30, 7, 24, 16), (24, 34, 13, 0)], // 22
+    [(30, 4, 121, 5), (28, 4, 47, 14), (30, 11, 24, 14), (30, 16, 15, 14)], // 23
+    [(30, 6, 117, 4), (28, 6, 45, 14), (30, 11, 24, 16), (30, 30, 16, 2)], // 24
+    [(26, 8, 106, 4), (28, 8, 47, 13), (30, 7, 24, 22), (30, 22, 15, 13)], // 25
+    [(28, 10, 114, 2), (28, 19, 46, 4), (28, 28, 22, 6), (30, 33, 16, 4)], // 26
+    [(30, 8, 122, 4), (28, 22, 45, 3), (30, 8, 23, 26), (30, 12, 15, 28)], // 27
+    [(30, 3, 117, 10), (28, 3, 45, 23), (30, 4, 24, 31), (30, 11, 15, 31)], // 28
+    [(30, 7, 116, 7), (28, 21, 45, 7), (30, 1, 23, 37), (30, 19, 15, 26)], // 29
+    [(30, 5, 115, 10), (28, 19, 47, 10), (30, 15, 24, 25), (30, 23, 15, 25)], // 30
+    [(30, 13, 115, 3), (28, 2, 46, 29), (30, 42, 24, 1), (30, 23, 15, 28)], // 31
+    [(30, 17, 115, 0), (28, 10, 46, 23), (30, 10, 24, 35), (30, 19, 15, 35)], // 32
+    [(30, 17, 115, 1), (28, 14, 46, 21), (30, 29, 24, 19), (30, 11, 15, 46)], // 33
+    [(30, 13, 115, 6), (28, 14, 46, 23), (30, 44, 24, 7), (30, 59, 16, 1)], // 34
+    [(30, 12, 121, 7), (28, 12, 47, 26), (30, 39, 24, 14), (30, 22, 15, 41)], // 35
+    [(30, 6, 121, 14), (28, 6, 47, 34), (30, 46, 24, 10), (30, 2, 15, 64)], // 36
+    [(30, 17, 122, 4), (28, 29, 46, 14), (30, 49, 24, 10), (30, 24, 15, 46)], // 37
+    [(30, 4, 122, 18), (28, 13, 46, 32), (30, 48, 24, 14), (30, 42, 15, 32)], // 38
+    [(30, 20, 117, 4), (28, 40, 47, 7), (30, 43, 24, 22), (30, 10, 15, 67)], // 39
+    [(30, 19, 118, 6), (28, 18, 47, 31), (30, 34, 24, 34), (30, 20, 15, 61)], // 40
+];
+
+/// The dark and light modules of a symbol without quiet zone, row by row.
+#[derive(Debug, Clone)]
+pub struct Matrix {
+    pub width: usize,
+    pub modules: Vec<bool>,
+}
+
+impl Matrix {
+    fn get(&self, x: usize, y: usize) -> bool {
+        self.modules[y * self.width + x]
+    }
+
+    /// The symbol mirrored at its main diagonal, as seen from the back of a transparent film.
+    pub fn transposed(&self) -> Self {
+        let width = self.width;
+        let modules = (0..width * width)
+            .map(|i| self.get(i / width, i % width))
+            .collect();
+        Self { width, modules }
+    }
+}
+
+/// Codewords of a symbol after error correction.
+#[derive(Debug)]
+pub struct Codewords {
+    pub version: i16,
+    pub ec_level: EcLevel,
+    pub mask: u8,
+    pub data: Vec<u8>,
+    /// Codewords that had to be corrected.
+    pub corrected: usize,
+}
+
+pub fn codewords(matrix: &Matrix) -> Result<Codewords, DecodeError> {
+    let width = matrix.width;
+    let version = match width.checked_sub(17) {
+        Some(modules) if modules % 4 == 0 && (1..=40).contains(&(modules / 4)) => modules / 4,
+        _ => return Err(DecodeError::InvalidSize(width)),
+    };
+    let (ec_level, mask) = format_info(matrix)?;
+    if version >= 7 {
+        let encoded = version_info(matrix).ok_or(DecodeError::InvalidFormat)?;
+        if encoded != version {
+            return Err(DecodeError::VersionMismatch {
+                encoded: encoded as i16,
+                measured: version as i16,
+            });
+        }
+    }
+
+    let (lens, ec_len) = blocks(version, ec_level);
+    let order = codeword_order(&lens, ec_len);
+    let modules = module_order(version);
+    if modules.len() < order.len() * 8 {
+        return Err(DecodeError::InvalidSize(width));
+    }
+
+    // data codewords are interleaved, followed by the interleaved error correction codewords
+    let mut blocks: Vec<Vec<u8>> = lens
+        .iter()
+        .map(|len| Vec::with_capacity(len + ec_len))
+        .collect();
+    for (codeword, bits) in order.iter().zip(modules.chunks(8)) {
+        let byte = bits.iter().fold(0_u8, |byte, &(x, y)| {
+            byte << 1 | u8::from(matrix.get(x, y) ^ is_masked(mask, x, y))
+        });
+        blocks[codeword.block].push(byte);
+    }
+
+    let mut data = Vec::with_capacity(lens.iter().sum());
+    let mut corrected = 0;
+    for (block, &len) in blocks.iter_mut().zip(&lens) {
+        corrected += reed_solomon::correct(block, ec_len).ok_or(DecodeError::TooManyErrors)?;
+        data.extend_from_slice(&block[..len]);
+    }
+    Ok(Codewords {
+        version: version as i16,
+        ec_level,
+        mask,
+        data,
+        corrected,
+    })
+}
+
+/// The data codewords of each block and the number of error correction codewords per block.
+pub fn blocks(version: usize, ec_level: EcLevel) -> (Vec<usize>, usize) {
+    let (ec_len, count_1, len_1, count_2) = BLOCKS[version - 1][ec_level as usize];
+    let lens = (0..count_1 + count_2)
+        .map(|block| len_1 + usize::from(block >= count_1))
+        .collect();
+    (lens, ec_len)
+}
+
+/// The place of a codeword in the blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Codeword {
+    pub block: usize,
+    /// The position in the block, where the error correction codewords follow the data.
+    pub index: usize,
+    /// An error correction codeword rather than a data codeword.
+    pub ec: bool,
+}
+
+/// The codewords in the order they are placed into the symbol: the data codewords of all
+/// blocks interleaved, followed by the interleaved error correction codewords.
+fn codeword_order(lens: &[usize], ec_len: usize) -> Vec<Codeword> {
+    let longest = lens.iter().copied().max().unwrap_or(0);
+    let data = (0..longest).flat_map(|i| {
+        (0..lens.len())
+            .filter(move |&block| i < lens[block])
+            .map(move |block| Codeword {
+                block,
+                index: i,
+                ec: false,
+            })
+    });
+    let ec = (0..ec_len).flat_map(|i| {
+        lens.iter().enumerate().map(move |(block, len)| Codeword {
+            block,
+            index: len + i,
+            ec: true,
+        })
+    });
+    data.chain(ec).collect()
+}
+
+/// The modules of the codeword bits, most significant bit first, followed by the remainder
+/// bits.
+fn module_order(version: usize) -> Vec<(usize, usize)> {
+    let width = 17 + 4 * version;
+    let function = function_modules(version);
+    let mut modules = Vec::with_capacity(width * width);
+    // pairs of columns from the right, upwards and downwards in turn, skipping the timing pattern
+    let mut right = width - 1;
+    while right >= 1 {
+        if right == 6 {
+            right = 5;
+        }
+        let upwards = (right + 1) & 2 == 0;
+        for vertical in 0..width {
+            let y = if upwards {
+                width - 1 - vertical
+            } else {
+                vertical
+            };
+            for x in [right, right - 1] {
+                if !function[y * width + x] {
+                    modules.push((x, y));
+                }
+            }
+        }
+        right = right.saturating_sub(2);
+        if right == 0 {
+            break;
+        }
+    }
+    modules
+}
+
+/// The codeword of each module, row by row, or `None` for function patterns and remainder
+/// bits.
+pub fn layout(version: usize, ec_level: EcLevel) -> Vec<Option<Codeword>> {
+    let width = 17 + 4 * version;
+    let (lens, ec_len) = blocks(version, ec_level);
+    let mut layout = vec![None; width * width];
+    for (codeword, bits) in codeword_order(&lens, ec_len)
+        .into_iter()
+        .zip(module_order(version).chunks(8))
+    {
+        for &(x, y) in bits {
+            layout[y * width + x] = Some(codeword);
+        }
+    }
+    layout
+}
+
+fn is_masked(mask: u8, x: usize, y: usize) -> bool {
+    match mask {
+        0 => (x + y).is_multiple_of(2),
+        1 => y.is_multiple_of(2),
+        2 => x.is_multiple_of(3),
+        3 => (x + y).is_multiple_of(3),
+        4 => (x / 3 + y / 2).is_multiple_of(2),
+        5 => x * y % 2 + x * y % 3 == 0,
+        6 => (x * y % 2 + x * y % 3).is_multiple_of(2),
+        _ => ((x + y) % 2 + x * y % 3).is_multiple_of(2),
+    }
+}
+
+/// The format information closest to one of its two copies, if it has at most 3 wrong bits.
+fn format_info(matrix: &Matrix) -> Result<(EcLevel, u8), DecodeError> {
+    let width = matrix.width;
+    let (mut first, mut second) = (0_u32, 0_u32);
+    for i in 0..15 {
+        let (x, y) = match i {
+            0..=5 => (8, i),
+            6 => (8, 7),
+            7 => (8, 8),
+            8 => (7, 8),
+            _ => (14 - i, 8),
+        };
+        first |= u32::from(matrix.get(x, y)) << i;
+        let (x, y) = match i {
+            0..=7 => (width - 1 - i, 8),
+            _ => (8, width - 15 + i),
+        };
+        second |= u32::from(matrix.get(x, y)) << i;
+    }
+    let best = (0..32_u32)
+        .map(|data| {
+            let encoded = bch(data, 10, 0x537) ^ 0x5412;
+            let distance = (encoded ^ first)
+                .count_ones()
+                .min((encoded ^ second).count_ones());
+            (distance, data)
+        })
+        .min();
+    match best {
+        Some((distance, data)) if distance <= 3 => {
+            let ec_level = match data >> 3 {
+                0 => EcLevel::M,
+                1 => EcLevel::L,
+                2 => EcLevel::H,
+                _ => EcLevel::Q,
+            };
+            Ok((ec_level, (data & 7) as u8))
+        },
+        _ => Err(DecodeError::InvalidFormat),
+    }
+}
+
+/// The version from the version information of symbols from version 7 on.
+fn version_info(matrix: &Matrix) -> Option<usize> {
+    let width = matrix.width;
+    let (mut first, mut second) = (0_u32, 0_u32);
+    for i in 0..18 {
+        let (a, b) = (width - 11 + i % 3, i / 3);
+        first |= u32::from(matrix.get(a, b)) << i;
+        second |= u32::from(matrix.get(b, a)) << i;
+    }
+    let (distance, version) = (7..=40_u32)
+        .map(|version| {
+            let encoded = bch(version, 12, 0x1f25);
+            let distance = (encoded ^ first)
+                .count_ones()
+                .min((encoded ^ second).count_ones());
+            (distance, version)
+        })
+        .min()?;
+    (distance <= 3).then_some(version as usize)
+}
+
+/// `data` followed by the remainder of a BCH code with the generator polynomial `generator`.
+fn bch(data: u32, len: u32, generator: u32) -> u32 {
+    let mut remainder = data;
+    for _ in 0..len {
+        remainder = (remainder << 1) ^ ((remainder >> (len - 1)) * generator);
+    }
+    data << len | remainder
+}
+
+/// The centres of the alignment patterns in each dimension.
+pub fn alignment_positions(version: usize) -> Vec<usize> {
+    if version == 1 {
+        return Vec::new();
+    }
+    let width = 17 + 4 * version;
+    let count = version / 7 + 2;
+    let step = match version {
+        32 => 26,
+        _ => (version * 4 + count * 2 + 1) / (count * 2 - 2) * 2,
+    };
+    let mut positions: Vec<usize> = (0..count - 1).map(|i| width - 7 - i * step).collect();
+    positions.push(6);
+    positions.reverse();
+    positions
+}
+
+/// Marks the modules of finder, timing and alignment patterns, and of format and version
+/// information.
+fn function_modules(version: usize) -> Vec<bool> {
+    let width = 17 + 4 * version;
+    let mut function = vec![false; width * width];
+    let mut mark = |x0: usize, y0: usize, w: usize, h: usize| {
+        for y in y0..y0 + h {
+            for x in x0..x0 + w {
+                function[y * width + x] = true;
+            }
+        }
+    };
+    // finder patterns with separators and format information
+    mark(0, 0, 9, 9);
+    mark(width - 8, 0, 8, 9);
+    mark(0, width - 8, 9, 8);
+    // timing patterns
+    mark(6, 0, 1, width);
+    mark(0, 6, width, 1);
+    let positions = alignment_positions(version);
+    let last = positions.len().saturating_sub(1);
+    for (i, &x) in positions.iter().enumerate() {
+        for (j, &y) in positions.iter().enumerate() {
+            if (i, j) != (0, 0) && (i, j) != (0, last) && (i, j) != (last, 0) {
+                mark(x - 2, y - 2, 5, 5);
+            }
+        }
+    }
+    if version >= 7 {
+        mark(width - 11, 0, 3, 6);
+        mark(0, width - 11, 6, 3);
+    }
+    function
+}
+
+#[cfg(test)]
+mod tests {
+    use qrcode::bits::Bits;
+    use qrcode::Version;
+
+    use super::*;
+
+    #[test]
+    fn block_table() {
+        for version in 1..=40 {
+            let width = 17 + 4 * version;
+            let available = function_modules(version)
+                .iter()
+                .filter(|&&function| !function)
+                .count();
+            for ec_level in [EcLevel::L, EcLevel::M, EcLevel::Q, EcLevel::H] {
+                let (ec_len, count_1, len_1, count_2) = BLOCKS[version - 1][ec_level as usize];
+                let data = count_1 * len_1 + count_2 * (len_1 + 1);
+                let total = data + (count_1 + count_2) * ec_len;
+                assert_eq!(total, available / 8, "version {version}, width {width}");
+                let bits = Bits::new(Version::Normal(version as i16));
+                assert_eq!(Some(data * 8), bits.max_len(ec_level).ok());
+            }
+        }
+    }
+
+    #[test]
+    fn codeword_layout() {
+        for (version, ec_level) in [(1, EcLevel::L), (5, EcLevel::Q), (40, EcLevel::H)] {
+            let (lens, ec_len) = blocks(version, ec_level);
+            let layout = layout(version, ec_level);
+            for (block, len) in lens.iter().enumerate() {
+                for index in 0..len + ec_len {
+                    let codeword = Codeword {
+                        block,
+                        index,
+                        ec: index >= *len,
+                    };
+                    let modules = layout.iter().filter(|&&c| c == Some(codeword)).count();
+                    assert_eq!(modules, 8, "version {version}, {codeword:?}");
+                }
+            }
+        }
+        // the first codeword starts in the bottom right corner
+        let layout = layout(2, EcLevel::M);
+        let width = 25;
+        assert_eq!(layout[width * width - 1].map(|c| c.index), Some(0));
+        assert_eq!(layout[0], None);
+    }
+
+    #[test]
+    fn alignment_patterns() {
+        assert_eq!(alignment_positions(2), [6, 18]);
+        assert_eq!(alignment_positions(7), [6, 22, 38]);
+        assert_eq!(alignment_positions(32), [6, 34, 60, 86, 112, 138]);
+        assert_eq!(alignment_positions(40), [6, 30, 58, 86, 114, 142, 170]);
+    }
+}
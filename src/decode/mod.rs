@@ -0,0 +1,452 @@
+//! Reading QR codes back, from rendered symbols as well as from scans and screenshots.
+
+mod detect;
+mod matrix;
+mod perspective;
+mod reed_solomon;
+
+use encoding_rs::{Encoding, SHIFT_JIS};
+use image::{GrayImage, ImageBuffer, Luma, LumaA};
+use qrcode::EcLevel;
+
+pub use self::detect::decode_image;
+pub use self::matrix::{blocks, layout, Codeword, Matrix};
+pub use self::perspective::Perspective;
+
+/// The group separator, which stands for FNC1 in GS1 element strings.
+const GS: char = '\x1d';
+
+const ALPHANUMERIC: &[u8; 45] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+#[derive(Debug, thiserror::Error)]
+pub enum DecodeError {
+    #[error("Im Bild wurde kein QR-Code gefunden.")]
+    NotFound,
+    #[error("Ein QR-Code mit {0}×{0} Modulen ist ungültig.")]
+    InvalidSize(usize),
+    #[error("Die Formatinformation des QR-Codes ist unlesbar.")]
+    InvalidFormat,
+    #[error("Der QR-Code hat Version {encoded}, gemessen wurde aber Version {measured}.")]
+    VersionMismatch { encoded: i16, measured: i16 },
+    #[error("Der QR-Code hat zu viele Fehler, um ihn zu korrigieren.")]
+    TooManyErrors,
+    #[error("Die Daten des QR-Codes sind ungültig.")]
+    InvalidData,
+    #[error("Das Bild konnte nicht gelesen werden: {0}")]
+    Image(#[from] image::ImageError),
+}
+
+/// The content of a QR code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decoded {
+    /// The content as text, in the character set from the ECI or else as guessed.
+    pub text: String,
+    /// The bytes of all segments, Kanji in Shift-JIS.
+    pub data: Vec<u8>,
+    pub version: i16,
+    pub ec_level: EcLevel,
+    pub mask: u8,
+    /// The first ECI designator.
+    pub eci: Option<u32>,
+    /// The content is a GS1 element string.
+    pub fnc1: bool,
+    /// Codewords that the error correction had to fix.
+    pub corrected: usize,
+    /// The position of the symbol in a sequence of symbols.
+    pub structured_append: Option<StructuredAppend>,
+}
+
+/// The header of a symbol that is part of a sequence (ISO/IEC 18004, section 8).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StructuredAppend {
+    /// The position in the sequence, starting at 0.
+    pub index: u8,
+    /// The number of symbols in the sequence, 2 to 16.
+    pub total: u8,
+    /// The XOR of all bytes of the complete content, which is the same in all symbols.
+    pub parity: u8,
+}
+
+/// Decodes all QR codes in an image file like PNG, JPEG or WebP.
+pub fn decode_file(bytes: &[u8]) -> Result<Vec<Decoded>, DecodeError> {
+    let image = image::load_from_memory(bytes)?;
+    decode_image(&flatten(&image.to_luma_alpha8()))
+}
+
+/// Composites an image with transparency onto white.
+pub fn flatten(image: &ImageBuffer<LumaA<u8>, Vec<u8>>) -> GrayImage {
+    GrayImage::from_fn(image.width(), image.height(), |x, y| {
+        let LumaA([luma, alpha]) = *image.get_pixel(x, y);
+        let (luma, alpha) = (u16::from(luma), u16::from(alpha));
+        Luma([((luma * alpha + 255 * (255 - alpha)) / 255) as u8])
+    })
+}
+
+/// Decodes the modules of a symbol without quiet zone, or of its mirror image.
+pub fn decode_matrix(matrix: &Matrix) -> Result<Decoded, DecodeError> {
+    match decode_codewords(matrix) {
+        Ok(decoded) => Ok(decoded),
+        Err(err) => decode_codewords(&matrix.transposed()).map_err(|_| err),
+    }
+}
+
+fn decode_codewords(matrix: &Matrix) -> Result<Decoded, DecodeError> {
+    let codewords = matrix::codewords(matrix)?;
+    let mut decoded = Decoded {
+        text: String::new(),
+        data: Vec::new(),
+        version: codewords.version,
+        ec_level: codewords.ec_level,
+        mask: codewords.mask,
+        eci: None,
+        fnc1: false,
+        corrected: codewords.corrected,
+        structured_append: None,
+    };
+    parse(&codewords.data, &mut decoded).ok_or(DecodeError::InvalidData)?;
+    Ok(decoded)
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl BitReader<'_> {
+    fn remaining(&self) -> usize {
+        self.data.len() * 8 - self.position
+    }
+
+    fn read(&mut self, bits: usize) -> Option<u32> {
+        if bits > self.remaining() {
+            return None;
+        }
+        let mut value = 0;
+        for _ in 0..bits {
+            let bit = self.data[self.position / 8] >> (7 - self.position % 8) & 1;
+            value = value << 1 | u32::from(bit);
+            self.position += 1;
+        }
+        Some(value)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Charset {
+    /// ISO-8859-1, which `encoding_rs` treats as windows-1252.
+    Latin1,
+    Other(&'static Encoding),
+}
+
+/// Decoded text, and bytes whose character set may have to be guessed.
+enum Part {
+    Text(String),
+    Bytes(Vec<u8>, Option<Charset>),
+}
+
+/// Reads the segments of the data codewords (ISO/IEC 18004, section 7.4).
+fn parse(data: &[u8], decoded: &mut Decoded) -> Option<()> {
+    let version = decoded.version;
+    let class = match version {
+        1..=9 => 0,
+        10..=26 => 1,
+        _ => 2,
+    };
+    let mut reader = BitReader { data, position: 0 };
+    let mut parts = Vec::new();
+    let mut charset = None;
+    let mut kanji = false;
+    while reader.remaining() >= 4 {
+        match reader.read(4)? {
+            0b0000 => break,
+            0b0001 => {
+                let count = reader.read([10, 12, 14][class])? as usize;
+                let mut digits = String::with_capacity(count);
+                for chunk in (0..count).step_by(3) {
+                    let len = (count - chunk).min(3);
+                    let value = reader.read([0, 4, 7, 10][len])?;
+                    if value >= [1, 10, 100, 1000][len] {
+                        return None;
+                    }
+                    digits.push_str(&format!("{value:0len$}"));
+                }
+                decoded.data.extend_from_slice(digits.as_bytes());
+                parts.push(Part::Text(digits));
+            },
+            0b0010 => {
+                let count = reader.read([9, 11, 13][class])? as usize;
+                let mut chars = Vec::with_capacity(count);
+                for _ in 0..count / 2 {
+                    let value = reader.read(11)? as usize;
+                    chars.push(*ALPHANUMERIC.get(value / 45)?);
+                    chars.push(*ALPHANUMERIC.get(value % 45)?);
+                }
+                if count % 2 == 1 {
+                    chars.push(*ALPHANUMERIC.get(reader.read(6)? as usize)?);
+                }
+                decoded.data.extend_from_slice(&chars);
+                let mut text = String::with_capacity(chars.len());
+                let mut chars = chars.into_iter().map(char::from).peekable();
+                while let Some(c) = chars.next() {
+                    // in GS1 element strings `%` stands for FNC1, `%%` for a percent sign
+                    match (c, decoded.fnc1) {
+                        ('%', true) if chars.next_if_eq(&'%').is_none() => text.push(GS),
+                        _ => text.push(c),
+                    }
+                }
+                parts.push(Part::Text(text));
+            },
+            0b0100 => {
+                let count = reader.read([8, 16, 16][class])?;
+                let mut bytes = Vec::with_capacity(count as usize);
+                for _ in 0..count {
+                    bytes.push(reader.read(8)? as u8);
+                }
+                decoded.data.extend_from_slice(&bytes);
+                match parts.last_mut() {
+                    Some(Part::Bytes(pending, c)) if *c == charset => pending.extend(bytes),
+                    _ => parts.push(Part::Bytes(bytes, charset)),
+                }
+            },
+            0b1000 => {
+                let count = reader.read([8, 10, 12][class])?;
+                let mut sjis = Vec::with_capacity(2 * count as usize);
+                for _ in 0..count {
+                    let value = reader.read(13)?;
+                    let code = ((value / 0xc0) << 8) | (value % 0xc0);
+                    let code = match code < 0x1f00 {
+                        true => code + 0x8140,
+                        false => code + 0xc140,
+                    };
+                    sjis.extend_from_slice(&(code as u16).to_be_bytes());
+                }
+                decoded.data.extend_from_slice(&sjis);
+                kanji = true;
+                let text = SHIFT_JIS.decode_without_bom_handling(&sjis).0;
+                parts.push(Part::Text(text.into_owned()));
+            },
+            0b0111 => {
+                let first = reader.read(8)?;
+                let designator = match first {
+                    0x00..=0x7f => first,
+                    0x80..=0xbf => (first & 0x3f) << 8 | reader.read(8)?,
+                    0xc0..=0xdf => (first & 0x1f) << 16 | reader.read(16)?,
+                    _ => return None,
+                };
+                decoded.eci.get_or_insert(designator);
+                charset = Some(eci_charset(designator)?);
+            },
+            0b0011 => {
+                let index = reader.read(4)? as u8;
+                let total = reader.read(4)? as u8 + 1;
+                let parity = reader.read(8)? as u8;
+                if index >= total {
+                    return None;
+                }
+                decoded.structured_append.get_or_insert(StructuredAppend {
+                    index,
+                    total,
+                    parity,
+                });
+            },
+            0b0101 => decoded.fnc1 = true,
+            0b1001 => {
+                // the application indicator, which is not part of the content
+                reader.read(8)?;
+                decoded.fnc1 = true;
+            },
+            _ => return None,
+        }
+    }
+    decoded.text = parts
+        .into_iter()
+        .map(|part| match part {
+            Part::Text(text) => text,
+            Part::Bytes(bytes, charset) => decode_bytes(&bytes, charset, kanji),
+        })
+        .collect();
+    Some(())
+}
+
+/// The character set of an ECI designator (AIM ECI specification).
+fn eci_charset(designator: u32) -> Option<Charset> {
+    let label: &[u8] = match designator {
+        0..=3 | 27 | 170 => return Some(Charset::Latin1),
+        4 => b"iso-8859-2",
+        5 => b"iso-8859-3",
+        6 => b"iso-8859-4",
+        7 => b"iso-8859-5",
+        8 => b"iso-8859-6",
+        9 => b"iso-8859-7",
+        10 => b"iso-8859-8",
+        11 => b"iso-8859-9",
+        13 => b"iso-8859-11",
+        15 => b"iso-8859-13",
+        16 => b"iso-8859-14",
+        17 => b"iso-8859-15",
+        18 => b"iso-8859-16",
+        20 => b"shift_jis",
+        21 => b"windows-1250",
+        22 => b"windows-1251",
+        23 => b"windows-1252",
+        24 => b"windows-1256",
+        25 => b"utf-16be",
+        26 => b"utf-8",
+        28 => b"big5",
+        29 => b"gb18030",
+        30 => b"euc-kr",
+        _ => return None,
+    };
+    Encoding::for_label(label).map(Charset::Other)
+}
+
+/// Decodes bytes in the charset of an ECI, or guesses it: UTF-8 if they are valid UTF-8,
+/// Shift-JIS if there are Kanji segments, and else ISO-8859-1 as the standard says.
+fn decode_bytes(bytes: &[u8], charset: Option<Charset>, kanji: bool) -> String {
+    let latin1 = || bytes.iter().map(|&b| char::from(b)).collect();
+    match charset {
+        Some(Charset::Latin1) => latin1(),
+        Some(Charset::Other(charset)) => charset.decode_without_bom_handling(bytes).0.into_owned(),
+        None => match std::str::from_utf8(bytes) {
+            Ok(text) => text.to_owned(),
+            Err(_) => match SHIFT_JIS.decode_without_bom_handling_and_without_replacement(bytes) {
+                Some(text) if kanji => text.into_owned(),
+                _ => latin1(),
+            },
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Luma;
+    use qrcode::{QrCode, Version};
+
+    use super::*;
+    use crate::encode::{self, Options};
+    use crate::symbol::Symbol;
+
+    fn matrix(code: &QrCode) -> Matrix {
+        Matrix {
+            width: code.width(),
+            modules: code
+                .to_colors()
+                .iter()
+                .map(|c| *c == qrcode::Color::Dark)
+                .collect(),
+        }
+    }
+
+    fn symbol_matrix(symbol: &Symbol) -> Matrix {
+        let image = symbol
+            .render::<Luma<u8>>()
+            .quiet_zone(false)
+            .module_dimensions(1, 1)
+            .build();
+        Matrix {
+            width: symbol.width(),
+            modules: image.pixels().map(|p| p.0[0] < 128).collect(),
+        }
+    }
+
+    #[test]
+    fn every_version() {
+        let levels = [EcLevel::L, EcLevel::M, EcLevel::Q, EcLevel::H];
+        for version in 1..=40 {
+            let ec_level = levels[version as usize % 4];
+            let text = format!("{version}{ec_level:?}ü");
+            let code = QrCode::with_version(&text, Version::Normal(version), ec_level).unwrap();
+            let decoded = decode_matrix(&matrix(&code)).unwrap();
+            assert_eq!(decoded.text, text);
+            assert_eq!(decoded.version, version);
+            assert_eq!(decoded.ec_level, ec_level);
+            assert_eq!(decoded.corrected, 0);
+        }
+    }
+
+    #[test]
+    fn segments_and_charsets() {
+        for (text, charset) in [
+            ("HELLO WORLD 0123456789 hello", encode::Charset::Utf8),
+            ("東京都千代田区 Ｔｏｋｙｏ", encode::Charset::Kanji),
+            ("東京都千代田区 ﾄｳｷｮｳ", encode::Charset::Sjis),
+            ("Straße 7, 14163 Berlin", encode::Charset::Utf8),
+        ] {
+            for eci in [true, false] {
+                let options = Options {
+                    eci,
+                    charset,
+                    ..Options::default()
+                };
+                let encoded = encode::encode(text, EcLevel::M, &options).unwrap();
+                let decoded = decode_matrix(&symbol_matrix(&encoded.symbol)).unwrap();
+                assert_eq!(decoded.text, text, "{charset:?}, eci: {eci}");
+            }
+        }
+
+        let options = Options {
+            fnc1: true,
+            ..Options::default()
+        };
+        let gs1 = "0104012345678901\x1d10AB%12";
+        let encoded = encode::encode(gs1, EcLevel::M, &options).unwrap();
+        let decoded = decode_matrix(&symbol_matrix(&encoded.symbol)).unwrap();
+        assert!(decoded.fnc1);
+        assert_eq!(decoded.text, gs1);
+    }
+
+    #[test]
+    fn corrects_damage() {
+        let code =
+            QrCode::with_error_correction_level("https://www.fu-berlin.de/", EcLevel::H).unwrap();
+        let mut damaged = matrix(&code);
+        let width = damaged.width;
+        // a blot in the centre, like a logo
+        for y in width / 2 - 3..width / 2 + 3 {
+            for x in width / 2 - 3..width / 2 + 3 {
+                damaged.modules[y * width + x] = false;
+            }
+        }
+        let decoded = decode_matrix(&damaged).unwrap();
+        assert_eq!(decoded.text, "https://www.fu-berlin.de/");
+        assert!(decoded.corrected > 0);
+
+        let mirrored = decode_matrix(&matrix(&code).transposed()).unwrap();
+        assert_eq!(mirrored.text, "https://www.fu-berlin.de/");
+    }
+
+    #[test]
+    fn reads_files() {
+        let text = "https://www.vetmed.fu-berlin.de/";
+        let code = QrCode::new(text).unwrap();
+        let image = code.render::<Luma<u8>>().module_dimensions(3, 3).build();
+        let mut jpeg = Vec::new();
+        image::jpeg::JpegEncoder::new_with_quality(&mut jpeg, 40)
+            .encode(
+                image.as_raw(),
+                image.width(),
+                image.height(),
+                image::ColorType::L8,
+            )
+            .unwrap();
+        assert_eq!(decode_file(&jpeg).unwrap()[0].text, text);
+
+        // dark modules on a transparent background
+        let transparent = code
+            .render::<LumaA<u8>>()
+            .light_color(LumaA([0, 0]))
+            .build();
+        let mut png = Vec::new();
+        image::png::PngEncoder::new(&mut png)
+            .encode(
+                transparent.as_raw(),
+                transparent.width(),
+                transparent.height(),
+                image::ColorType::La8,
+            )
+            .unwrap();
+        assert_eq!(decode_file(&png).unwrap()[0].text, text);
+
+        assert!(matches!(decode_file(b"GIF89a"), Err(DecodeError::Image(_))));
+    }
+}
@@ -0,0 +1,97 @@
+//! Projective transforms, which map the plane of a symbol to the picture of it.
+
+/// A projective transform of the plane.
+#[derive(Debug, Clone, Copy)]
+pub struct Perspective([f64; 9]);
+
+impl Perspective {
+    /// Maps the unit square to a quadrilateral, corners in the order (0, 0), (1, 0), (1, 1),
+    /// (0, 1).
+    fn square_to_quad(q: [(f64, f64); 4]) -> Self {
+        let [(x0, y0), (x1, y1), (x2, y2), (x3, y3)] = q;
+        let (dx3, dy3) = (x0 - x1 + x2 - x3, y0 - y1 + y2 - y3);
+        if dx3 == 0.0 && dy3 == 0.0 {
+            return Self([x1 - x0, x2 - x1, x0, y1 - y0, y2 - y1, y0, 0.0, 0.0, 1.0]);
+        }
+        let (dx1, dx2, dy1, dy2) = (x1 - x2, x3 - x2, y1 - y2, y3 - y2);
+        let denominator = dx1 * dy2 - dx2 * dy1;
+        let a13 = (dx3 * dy2 - dx2 * dy3) / denominator;
+        let a23 = (dx1 * dy3 - dx3 * dy1) / denominator;
+        Self([
+            x1 - x0 + a13 * x1,
+            x3 - x0 + a23 * x3,
+            x0,
+            y1 - y0 + a13 * y1,
+            y3 - y0 + a23 * y3,
+            y0,
+            a13,
+            a23,
+            1.0,
+        ])
+    }
+
+    /// Maps the corners of `from` to those of `to`, both in the order of
+    /// [`Self::square_to_quad`].
+    pub fn quad_to_quad(from: [(f64, f64); 4], to: [(f64, f64); 4]) -> Self {
+        Self::square_to_quad(to).times(&Self::square_to_quad(from).adjoint())
+    }
+
+    fn adjoint(&self) -> Self {
+        let [a11, a21, a31, a12, a22, a32, a13, a23, a33] = self.0;
+        Self([
+            a22 * a33 - a23 * a32,
+            a23 * a31 - a21 * a33,
+            a21 * a32 - a22 * a31,
+            a13 * a32 - a12 * a33,
+            a11 * a33 - a13 * a31,
+            a12 * a31 - a11 * a32,
+            a12 * a23 - a13 * a22,
+            a13 * a21 - a11 * a23,
+            a11 * a22 - a12 * a21,
+        ])
+    }
+
+    fn times(&self, other: &Self) -> Self {
+        let [a11, a21, a31, a12, a22, a32, a13, a23, a33] = self.0;
+        let [b11, b21, b31, b12, b22, b32, b13, b23, b33] = other.0;
+        Self([
+            a11 * b11 + a21 * b12 + a31 * b13,
+            a11 * b21 + a21 * b22 + a31 * b23,
+            a11 * b31 + a21 * b32 + a31 * b33,
+            a12 * b11 + a22 * b12 + a32 * b13,
+            a12 * b21 + a22 * b22 + a32 * b23,
+            a12 * b31 + a22 * b32 + a32 * b33,
+            a13 * b11 + a23 * b12 + a33 * b13,
+            a13 * b21 + a23 * b22 + a33 * b23,
+            a13 * b31 + a23 * b32 + a33 * b33,
+        ])
+    }
+
+    pub fn apply(&self, x: f64, y: f64) -> (f64, f64) {
+        let [a11, a21, a31, a12, a22, a32, a13, a23, a33] = self.0;
+        let denominator = a13 * x + a23 * y + a33;
+        (
+            (a11 * x + a21 * y + a31) / denominator,
+            (a12 * x + a22 * y + a32) / denominator,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_corners() {
+        let t = Perspective::quad_to_quad([(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)], [
+            (5.0, 5.0),
+            (25.0, 7.0),
+            (27.0, 30.0),
+            (4.0, 24.0),
+        ]);
+        let (x, y) = t.apply(10.0, 10.0);
+        assert!((x - 27.0).abs() < 1e-9 && (y - 30.0).abs() < 1e-9);
+        let (x, y) = t.apply(0.0, 10.0);
+        assert!((x - 4.0).abs() < 1e-9 && (y - 24.0).abs() < 1e-9);
+    }
+}
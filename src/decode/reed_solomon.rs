@@ -0,0 +1,174 @@
+//! Error correction of QR code blocks, Reed-Solomon codes over GF(256) with the polynomial
+//! x⁸ + x⁴ + x³ + x² + 1 and the generator roots α⁰ … αⁿ⁻¹.
+
+const EXP: [u8; 512] = {
+    let mut exp = [0; 512];
+    let mut x = 1_u16;
+    let mut i = 0;
+    while i < exp.len() {
+        exp[i] = x as u8;
+        x <<= 1;
+        if x & 0x100 != 0 {
+            x ^= 0x11d;
+        }
+        i += 1;
+    }
+    exp
+};
+
+const LOG: [u8; 256] = {
+    let mut log = [0; 256];
+    let mut i = 0;
+    while i < 255 {
+        log[EXP[i] as usize] = i as u8;
+        i += 1;
+    }
+    log
+};
+
+fn mul(a: u8, b: u8) -> u8 {
+    match a == 0 || b == 0 {
+        true => 0,
+        false => EXP[LOG[a as usize] as usize + LOG[b as usize] as usize],
+    }
+}
+
+fn div(a: u8, b: u8) -> u8 {
+    debug_assert_ne!(b, 0);
+    match a == 0 {
+        true => 0,
+        false => EXP[LOG[a as usize] as usize + 255 - LOG[b as usize] as usize],
+    }
+}
+
+/// α to the power of `n`, which may be negative.
+fn pow(n: isize) -> u8 {
+    EXP[n.rem_euclid(255) as usize]
+}
+
+/// Evaluates a polynomial with the coefficient of x⁰ first.
+fn eval(poly: &[u8], x: u8) -> u8 {
+    poly.iter().rev().fold(0, |acc, &c| mul(acc, x) ^ c)
+}
+
+/// Corrects a block of data and error correction codewords in place.
+///
+/// Returns the number of corrected codewords, or `None` if there are too many errors.
+pub fn correct(block: &mut [u8], ec_len: usize) -> Option<usize> {
+    let n = block.len();
+    // the codewords are the coefficients of the received polynomial, highest degree first
+    let syndromes: Vec<u8> = (0..ec_len)
+        .map(|i| {
+            block
+                .iter()
+                .fold(0, |acc, &c| mul(acc, pow(i as isize)) ^ c)
+        })
+        .collect();
+    if syndromes.iter().all(|&s| s == 0) {
+        return Some(0);
+    }
+
+    // Berlekamp-Massey: the error locator Λ(x) with the roots X⁻¹ of the error locations X
+    let mut locator = vec![1_u8];
+    let mut previous = vec![1_u8];
+    let (mut errors, mut shift, mut discrepancy_before) = (0, 1, 1_u8);
+    for k in 0..ec_len {
+        let mut discrepancy = syndromes[k];
+        for i in 1..=errors.min(locator.len() - 1) {
+            discrepancy ^= mul(locator[i], syndromes[k - i]);
+        }
+        if discrepancy == 0 {
+            shift += 1;
+            continue;
+        }
+        let factor = div(discrepancy, discrepancy_before);
+        let mut next = locator.clone();
+        next.resize(next.len().max(previous.len() + shift), 0);
+        for (i, &c) in previous.iter().enumerate() {
+            next[i + shift] ^= mul(factor, c);
+        }
+        if 2 * errors <= k {
+            previous = std::mem::replace(&mut locator, next);
+            errors = k + 1 - errors;
+            discrepancy_before = discrepancy;
+            shift = 1;
+        } else {
+            locator = next;
+            shift += 1;
+        }
+    }
+    if 2 * errors > ec_len {
+        return None;
+    }
+
+    // Chien search, codeword k is the coefficient of x^(n - 1 - k)
+    let positions: Vec<usize> = (0..n)
+        .filter(|&k| eval(&locator, pow(-((n - 1 - k) as isize))) == 0)
+        .collect();
+    if positions.len() != errors {
+        return None;
+    }
+
+    // Forney: the error evaluator Ω(x) = S(x) Λ(x) mod x^ec_len and the derivative Λ'(x)
+    let mut evaluator = vec![0_u8; ec_len];
+    for (i, &s) in syndromes.iter().enumerate() {
+        for (j, &l) in locator.iter().enumerate().take(ec_len - i) {
+            evaluator[i + j] ^= mul(s, l);
+        }
+    }
+    let derivative: Vec<u8> = locator
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(i, &c)| if i % 2 == 1 { c } else { 0 })
+        .collect();
+    for &k in &positions {
+        let x = pow((n - 1 - k) as isize);
+        let x_inv = div(1, x);
+        let denominator = eval(&derivative, x_inv);
+        if denominator == 0 {
+            return None;
+        }
+        block[k] ^= mul(x, div(eval(&evaluator, x_inv), denominator));
+    }
+
+    let corrected = (0..ec_len).all(|i| {
+        block
+            .iter()
+            .fold(0, |acc, &c| mul(acc, pow(i as isize)) ^ c)
+            == 0
+    });
+    corrected.then_some(errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use qrcode::ec::create_error_correction_code;
+
+    use super::*;
+
+    #[test]
+    fn corrects_up_to_half_the_ec_codewords() {
+        let data: Vec<u8> = (0..40).map(|i| (i * 37 + 11) as u8).collect();
+        let ec = create_error_correction_code(&data, 18);
+        let block: Vec<u8> = data.iter().chain(&ec).copied().collect();
+
+        let mut clean = block.clone();
+        assert_eq!(correct(&mut clean, 18), Some(0));
+
+        for errors in 1..=9 {
+            let mut damaged = block.clone();
+            for e in 0..errors {
+                damaged[e * 6 + 1] ^= 0x5a + e as u8;
+            }
+            assert_eq!(correct(&mut damaged, 18), Some(errors));
+            assert_eq!(damaged, block);
+        }
+
+        let mut hopeless = block.clone();
+        for byte in hopeless.iter_mut().take(20) {
+            *byte ^= 0xff;
+        }
+        assert_ne!(correct(&mut hopeless, 18), Some(0));
+    }
+}
@@ -0,0 +1,688 @@
+use std::ops::RangeInclusive;
+
+use encoding_rs::SHIFT_JIS;
+use qrcode::bits::Bits;
+use qrcode::types::{Mode, QrError};
+use qrcode::{EcLevel, Version};
+use serde::Deserialize;
+
+use crate::datamatrix::GS;
+use crate::symbol::{Symbol, MASK_COUNT};
+
+/// ECI assignment number of Shift-JIS.
+const ECI_SJIS: u32 = 20;
+
+/// ECI assignment number of UTF-8.
+const ECI_UTF8: u32 = 26;
+
+/// Versions sharing the same widths of the character count indicators.
+const VERSION_CLASSES: [(i16, i16); 3] = [(1, 9), (10, 26), (27, 40)];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Pin {
+    /// Use the pinned version or any larger one.
+    #[default]
+    Min,
+    /// Use exactly the pinned version.
+    Exact,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Charset {
+    /// UTF-8 in byte mode only.
+    #[default]
+    Utf8,
+    /// UTF-8 in byte mode, mixed with Kanji mode for characters in the Shift-JIS Kanji range.
+    ///
+    /// Only for readers that handle Kanji segments next to the UTF-8 ECI, which many do not.
+    #[serde(alias = "auto")]
+    Kanji,
+    /// Shift-JIS in Kanji and byte mode, as expected by many Japanese readers.
+    ///
+    /// Falls back to [`Charset::Utf8`] if the text cannot be represented in Shift-JIS.
+    Sjis,
+}
+
+impl Charset {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Utf8 => "utf8",
+            Self::Kanji => "kanji",
+            Self::Sjis => "sjis",
+        }
+    }
+
+    fn eci(self) -> u32 {
+        match self {
+            Self::Utf8 | Self::Kanji => ECI_UTF8,
+            Self::Sjis => ECI_SJIS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Options {
+    /// Prefix the data with an ECI designator of the charset if it contains non-ASCII text.
+    pub eci: bool,
+    pub charset: Charset,
+    pub version: Option<i16>,
+    pub pin: Pin,
+    pub mask: Option<u8>,
+    /// The largest version that the format of the data allows.
+    pub max_version: Option<i16>,
+    /// Mark the data as GS1 element string, in which [`GS`] separates variable length fields.
+    pub fnc1: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            eci: true,
+            charset: Charset::default(),
+            version: None,
+            pin: Pin::default(),
+            mask: None,
+            max_version: None,
+            fnc1: false,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum EncodeError {
+    #[error("Die Daten sind zu lang für einen QR-Code.")]
+    DataTooLong,
+    #[error(
+        "Die Daten benötigen mindestens Version {required}, passen also nicht in Version {pinned}."
+    )]
+    VersionTooSmall { pinned: i16, required: i16 },
+    #[error("Dieses Format erlaubt höchstens Version {max}, nicht {pinned}.")]
+    VersionTooLarge { pinned: i16, max: i16 },
+    #[error("Die Version {0} gibt es nicht, erlaubt sind 1 bis 40.")]
+    InvalidVersion(i16),
+    #[error("Das Maskenmuster {0} gibt es nicht, erlaubt sind 0 bis 7.")]
+    InvalidMask(u8),
+    #[error("Der QR-Code konnte nicht erzeugt werden: {0}")]
+    Qr(#[source] QrError),
+}
+
+impl From<QrError> for EncodeError {
+    fn from(err: QrError) -> Self {
+        match err {
+            QrError::DataTooLong => Self::DataTooLong,
+            err => Self::Qr(err),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Segment {
+    pub mode: Mode,
+    pub text: String,
+    /// The bytes written into the symbol, i.e. Shift-JIS for Kanji segments.
+    data: Vec<u8>,
+    chars: usize,
+}
+
+impl Segment {
+    /// Number of bits needed to store the segment, including its header.
+    pub fn encoded_len(&self, version: Version) -> usize {
+        version.mode_bits_count()
+            + self.mode.length_bits_count(version)
+            + self.mode.data_bits_count(self.chars)
+    }
+}
+
+pub fn mode_name(mode: Mode) -> &'static str {
+    match mode {
+        Mode::Numeric => "numeric",
+        Mode::Alphanumeric => "alphanumeric",
+        Mode::Byte => "byte",
+        Mode::Kanji => "kanji",
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Encoded {
+    pub symbol: Symbol,
+    pub segments: Vec<Segment>,
+    /// The used charset, which differs from the requested one after a fallback.
+    pub charset: Charset,
+    pub eci: Option<u32>,
+    /// Number of used data bits, without terminator and padding.
+    pub bits: usize,
+    /// Number of available data bits in the chosen version.
+    pub capacity: usize,
+}
+
+/// Encodes `text` in the smallest allowed version, using the optimal sequence of segments.
+pub fn encode(text: &str, ec_level: EcLevel, options: &Options) -> Result<Encoded, EncodeError> {
+    let (charset, units): (_, Vec<_>) = match options.charset {
+        Charset::Utf8 => (Charset::Utf8, text.chars().map(Unit::utf8).collect()),
+        Charset::Kanji => (
+            Charset::Kanji,
+            text.chars().map(Unit::utf8_or_kanji).collect(),
+        ),
+        Charset::Sjis => match text.chars().map(Unit::sjis).collect() {
+            Some(units) => (Charset::Sjis, units),
+            None => (Charset::Utf8, text.chars().map(Unit::utf8).collect()),
+        },
+    };
+    let units: Vec<_> = units
+        .into_iter()
+        .map(|unit| Unit {
+            fnc1: options.fnc1,
+            ..unit
+        })
+        .collect();
+    let header = Header {
+        eci: options.eci.then(|| charset.eci()),
+        fnc1: options.fnc1,
+    };
+    encode_units(&units, charset, header, ec_level, options)
+}
+
+/// Encodes binary data as is, without an ECI designator, which would claim it to be text.
+pub fn encode_bytes(
+    data: &[u8],
+    ec_level: EcLevel,
+    options: &Options,
+) -> Result<Encoded, EncodeError> {
+    let units: Vec<_> = data.iter().copied().map(Unit::byte).collect();
+    let header = Header {
+        eci: None,
+        fnc1: false,
+    };
+    encode_units(&units, options.charset, header, ec_level, options)
+}
+
+fn encode_units(
+    units: &[Unit],
+    charset: Charset,
+    header: Header,
+    ec_level: EcLevel,
+    options: &Options,
+) -> Result<Encoded, EncodeError> {
+    if let Some(mask) = options.mask {
+        if mask >= MASK_COUNT {
+            return Err(EncodeError::InvalidMask(mask));
+        }
+    }
+    let max = options.max_version.unwrap_or(40);
+    let versions = match (options.version, options.pin) {
+        (None, _) => 1..=max,
+        (Some(version @ 1..=40), _) if version > max => {
+            return Err(EncodeError::VersionTooLarge {
+                pinned: version,
+                max,
+            })
+        },
+        (Some(version @ 1..=40), Pin::Min) => version..=max,
+        (Some(version @ 1..=40), Pin::Exact) => version..=version,
+        (Some(version), _) => return Err(EncodeError::InvalidVersion(version)),
+    };
+
+    if let Some(plan) = Plan::find(units, ec_level, header, &versions)? {
+        return plan.build(ec_level, charset, options.mask);
+    }
+    match Plan::find(units, ec_level, header, &(1..=40))? {
+        Some(plan) => Err(EncodeError::VersionTooSmall {
+            pinned: *versions.end(),
+            required: plan.version,
+        }),
+        None => Err(EncodeError::DataTooLong),
+    }
+}
+
+/// The designators preceding the segments.
+#[derive(Debug, Clone, Copy)]
+struct Header {
+    eci: Option<u32>,
+    fnc1: bool,
+}
+
+/// The segments of the data and the smallest version they fit in.
+struct Plan {
+    version: i16,
+    segments: Vec<Segment>,
+    eci: Option<u32>,
+    fnc1: bool,
+    bits: usize,
+    capacity: usize,
+}
+
+impl Plan {
+    fn find(
+        units: &[Unit],
+        ec_level: EcLevel,
+        header: Header,
+        versions: &RangeInclusive<i16>,
+    ) -> Result<Option<Self>, EncodeError> {
+        for (first, last) in VERSION_CLASSES {
+            let first = first.max(*versions.start());
+            let last = last.min(*versions.end());
+            if first > last {
+                continue;
+            }
+
+            let segments = segment(units, Version::Normal(first));
+            let eci = header.eci.filter(|_| needs_eci(&segments));
+            let fnc1_len = if header.fnc1 { 4 } else { 0 };
+            let bits =
+                eci_len(eci) + fnc1_len + total_encoded_len(&segments, Version::Normal(first));
+            for version in first..=last {
+                let capacity = Bits::new(Version::Normal(version)).max_len(ec_level)?;
+                if bits <= capacity {
+                    return Ok(Some(Self {
+                        version,
+                        segments,
+                        eci,
+                        fnc1: header.fnc1,
+                        bits,
+                        capacity,
+                    }));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn build(
+        self,
+        ec_level: EcLevel,
+        charset: Charset,
+        mask: Option<u8>,
+    ) -> Result<Encoded, EncodeError> {
+        let version = Version::Normal(self.version);
+        let mut bits = Bits::new(version);
+        if let Some(eci) = self.eci {
+            bits.push_eci_designator(eci)?;
+        }
+        if self.fnc1 {
+            bits.push_fnc1_first_position()?;
+        }
+        for segment in &self.segments {
+            match segment.mode {
+                Mode::Numeric => bits.push_numeric_data(&segment.data),
+                Mode::Alphanumeric => bits.push_alphanumeric_data(&segment.data),
+                Mode::Byte => bits.push_byte_data(&segment.data),
+                Mode::Kanji => bits.push_kanji_data(&segment.data),
+            }?;
+        }
+        bits.push_terminator(ec_level)?;
+        let symbol = Symbol::new(&bits.into_bytes(), version, ec_level, mask)?;
+        Ok(Encoded {
+            symbol,
+            segments: self.segments,
+            charset,
+            eci: self.eci,
+            bits: self.bits,
+            capacity: self.capacity,
+        })
+    }
+}
+
+fn needs_eci(segments: &[Segment]) -> bool {
+    segments
+        .iter()
+        .any(|s| s.mode == Mode::Byte && !s.data.is_ascii())
+}
+
+fn eci_len(eci: Option<u32>) -> usize {
+    match eci {
+        // mode indicator + 8 bit designator, assignment numbers up to 127
+        Some(0..=127) => 4 + 8,
+        Some(0..=16383) => 4 + 16,
+        Some(_) => 4 + 24,
+        None => 0,
+    }
+}
+
+fn total_encoded_len(segments: &[Segment], version: Version) -> usize {
+    segments.iter().map(|s| s.encoded_len(version)).sum()
+}
+
+/// A single input character and the modes it can be encoded in.
+#[derive(Debug, Clone, Copy)]
+struct Unit {
+    c: char,
+    /// The encoded character in byte mode.
+    bytes: [u8; 4],
+    len: usize,
+    /// The Shift-JIS code of the character if it can be encoded in Kanji mode.
+    kanji: Option<[u8; 2]>,
+    /// Readers that expect Shift-JIS also expect Kanji characters in Kanji mode.
+    kanji_only: bool,
+    /// The data is a GS1 element string, which changes the meaning of `%` in alphanumeric mode.
+    fnc1: bool,
+}
+
+impl Unit {
+    fn utf8(c: char) -> Self {
+        let mut bytes = [0; 4];
+        let len = c.encode_utf8(&mut bytes).len();
+        Self {
+            c,
+            bytes,
+            len,
+            kanji: None,
+            kanji_only: false,
+            fnc1: false,
+        }
+    }
+
+    /// A byte of binary data, shown as itself if it is printable ASCII.
+    fn byte(byte: u8) -> Self {
+        let c = match byte.is_ascii_graphic() || byte == b' ' {
+            true => char::from(byte),
+            false => '·',
+        };
+        Self {
+            c,
+            bytes: [byte, 0, 0, 0],
+            len: 1,
+            kanji: None,
+            kanji_only: false,
+            fnc1: false,
+        }
+    }
+
+    fn utf8_or_kanji(c: char) -> Self {
+        Self {
+            kanji: Self::sjis(c).and_then(|unit| unit.kanji),
+            ..Self::utf8(c)
+        }
+    }
+
+    fn sjis(c: char) -> Option<Self> {
+        let mut buf = [0; 4];
+        let (encoded, _, had_errors) = SHIFT_JIS.encode(c.encode_utf8(&mut buf));
+        if had_errors {
+            return None;
+        }
+        let mut bytes = [0; 4];
+        let kanji = match *encoded {
+            [byte] => {
+                bytes[0] = byte;
+                None
+            },
+            [hi, lo] => {
+                bytes[..2].copy_from_slice(&[hi, lo]);
+                match u16::from_be_bytes([hi, lo]) {
+                    0x8140..=0x9ffc | 0xe040..=0xebbf => Some([hi, lo]),
+                    _ => None,
+                }
+            },
+            _ => return None,
+        };
+        Some(Self {
+            c,
+            bytes,
+            len: encoded.len(),
+            kanji,
+            kanji_only: kanji.is_some(),
+            fnc1: false,
+        })
+    }
+
+    fn bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+
+    /// The character in alphanumeric mode, if it can be encoded in that mode.
+    fn alphanumeric(&self) -> Option<&[u8]> {
+        match (self.c, self.fnc1) {
+            (GS, true) => Some(b"%"),
+            ('%', true) => Some(b"%%"),
+            (c, _) if is_alphanumeric(c) => Some(self.bytes()),
+            _ => None,
+        }
+    }
+
+    /// Cost of the character in a `mode` in sixths of a bit, if it can be encoded in that mode.
+    fn cost(self, mode: Mode) -> Option<usize> {
+        match mode {
+            Mode::Numeric => self.c.is_ascii_digit().then_some(20),
+            Mode::Alphanumeric => self.alphanumeric().map(|data| 33 * data.len()),
+            Mode::Byte => (!self.kanji_only).then_some(48 * self.len),
+            Mode::Kanji => self.kanji.map(|_| 78),
+        }
+    }
+}
+
+const MODES: [Mode; 4] = [Mode::Numeric, Mode::Alphanumeric, Mode::Byte, Mode::Kanji];
+
+fn is_alphanumeric(c: char) -> bool {
+    matches!(c, '0'..='9' | 'A'..='Z' | ' ' | '$' | '%' | '*' | '+' | '-' | '.' | '/' | ':')
+}
+
+/// Finds the sequence of segments with the shortest encoded length for `version`.
+///
+/// Every character is assigned the mode that minimizes the total cost of the prefix ending in
+/// it, including the header costs of switching modes. Costs are computed in sixths of a bit, so
+/// that numeric (10 bits per 3 chars) and alphanumeric (11 bits per 2 chars) data are exact.
+fn segment(units: &[Unit], version: Version) -> Vec<Segment> {
+    let head = MODES.map(|mode| 6 * (version.mode_bits_count() + mode.length_bits_count(version)));
+
+    let mut costs: [Option<usize>; 4] = [Some(0); 4];
+    let mut from: Vec<[usize; 4]> = Vec::with_capacity(units.len());
+    for (index, unit) in units.iter().enumerate() {
+        let mut next = [None; 4];
+        let mut prev = [0; 4];
+        for (m, &mode) in MODES.iter().enumerate() {
+            let Some(cost) = unit.cost(mode) else { continue };
+            for (p, &prev_cost) in costs.iter().enumerate() {
+                let Some(prev_cost) = prev_cost else { continue };
+                let switch = match index > 0 && p == m {
+                    true => 0,
+                    false => head[m],
+                };
+                let total = prev_cost + switch + cost;
+                if next[m].is_none_or(|best| total < best) {
+                    next[m] = Some(total);
+                    prev[m] = p;
+                }
+            }
+        }
+        costs = next;
+        from.push(prev);
+    }
+
+    let mut modes = vec![0; units.len()];
+    let mut current = (0..4)
+        .filter_map(|m| Some((costs[m]?, m)))
+        .min()
+        .map_or(0, |(_, m)| m);
+    for (index, prev) in from.iter().enumerate().rev() {
+        modes[index] = current;
+        current = prev[current];
+    }
+
+    let mut segments: Vec<Segment> = Vec::new();
+    for (unit, m) in units.iter().zip(modes) {
+        let mode = MODES[m];
+        let segment = match segments.last_mut() {
+            Some(segment) if segment.mode == mode => segment,
+            _ => {
+                segments.push(Segment {
+                    mode,
+                    text: String::new(),
+                    data: Vec::new(),
+                    chars: 0,
+                });
+                segments.last_mut().unwrap()
+            },
+        };
+        segment.text.push(unit.c);
+        match (mode, unit.kanji, unit.alphanumeric()) {
+            (Mode::Kanji, Some(kanji), _) => {
+                segment.data.extend_from_slice(&kanji);
+                segment.chars += 1;
+            },
+            (Mode::Alphanumeric, _, Some(data)) => {
+                segment.data.extend_from_slice(data);
+                segment.chars += data.len();
+            },
+            _ => {
+                segment.data.extend_from_slice(unit.bytes());
+                segment.chars += unit.len;
+            },
+        }
+    }
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn modes(text: &str, version: i16) -> Vec<Mode> {
+        let units: Vec<_> = text.chars().map(Unit::utf8).collect();
+        segment(&units, Version::Normal(version))
+            .iter()
+            .map(|segment| segment.mode)
+            .collect()
+    }
+
+    /// The length of the data as written by [`Bits`], without terminator and padding.
+    fn written_len(encoded: &Encoded) -> usize {
+        let mut bits = Bits::new(encoded.symbol.version());
+        if let Some(eci) = encoded.eci {
+            bits.push_eci_designator(eci).unwrap();
+        }
+        for segment in &encoded.segments {
+            match segment.mode {
+                Mode::Numeric => bits.push_numeric_data(&segment.data),
+                Mode::Alphanumeric => bits.push_alphanumeric_data(&segment.data),
+                Mode::Byte => bits.push_byte_data(&segment.data),
+                Mode::Kanji => bits.push_kanji_data(&segment.data),
+            }
+            .unwrap();
+        }
+        bits.len()
+    }
+
+    #[test]
+    fn switches_modes_at_class_boundaries() {
+        use Mode::{Byte, Numeric};
+
+        // the character count indicators of numeric and byte mode grow by 2 and 8 bits in
+        // version 10, which makes a numeric segment of 7 digits too expensive
+        assert_eq!(modes("a1234567a", 9), [Byte, Numeric, Byte]);
+        assert_eq!(modes("a1234567a", 10), [Byte]);
+        // and 8 digits from version 27 on
+        assert_eq!(modes("a12345678a", 26), [Byte, Numeric, Byte]);
+        assert_eq!(modes("a12345678a", 27), [Byte]);
+        assert_eq!(modes("a123456789a", 27), [Byte, Numeric, Byte]);
+
+        for (text, version) in [("a1234567a", 9), ("a1234567a", 10), ("a12345678a", 27)] {
+            let options = Options {
+                version: Some(version),
+                pin: Pin::Exact,
+                ..Options::default()
+            };
+            let encoded = encode(text, EcLevel::L, &options).unwrap();
+            assert_eq!(encoded.symbol.version(), Version::Normal(version));
+            assert_eq!(encoded.bits, written_len(&encoded), "{text} in {version}");
+        }
+    }
+
+    #[test]
+    fn optimal_segments() {
+        let text = "HELLO WORLD 0123456789012345 hello https://FU-BERLIN.DE/";
+        let encoded = encode(text, EcLevel::M, &Options::default()).unwrap();
+        let modes: Vec<_> = encoded.segments.iter().map(|s| s.mode).collect();
+        assert_eq!(modes, [
+            Mode::Alphanumeric,
+            Mode::Numeric,
+            Mode::Byte,
+            Mode::Alphanumeric
+        ]);
+        assert_eq!(encoded.eci, None);
+        assert_eq!(encoded.bits, written_len(&encoded));
+
+        // never longer than a single byte segment
+        let bytes = Segment {
+            mode: Mode::Byte,
+            text: text.to_owned(),
+            data: text.as_bytes().to_vec(),
+            chars: text.len(),
+        };
+        assert!(encoded.bits <= bytes.encoded_len(encoded.symbol.version()));
+    }
+
+    #[test]
+    fn kanji_segments() {
+        // UTF-8 only, unless Kanji mode is asked for
+        let encoded = encode("東京都 tokyo", EcLevel::M, &Options::default()).unwrap();
+        let modes: Vec<_> = encoded.segments.iter().map(|s| s.mode).collect();
+        assert_eq!(modes, [Mode::Byte]);
+        assert_eq!(encoded.charset, Charset::Utf8);
+        assert_eq!(encoded.eci, Some(ECI_UTF8));
+
+        let options = Options {
+            charset: Charset::Kanji,
+            ..Options::default()
+        };
+        let encoded = encode("東京都 tokyo", EcLevel::M, &options).unwrap();
+        let modes: Vec<_> = encoded.segments.iter().map(|s| s.mode).collect();
+        assert_eq!(modes, [Mode::Kanji, Mode::Byte]);
+        assert_eq!(encoded.segments[0].data, [
+            0x93, 0x8c, 0x8b, 0x9e, 0x93, 0x73
+        ]);
+        // the byte segment is ASCII, so it needs no ECI
+        assert_eq!(encoded.eci, None);
+        assert_eq!(encoded.bits, written_len(&encoded));
+
+        let options = Options {
+            charset: Charset::Sjis,
+            ..Options::default()
+        };
+        let encoded = encode("東京 ﾄｳｷｮｳ", EcLevel::M, &options).unwrap();
+        assert_eq!(encoded.charset, Charset::Sjis);
+        assert_eq!(encoded.eci, Some(ECI_SJIS));
+        assert_eq!(encoded.segments[1].data, [
+            b' ', 0xc4, 0xb3, 0xb7, 0xae, 0xb3
+        ]);
+        assert_eq!(encoded.bits, written_len(&encoded));
+
+        // not in Shift-JIS
+        let encoded = encode("東京 🗼", EcLevel::M, &options).unwrap();
+        assert_eq!(encoded.charset, Charset::Utf8);
+        assert_eq!(encoded.eci, Some(ECI_UTF8));
+    }
+
+    #[test]
+    fn charset_names() {
+        let parse = |name: &str| serde_json::from_value::<Charset>(name.into()).unwrap();
+        assert_eq!(parse("utf8"), Charset::default());
+        assert_eq!(parse("kanji"), Charset::Kanji);
+        // the former name in exported settings
+        assert_eq!(parse("auto"), Charset::Kanji);
+        for charset in [Charset::Utf8, Charset::Kanji, Charset::Sjis] {
+            assert_eq!(parse(charset.name()), charset);
+        }
+    }
+
+    #[test]
+    fn eci() {
+        let encoded = encode("Grüße", EcLevel::M, &Options::default()).unwrap();
+        assert_eq!(encoded.eci, Some(ECI_UTF8));
+        assert_eq!(encoded.bits, 12 + 4 + 8 + 8 * "Grüße".len());
+        assert_eq!(encoded.bits, written_len(&encoded));
+
+        let options = Options {
+            eci: false,
+            ..Options::default()
+        };
+        let encoded = encode("Grüße", EcLevel::M, &options).unwrap();
+        assert_eq!(encoded.eci, None);
+        assert_eq!(encoded.bits, 4 + 8 + 8 * "Grüße".len());
+
+        assert_eq!(eci_len(Some(26)), 12);
+        assert_eq!(eci_len(Some(899)), 20);
+        assert_eq!(eci_len(Some(20_000)), 28);
+    }
+}
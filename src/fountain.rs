@@ -0,0 +1,359 @@
+//! A fountain code (LT code) that spreads a file over an endless sequence of chunks, so that a
+//! receiver can restore it from any chunks it happens to see, a few more than the file has
+//! blocks.
+//!
+//! Each chunk carries a header with the length and the checksum of the file, the block length
+//! and a seed. The seed selects the blocks that are XORed into the chunk: seeds below the
+//! number of blocks carry the blocks as they are, all others a random mix.
+
+use std::collections::HashSet;
+
+use sha2::{Digest, Sha256};
+
+use crate::payload::file::MAX_ANIMATED_LEN;
+
+const MAGIC: &[u8; 3] = b"QRF";
+pub const HEADER_LEN: usize = MAGIC.len() + 4 + 4 + 2 + 4;
+/// More blocks than this hint at a damaged header, and would take the decoder long to sort out.
+pub const MAX_BLOCKS: usize = 8192;
+
+/// Parameters of the robust soliton distribution.
+const C: f64 = 0.1;
+const DELTA: f64 = 0.5;
+
+#[derive(Debug, thiserror::Error)]
+pub enum FountainError {
+    #[error("Die Bilder enthalten Teile verschiedener Übertragungen.")]
+    Mismatch,
+    #[error("Es fehlen noch {missing} von {total} Blöcken der Übertragung.")]
+    Incomplete { missing: usize, total: usize },
+    #[error("Die Prüfsumme der zusammengesetzten Daten stimmt nicht.")]
+    Checksum,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    /// The length of the file in bytes.
+    pub len: u32,
+    /// The first four bytes of the SHA-256 digest of the file.
+    pub checksum: u32,
+    pub seed: u32,
+    /// The XOR of the selected blocks, as long as a block.
+    pub data: Vec<u8>,
+}
+
+impl Chunk {
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        let header = bytes.get(..HEADER_LEN)?.strip_prefix(MAGIC)?;
+        let u32_at =
+            |i: usize| u32::from_be_bytes([header[i], header[i + 1], header[i + 2], header[i + 3]]);
+        let block_len = usize::from(u16::from_be_bytes([header[8], header[9]]));
+        let data = &bytes[HEADER_LEN..];
+        let len = u32_at(0);
+        if block_len == 0 || data.len() != block_len || len as usize > MAX_ANIMATED_LEN {
+            return None;
+        }
+        let chunk = Self {
+            len,
+            checksum: u32_at(4),
+            seed: u32_at(10),
+            data: data.to_vec(),
+        };
+        (chunk.blocks() <= MAX_BLOCKS).then_some(chunk)
+    }
+
+    /// The length, checksum and block length, which all chunks of a transfer share.
+    pub fn header(&self) -> (u32, u32, usize) {
+        (self.len, self.checksum, self.data.len())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + self.data.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&self.len.to_be_bytes());
+        bytes.extend_from_slice(&self.checksum.to_be_bytes());
+        bytes.extend_from_slice(&(self.data.len() as u16).to_be_bytes());
+        bytes.extend_from_slice(&self.seed.to_be_bytes());
+        bytes.extend_from_slice(&self.data);
+        bytes
+    }
+
+    fn blocks(&self) -> usize {
+        (self.len as usize).div_ceil(self.data.len()).max(1)
+    }
+}
+
+fn checksum(data: &[u8]) -> u32 {
+    let digest = Sha256::digest(data);
+    u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]])
+}
+
+/// The blocks that the chunk with `seed` mixes, out of `blocks`.
+fn neighbours(seed: u32, blocks: usize) -> Vec<usize> {
+    if (seed as usize) < blocks {
+        return vec![seed as usize];
+    }
+    let mut rng = Rng::new(seed);
+    let degree = degree(rng.next_f64(), blocks);
+    let mut picked = Vec::with_capacity(degree);
+    while picked.len() < degree {
+        let block = (rng.next_u64() % blocks as u64) as usize;
+        if !picked.contains(&block) {
+            picked.push(block);
+        }
+    }
+    picked
+}
+
+/// Samples the robust soliton distribution over `1..=blocks` with a uniform `x` in `[0, 1)`.
+fn degree(x: f64, blocks: usize) -> usize {
+    let k = blocks as f64;
+    let r = C * (k / DELTA).ln() * k.sqrt();
+    let spike = (k / r).floor().max(1.0) as usize;
+    let weight = |d: usize| {
+        let ideal = match d {
+            1 => 1.0 / k,
+            _ => 1.0 / (d * (d - 1)) as f64,
+        };
+        let robust = match d {
+            d if d < spike => r / (d as f64 * k),
+            d if d == spike => r * (r / DELTA).ln() / k,
+            _ => 0.0,
+        };
+        ideal + robust.max(0.0)
+    };
+    let total: f64 = (1..=blocks).map(weight).sum();
+    let mut sum = 0.0;
+    for d in 1..=blocks {
+        sum += weight(d) / total;
+        if x < sum {
+            return d;
+        }
+    }
+    blocks
+}
+
+/// SplitMix64, which is good enough to pick blocks and the same on every platform.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u32) -> Self {
+        Self(u64::from(seed))
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1_u64 << 53) as f64
+    }
+}
+
+fn xor(target: &mut [u8], source: &[u8]) {
+    for (t, s) in target.iter_mut().zip(source) {
+        *t ^= s;
+    }
+}
+
+/// Cuts a file into blocks and mixes them into chunks.
+#[derive(Debug)]
+pub struct Encoder {
+    data: Vec<u8>,
+    block_len: usize,
+    checksum: u32,
+}
+
+impl Encoder {
+    /// `block_len` must be between 1 and [`u16::MAX`], and the file shorter than 4 GiB.
+    pub fn new(data: Vec<u8>, block_len: usize) -> Self {
+        Self {
+            checksum: checksum(&data),
+            data,
+            block_len,
+        }
+    }
+
+    pub fn blocks(&self) -> usize {
+        self.data.len().div_ceil(self.block_len).max(1)
+    }
+
+    pub fn chunk(&self, seed: u32) -> Chunk {
+        let mut data = vec![0; self.block_len];
+        for i in neighbours(seed, self.blocks()) {
+            let end = self.data.len().min((i + 1) * self.block_len);
+            xor(&mut data, &self.data[i * self.block_len..end]);
+        }
+        Chunk {
+            len: self.data.len() as u32,
+            checksum: self.checksum,
+            seed,
+            data,
+        }
+    }
+}
+
+/// Restores a file from chunks in any order, by peeling off the blocks that are already known.
+#[derive(Debug, Default)]
+pub struct Decoder {
+    /// The length, checksum and block length of the first chunk.
+    header: Option<(u32, u32, usize)>,
+    blocks: Vec<Option<Vec<u8>>>,
+    /// Chunks that still mix more than one unknown block.
+    pending: Vec<(Vec<usize>, Vec<u8>)>,
+    seen: HashSet<u32>,
+}
+
+impl Decoder {
+    /// Adds a chunk, and tells whether the file is complete.
+    pub fn push(&mut self, chunk: Chunk) -> Result<bool, FountainError> {
+        let header = chunk.header();
+        match self.header {
+            None => {
+                self.header = Some(header);
+                self.blocks = vec![None; chunk.blocks()];
+            },
+            Some(known) if known != header => return Err(FountainError::Mismatch),
+            Some(_) => {},
+        }
+        if self.seen.insert(chunk.seed) {
+            let indices = neighbours(chunk.seed, self.blocks.len());
+            self.pending.push((indices, chunk.data));
+            self.peel();
+        }
+        Ok(self.missing() == 0)
+    }
+
+    pub fn missing(&self) -> usize {
+        self.blocks.iter().filter(|block| block.is_none()).count()
+    }
+
+    /// Resolves chunks that only mix one unknown block until no such chunk is left.
+    fn peel(&mut self) {
+        loop {
+            let mut progress = false;
+            let mut pending = Vec::with_capacity(self.pending.len());
+            for (mut indices, mut data) in self.pending.drain(..) {
+                indices.retain(|&i| match &self.blocks[i] {
+                    Some(block) => {
+                        xor(&mut data, block);
+                        false
+                    },
+                    None => true,
+                });
+                match indices[..] {
+                    [] => {},
+                    [i] => {
+                        self.blocks[i] = Some(data);
+                        progress = true;
+                    },
+                    _ => pending.push((indices, data)),
+                }
+            }
+            self.pending = pending;
+            if !progress {
+                return;
+            }
+        }
+    }
+
+    /// The restored file, if all blocks are known and it matches its checksum.
+    pub fn finish(self) -> Result<Vec<u8>, FountainError> {
+        let total = self.blocks.len();
+        let missing = self.missing();
+        let Some((len, expected, block_len)) = self.header else {
+            return Err(FountainError::Incomplete { missing, total });
+        };
+        let mut data = Vec::with_capacity(total * block_len);
+        for block in self.blocks {
+            data.extend(block.ok_or(FountainError::Incomplete { missing, total })?);
+        }
+        data.truncate(len as usize);
+        match checksum(&data) == expected {
+            true => Ok(data),
+            false => Err(FountainError::Checksum),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(data: &[u8], block_len: usize, seed: u32) -> Chunk {
+        Encoder::new(data.to_vec(), block_len).chunk(seed)
+    }
+
+    #[test]
+    fn chunk_bytes() {
+        let chunk = chunk(b"Hallo, Welt!", 5, 7);
+        let bytes = chunk.to_bytes();
+        assert_eq!(bytes.len(), HEADER_LEN + 5);
+        assert_eq!(Chunk::parse(&bytes), Some(chunk));
+        assert_eq!(Chunk::parse(&bytes[..bytes.len() - 1]), None);
+        assert_eq!(Chunk::parse(b"https://www.fu-berlin.de/"), None);
+
+        // too long, and too many blocks
+        let mut bytes = bytes;
+        bytes[3..7].copy_from_slice(&(MAX_ANIMATED_LEN as u32 + 1).to_be_bytes());
+        assert_eq!(Chunk::parse(&bytes), None);
+        bytes[3..7].copy_from_slice(&(MAX_BLOCKS as u32 * 5 + 1).to_be_bytes());
+        assert_eq!(Chunk::parse(&bytes), None);
+        bytes[3..7].copy_from_slice(&(MAX_BLOCKS as u32 * 5).to_be_bytes());
+        assert!(Chunk::parse(&bytes).is_some());
+    }
+
+    #[test]
+    fn degrees() {
+        assert_eq!(degree(0.0, 1), 1);
+        assert_eq!(degree(0.999_999, 1), 1);
+        let mean = (0..1000)
+            .map(|i| degree(i as f64 / 1000.0, 100))
+            .sum::<usize>() as f64
+            / 1000.0;
+        assert!((2.0..20.0).contains(&mean), "{mean}");
+        for seed in 0..500 {
+            let mut picked = neighbours(seed, 30);
+            assert!(!picked.is_empty());
+            picked.sort_unstable();
+            picked.dedup();
+            assert_eq!(picked.len(), neighbours(seed, 30).len());
+        }
+    }
+
+    #[test]
+    fn restores_without_the_systematic_chunks() {
+        let data: Vec<u8> = (0..1000_u32).map(|i| (i * 7 % 251) as u8).collect();
+        let block_len = 64;
+        let mut decoder = Decoder::default();
+        // every fourth chunk is lost, and the plain blocks are never seen
+        let mut seeds = (16..).filter(|seed| seed % 4 != 0);
+        let mut received = 0;
+        while !decoder
+            .push(chunk(&data, block_len, seeds.next().unwrap()))
+            .unwrap()
+        {
+            received += 1;
+            assert!(received < 200);
+        }
+        assert_eq!(decoder.finish().unwrap(), data);
+
+        let mut decoder = Decoder::default();
+        assert!(!decoder.push(chunk(&data, block_len, 0)).unwrap());
+        assert!(matches!(
+            decoder.push(chunk(&data[1..], block_len, 1)),
+            Err(FountainError::Mismatch)
+        ));
+        assert!(matches!(
+            decoder.finish(),
+            Err(FountainError::Incomplete {
+                missing: 15,
+                total: 16
+            })
+        ));
+    }
+}
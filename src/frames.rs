@@ -0,0 +1,380 @@
+//! Transfers that span many QR codes, read from the frames of an animated GIF or PNG, or from a
+//! directory of images, e.g. a screen recording split into single images.
+//!
+//! The codes are either a structured append sequence of up to 16 symbols, or the chunks of a
+//! [`fountain`](crate::fountain) code.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::Path;
+
+use image::gif::GifDecoder;
+use image::{AnimationDecoder, DynamicImage, GrayAlphaImage, GrayImage, RgbImage, RgbaImage};
+use serde::Serialize;
+
+use crate::decode::{self, DecodeError, Decoded};
+use crate::fountain::{self, Chunk, FountainError};
+
+/// Extensions of the images that are read from a directory, in lower case.
+const EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "webp", "bmp"];
+
+#[derive(Debug, thiserror::Error)]
+pub enum FramesError {
+    #[error("Im Ordner wurden keine Bilder gefunden.")]
+    NoFrames,
+    #[error("In {0} Bildern wurde keine Folge von QR-Codes gefunden.")]
+    NoSequence(usize),
+    #[error("Es fehlen die Teile {0} der Folge.")]
+    Missing(String),
+    #[error("Die Prüfsumme der zusammengesetzten Folge stimmt nicht.")]
+    Parity,
+    #[error(transparent)]
+    Fountain(#[from] FountainError),
+    #[error("Die Bilder konnten nicht gelesen werden: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Das animierte PNG konnte nicht gelesen werden: {0}")]
+    Png(#[from] png::DecodingError),
+    #[error(transparent)]
+    Decode(#[from] DecodeError),
+}
+
+#[derive(Debug, Serialize)]
+pub struct Recovered {
+    #[serde(skip)]
+    pub data: Vec<u8>,
+    /// The number of read images.
+    pub frames: usize,
+    /// The number of distinct codes that were used.
+    pub codes: usize,
+    /// `"structured-append"` or `"fountain"`.
+    pub kind: &'static str,
+}
+
+/// Decodes the frames of an animated GIF or PNG, or all images in the directory of `path`.
+pub fn decode(path: &Path) -> Result<Recovered, FramesError> {
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+    if extension.as_deref() == Some("png") {
+        if let Some(frames) = apng_frames(path)? {
+            return reassemble(frames);
+        }
+    }
+    if extension.as_deref() == Some("gif") {
+        let decoder =
+            GifDecoder::new(BufReader::new(File::open(path)?)).map_err(DecodeError::Image)?;
+        let frames = decoder.into_frames().map(|frame| {
+            let image = DynamicImage::ImageRgba8(frame.map_err(DecodeError::Image)?.into_buffer());
+            Ok(decode::decode_image(&decode::flatten(
+                &image.to_luma_alpha8(),
+            ))?)
+        });
+        return reassemble(frames);
+    }
+
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_image = path.extension().is_some_and(|ext| {
+            let ext = ext.to_string_lossy().to_ascii_lowercase();
+            EXTENSIONS.contains(&ext.as_str())
+        });
+        if is_image && path.is_file() {
+            paths.push(path);
+        }
+    }
+    if paths.is_empty() {
+        return Err(FramesError::NoFrames);
+    }
+    // recordings number their frames, so that the names sort in order
+    paths.sort();
+    let frames = paths
+        .into_iter()
+        .map(|path| Ok(decode::decode_file(&fs::read(path)?)?));
+    reassemble(frames)
+}
+
+/// The frames of an animated PNG, or `None` for a still image.
+fn apng_frames(
+    path: &Path,
+) -> Result<Option<impl Iterator<Item = Result<Vec<Decoded>, FramesError>>>, FramesError> {
+    let mut decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+    let Some(control) = reader.info().animation_control else {
+        return Ok(None);
+    };
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let frames = (0..control.num_frames).map(move |_| {
+        let info = reader.next_frame(&mut buffer)?;
+        let (width, height) = (info.width, info.height);
+        let bytes = buffer[..info.buffer_size()].to_vec();
+        let image = match info.color_type {
+            png::ColorType::Grayscale => {
+                GrayImage::from_raw(width, height, bytes).map(DynamicImage::ImageLuma8)
+            },
+            png::ColorType::GrayscaleAlpha => {
+                GrayAlphaImage::from_raw(width, height, bytes).map(DynamicImage::ImageLumaA8)
+            },
+            png::ColorType::Rgb => {
+                RgbImage::from_raw(width, height, bytes).map(DynamicImage::ImageRgb8)
+            },
+            png::ColorType::Rgba => {
+                RgbaImage::from_raw(width, height, bytes).map(DynamicImage::ImageRgba8)
+            },
+            // expanded by the transformations
+            png::ColorType::Indexed => None,
+        };
+        let Some(image) = image else {
+            log::debug!("skipping a frame in {:?}", info.color_type);
+            return Ok(Vec::new());
+        };
+        Ok(decode::decode_image(&decode::flatten(
+            &image.to_luma_alpha8(),
+        ))?)
+    });
+    Ok(Some(frames))
+}
+
+/// Collects the codes of all frames and puts the sequence together.
+///
+/// Frames without a readable code are skipped, since recordings often catch the change from one
+/// code to the next.
+fn reassemble(
+    frames: impl Iterator<Item = Result<Vec<Decoded>, FramesError>>,
+) -> Result<Recovered, FramesError> {
+    let mut count = 0;
+    // the chunks of each fountain transfer by header, since a stray chunk may come first
+    let mut transfers: BTreeMap<(u32, u32, usize), (fountain::Decoder, usize)> = BTreeMap::new();
+    let mut complete = None;
+    // the parts of each sequence by parity and size, since one image may hold several sequences
+    let mut sequences: BTreeMap<(u8, u8), BTreeMap<u8, Vec<u8>>> = BTreeMap::new();
+    for frame in frames {
+        count += 1;
+        let codes = match frame {
+            Ok(codes) => codes,
+            Err(FramesError::Decode(err)) if !matches!(err, DecodeError::Image(_)) => {
+                log::debug!("no code in frame {count}: {err}");
+                continue;
+            },
+            Err(err) => return Err(err),
+        };
+        for code in codes {
+            if let Some(chunk) = Chunk::parse(&code.data) {
+                let header = chunk.header();
+                let (decoder, codes) = transfers.entry(header).or_default();
+                *codes += 1;
+                if decoder.push(chunk)? {
+                    complete = Some(header);
+                }
+            } else if let Some(header) = code.structured_append {
+                sequences
+                    .entry((header.parity, header.total))
+                    .or_default()
+                    .insert(header.index, code.data);
+            }
+        }
+        if let Some((decoder, codes)) = complete.and_then(|header| transfers.remove(&header)) {
+            log::info!("fountain transfer complete after {count} frames");
+            if !transfers.is_empty() {
+                log::warn!("skipped the chunks of {} other transfers", transfers.len());
+            }
+            return Ok(Recovered {
+                data: decoder.finish()?,
+                frames: count,
+                codes,
+                kind: "fountain",
+            });
+        }
+    }
+
+    // report what the transfer with the most chunks lacks, not what a stray chunk lacks
+    let incomplete = transfers.into_values().max_by_key(|(_, codes)| *codes);
+    if let Some((decoder, _)) = incomplete {
+        return Err(decoder
+            .finish()
+            .expect_err("the transfer is incomplete")
+            .into());
+    }
+    let complete = sequences
+        .iter()
+        .find(|((_, total), parts)| parts.len() == usize::from(*total));
+    if let Some(((parity, _), parts)) = complete {
+        let data: Vec<u8> = parts.values().flatten().copied().collect();
+        if data.iter().fold(0, |acc, b| acc ^ b) != *parity {
+            return Err(FramesError::Parity);
+        }
+        return Ok(Recovered {
+            data,
+            frames: count,
+            codes: parts.len(),
+            kind: "structured-append",
+        });
+    }
+    let Some(((_, total), parts)) = sequences.iter().max_by_key(|(_, parts)| parts.len()) else {
+        return Err(FramesError::NoSequence(count));
+    };
+    let missing: Vec<String> = (0..*total)
+        .filter(|index| !parts.contains_key(index))
+        .map(|index| (index + 1).to_string())
+        .collect();
+    Err(FramesError::Missing(missing.join(", ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use image::gif::GifEncoder;
+    use image::{Frame, GrayImage, Luma, Rgba, RgbaImage};
+    use qrcode::{EcLevel, Version};
+
+    use super::*;
+    use crate::decode::StructuredAppend;
+    use crate::symbol::Symbol;
+
+    /// A symbol of version 3 with a structured append header and `text` in one byte segment.
+    fn part(index: u8, total: u8, parity: u8, text: &str) -> GrayImage {
+        let mut bits = Vec::new();
+        let mut push = |len: usize, value: usize| {
+            bits.extend((0..len).rev().map(|i| value >> i & 1 == 1));
+        };
+        push(4, 0b0011);
+        push(4, usize::from(index));
+        push(4, usize::from(total - 1));
+        push(8, usize::from(parity));
+        push(4, 0b0100);
+        push(8, text.len());
+        for &byte in text.as_bytes() {
+            push(8, usize::from(byte));
+        }
+        push(4, 0);
+        let mut data: Vec<u8> = bits
+            .chunks(8)
+            .map(|byte| (0..8).fold(0, |acc, i| acc << 1 | u8::from(byte.get(i) == Some(&true))))
+            .collect();
+        let capacity = qrcode::bits::Bits::new(Version::Normal(3))
+            .max_len(EcLevel::M)
+            .unwrap();
+        let padding = [0b1110_1100, 0b0001_0001].into_iter().cycle();
+        data.extend(padding.take(capacity / 8 - data.len()));
+        Symbol::new(&data, Version::Normal(3), EcLevel::M, None)
+            .unwrap()
+            .render::<Luma<u8>>()
+            .module_dimensions(4, 4)
+            .build()
+    }
+
+    fn code(data: Vec<u8>) -> Decoded {
+        Decoded {
+            text: String::new(),
+            data,
+            version: 15,
+            ec_level: EcLevel::L,
+            mask: 0,
+            eci: None,
+            fnc1: false,
+            corrected: 0,
+            structured_append: None,
+        }
+    }
+
+    fn frames(images: Vec<GrayImage>) -> impl Iterator<Item = Result<Vec<Decoded>, FramesError>> {
+        images
+            .into_iter()
+            .map(|image| Ok(decode::decode_image(&image)?))
+    }
+
+    #[test]
+    fn structured_append() {
+        let texts = ["Zusammen", "gesetzte ", "Folge"];
+        let parity = texts.concat().bytes().fold(0, |acc, b| acc ^ b);
+        let images: Vec<_> = texts
+            .iter()
+            .enumerate()
+            .map(|(index, text)| part(index as u8, 3, parity, text))
+            .collect();
+        let decoded = decode::decode_image(&images[1]).unwrap();
+        assert_eq!(
+            decoded[0].structured_append,
+            Some(StructuredAppend {
+                index: 1,
+                total: 3,
+                parity,
+            })
+        );
+
+        // out of order, repeated and with an empty frame
+        let blank = GrayImage::from_pixel(100, 100, Luma([255]));
+        let shuffled = vec![
+            images[2].clone(),
+            blank,
+            images[0].clone(),
+            images[2].clone(),
+            images[1].clone(),
+        ];
+        let recovered = reassemble(frames(shuffled)).unwrap();
+        assert_eq!(recovered.data, texts.concat().as_bytes());
+        assert_eq!((recovered.frames, recovered.codes), (5, 3));
+
+        let incomplete = vec![images[0].clone()];
+        assert!(matches!(
+            reassemble(frames(incomplete)),
+            Err(FramesError::Missing(missing)) if missing == "2, 3"
+        ));
+        assert!(matches!(
+            reassemble(frames(Vec::new())),
+            Err(FramesError::NoSequence(0))
+        ));
+    }
+
+    #[test]
+    fn fountain_with_a_stray_chunk() {
+        let data = b"Zusammengesetzte Folge".to_vec();
+        let encoder = fountain::Encoder::new(data.clone(), 8);
+        let stray = fountain::Encoder::new(b"Andere Datei".to_vec(), 8);
+        let chunks = [
+            stray.chunk(1),
+            encoder.chunk(0),
+            encoder.chunk(1),
+            encoder.chunk(2),
+        ];
+        let frames = |len: usize| {
+            chunks[..len]
+                .iter()
+                .map(|chunk| Ok(vec![code(chunk.to_bytes())]))
+        };
+        let recovered = reassemble(frames(4)).unwrap();
+        assert_eq!(recovered.data, data);
+        assert_eq!((recovered.frames, recovered.codes), (4, 3));
+        assert_eq!(recovered.kind, "fountain");
+
+        assert!(matches!(
+            reassemble(frames(3)),
+            Err(FramesError::Fountain(FountainError::Incomplete {
+                missing: 1,
+                total: 3
+            }))
+        ));
+    }
+
+    #[test]
+    fn animated_gif() {
+        let path = tempfile::Builder::new().suffix(".gif").tempfile().unwrap();
+        let parity = b"AB".iter().fold(0, |acc, b| acc ^ b);
+        let mut encoder = GifEncoder::new(path.reopen().unwrap());
+        for (index, text) in ["A", "B"].into_iter().enumerate() {
+            let gray = part(index as u8, 2, parity, text);
+            let rgba = RgbaImage::from_fn(gray.width(), gray.height(), |x, y| {
+                let Luma([luma]) = *gray.get_pixel(x, y);
+                Rgba([luma, luma, luma, 255])
+            });
+            encoder.encode_frame(Frame::new(rgba)).unwrap();
+        }
+        drop(encoder);
+
+        let recovered = decode(path.path()).unwrap();
+        assert_eq!(recovered.data, b"AB");
+        assert_eq!(recovered.kind, "structured-append");
+    }
+}
@@ -0,0 +1,90 @@
+//! Text in Helvetica, one of the standard fonts every PDF reader has.
+
+use encoding_rs::WINDOWS_1252;
+
+/// Widths of the printable ASCII characters in Helvetica, in 1/1000 em.
+#[rustfmt::skip]
+const WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556,
+    1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556,
+    333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556,
+    556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+/// The width of `text` in points, in Helvetica of `size` points.
+pub fn text_width(text: &str, size: f32) -> f32 {
+    let width: u32 = text
+        .chars()
+        .map(|c| match c {
+            ' '..='~' => u32::from(WIDTHS[c as usize - 0x20]),
+            _ => 556,
+        })
+        .sum();
+    width as f32 * size / 1000.0
+}
+
+/// Splits `text` into lines of at most `width` points in Helvetica of `size` points.
+pub fn wrap(text: &str, width: f32, size: f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split(' ') {
+        let candidate = match line.is_empty() {
+            true => word.to_owned(),
+            false => format!("{line} {word}"),
+        };
+        if text_width(&candidate, size) > width && !line.is_empty() {
+            lines.push(std::mem::replace(&mut line, word.to_owned()));
+        } else {
+            line = candidate;
+        }
+    }
+    lines.push(line);
+    lines
+}
+
+/// Encodes `text` for the standard fonts, replacing characters they lack with "?".
+pub fn win_ansi(text: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut buf = [0; 4];
+    for c in text.chars() {
+        match WINDOWS_1252.encode(c.encode_utf8(&mut buf)) {
+            (encoded, _, false) => bytes.extend_from_slice(&encoded),
+            (_, _, true) => bytes.push(b'?'),
+        }
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn widths() {
+        assert_eq!(text_width("", 10.0), 0.0);
+        assert_eq!(text_width("a", 10.0), 5.56);
+        assert!(text_width("W", 10.0) > text_width("i", 10.0));
+    }
+
+    #[test]
+    fn line_breaks() {
+        let text = "Auftrag vom 15.06.2020 für die Robert Schneider AG";
+        let lines = wrap(text, 100.0, 10.0);
+        assert!(lines.len() > 1);
+        assert!(lines.iter().all(|line| text_width(line, 10.0) <= 100.0));
+        assert_eq!(lines.join(" "), text);
+
+        // a word wider than the line is not split
+        let lines = wrap("Rechnung Donaudampfschifffahrtsgesellschaft", 50.0, 10.0);
+        assert_eq!(lines, ["Rechnung", "Donaudampfschifffahrtsgesellschaft"]);
+        assert_eq!(wrap("", 50.0, 10.0), [""]);
+    }
+
+    #[test]
+    fn encoding() {
+        assert_eq!(win_ansi("Grüße, 5 €"), b"Gr\xfc\xdfe, 5 \x80");
+        assert_eq!(win_ansi("Ωmega"), b"?mega");
+    }
+}
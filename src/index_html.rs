@@ -1,3 +1,5 @@
+use crate::payload::provider::{Field, Input, DEFAULT_URL, PROVIDERS};
+
 markup::define! {
     IndexHtml {
         @markup::doctype()
@@ -21,6 +23,58 @@ markup::define! {
                 }
                 form[id="form"] {
                     fieldset {
+                        div {
+                            label[for="type"] {
+                                "Inhalt:"
+                            }
+                            " "
+                            label {
+                                select[id="type", name="t"] {
+                                    @for provider in PROVIDERS {
+                                        option[
+                                            value=provider.kind(),
+                                            selected=provider.kind() == PROVIDERS[0].kind(),
+                                        ] {
+                                            @provider.title()
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                        div {
+                            label[for="scan-open"] {
+                                "Vorhandener Code:"
+                            }
+                            " "
+                            label {
+                                button[type="button", id="scan-open"] {
+                                    "Bild öffnen …"
+                                }
+                                " "
+                                button[type="button", id="scan-clipboard"] {
+                                    "Aus der Zwischenablage"
+                                }
+                                " "
+                                button[
+                                    type="button",
+                                    id="export-open",
+                                    title="Die Einstellungen einer mit diesem Programm gespeicherten SVG-, PNG- oder PDF-Datei laden.",
+                                ] {
+                                    "Export öffnen …"
+                                }
+                                " "
+                                button[
+                                    type="button",
+                                    id="scan-frames",
+                                    title="Eine Übertragung aus vielen Codes lesen, aus einem animierten GIF oder PNG oder aus allen Bildern im Ordner des gewählten Bildes.",
+                                ] {
+                                    "Bildfolge lesen …"
+                                }
+                            }
+                        }
+                        div.hint[id="scan", hidden] {}
+                    }
+                    fieldset["data-type"="url"] {
                         div {
                             label[for="url"] {
                                 "URL:"
@@ -29,15 +83,233 @@ markup::define! {
                             label {
                                 input[
                                     type="url",
-                                    value="https://www.vetmed.fu-berlin.de/",
+                                    value=DEFAULT_URL,
                                     autocomplete="off",
                                     spellcheck="off",
                                     id="url",
+                                    name="q",
+                                ];
+                            }
+                        }
+                        p.hint.error[id="url-warnings", hidden] {
+                            span {}
+                            " "
+                            label {
+                                input[type="checkbox"];
+                                " Ziel geprüft, trotzdem speichern"
+                            }
+                        }
+                    }
+                    @for provider in PROVIDERS {
+                        @if let Some(schema) = provider.schema() {
+                            fieldset["data-type"=provider.kind(), hidden] {
+                                @for field in schema {
+                                    @SchemaField { kind: provider.kind(), field }
+                                }
+                            }
+                        }
+                    }
+                    fieldset {
+                        div {
+                            label[for="charset"] {
+                                "Zeichensatz:"
+                            }
+                            " "
+                            label {
+                                select[id="charset", name="charset"] {
+                                    option[value="utf8", selected] { "UTF-8" }
+                                    option[value="kanji"] {
+                                        "UTF-8, japanische Schriftzeichen im Kanji-Modus"
+                                    }
+                                    option[value="sjis"] { "Shift-JIS (Kanji-Modus), ersatzweise UTF-8" }
+                                }
+                            }
+                        }
+                        div {
+                            label[for="eci"] {
+                                "Kennung (ECI):"
+                            }
+                            " "
+                            label {
+                                input[type="checkbox", checked, id="eci", name="eci"];
+                                " Zeichensatz für Umlaute und andere Sonderzeichen angeben"
+                            }
+                        }
+                        div {
+                            label[for="version"] {
+                                "Version:"
+                            }
+                            " "
+                            label {
+                                select[id="pin", name="pin"] {
+                                    option[value="min", selected] { "mindestens" }
+                                    option[value="exact"] { "genau" }
+                                }
+                                " "
+                                select[id="version", name="version"] {
+                                    option[value="", selected] { "automatisch" }
+                                    @for version in 1..=40 {
+                                        option[value=version] {
+                                            @version " (" @{17 + 4 * version} "×" @{17 + 4 * version} ")"
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                        div {
+                            label[for="sign"] {
+                                "Signatur:"
+                            }
+                            " "
+                            label {
+                                input[type="checkbox", id="sign", name="sign"];
+                                " Inhalt mit dem eigenen Schlüssel signieren (Ed25519), als "
+                                select[id="sign-encoding", name="sign_encoding"] {
+                                    option[value="base64url", selected] { "Base64url" }
+                                    option[value="base45"] { "Base45 (nicht bei Links)" }
+                                }
+                            }
+                        }
+                        div {
+                            label[for="mask"] {
+                                "Maskenmuster:"
+                            }
+                            " "
+                            label {
+                                select[id="mask", name="mask"] {
+                                    option[value="", selected] { "automatisch" }
+                                    @for mask in 0..8 {
+                                        option[value=mask] { @mask }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    fieldset {
+                        div {
+                            label[for="print-width"] {
+                                "Druckbreite:"
+                            }
+                            " "
+                            label {
+                                input[
+                                    type="number",
+                                    id="print-width",
+                                    name="print_width",
+                                    min="1",
+                                    step="any",
+                                    placeholder="automatisch",
+                                    style="width: 8em",
+                                ];
+                                " mm mit Ruhezone, für PDF und PNG"
+                            }
+                        }
+                        div {
+                            label[for="distance"] {
+                                "Scanabstand:"
+                            }
+                            " "
+                            label {
+                                input[
+                                    type="number",
+                                    id="distance",
+                                    name="distance",
+                                    value="30",
+                                    min="1",
+                                    step="any",
+                                    style="width: 8em",
+                                ];
+                                " cm"
+                            }
+                        }
+                        div {
+                            label[for="fov"] {
+                                "Kamera:"
+                            }
+                            " "
+                            label {
+                                input[
+                                    type="number",
+                                    id="fov",
+                                    name="fov",
+                                    value="65",
+                                    min="1",
+                                    max="179",
+                                    step="any",
+                                    style="width: 5em",
+                                ];
+                                "° Bildwinkel, "
+                            }
+                            label {
+                                input[
+                                    type="number",
+                                    id="resolution",
+                                    name="resolution",
+                                    value="1280",
+                                    min="1",
+                                    step="1",
+                                    style="width: 6em",
+                                ];
+                                " Pixel Bildbreite, "
+                            }
+                            label {
+                                input[
+                                    type="number",
+                                    id="pixels-per-module",
+                                    name="pixels_per_module",
+                                    value="3",
+                                    min="1",
+                                    step="any",
+                                    style="width: 5em",
                                 ];
+                                " Pixel pro Modul"
                             }
                         }
                     }
                 }
+                p.hint[id="info"] {}
+                p.hint[id="compact", hidden] {
+                    span {}
+                    " "
+                    button[type="button"] {
+                        "Übernehmen"
+                    }
+                }
+                p.hint[id="verify"] {
+                    span {}
+                    " "
+                    label[hidden] {
+                        input[type="checkbox"];
+                        " Trotzdem herunterladen (gesperrt sind nur die Links oben, nicht das Vorschaubild)"
+                    }
+                }
+                p.hint[id="print-size"] {}
+                p.hint[id="budget"] {
+                    label {
+                        input[type="checkbox"];
+                        " Schadensbudget anzeigen"
+                    }
+                    " "
+                    span {}
+                    span[hidden] {
+                        " "
+                        span.swatch.data {}
+                        " Daten "
+                        span.swatch.ec {}
+                        " Fehlerkorrektur "
+                        span.swatch.covered {}
+                        " verdeckt "
+                        span.swatch.function {}
+                        " Funktionsmuster"
+                    }
+                }
+                p.hint[id="robustness"] {
+                    button[type="button"] {
+                        "Robustheit prüfen"
+                    }
+                    " "
+                    span {}
+                }
                 div[style="text-align: center"] {
                     div.grid {
                         p[style="margin-left: auto;"] {
@@ -91,8 +363,231 @@ markup::define! {
                          Wenn möglich, sollten Sie PDFs oder SVGs bevorzugen."
                     }
                 }
+                div[id="animation", hidden, title="Klicken zum Beenden."] {
+                    img[alt="Animierte Übertragung"];
+                }
+                details[id="signature"] {
+                    summary {
+                        "Signaturen verwalten und prüfen"
+                    }
+                    fieldset {
+                        div {
+                            label[for="signature-generate"] {
+                                "Eigener öffentlicher Schlüssel:"
+                            }
+                            " "
+                            label {
+                                output[id="signature-public-key"] { "–" }
+                                " "
+                                button[type="button", id="signature-generate", hidden] {
+                                    "Schlüssel erzeugen"
+                                }
+                            }
+                        }
+                        div {
+                            label[for="signature-name"] {
+                                "Vertrauenswürdige Schlüssel:"
+                            }
+                            " "
+                            label {
+                                output[id="signature-trusted"] { "–" }
+                                input[
+                                    type="text",
+                                    autocomplete="off",
+                                    placeholder="Name, z.B. Pressestelle",
+                                    id="signature-name",
+                                ];
+                                input[
+                                    type="text",
+                                    autocomplete="off",
+                                    placeholder="Öffentlicher Schlüssel (Base64url)",
+                                    id="signature-key",
+                                ];
+                                button[type="button", id="signature-trust"] {
+                                    "Schlüssel hinzufügen"
+                                }
+                            }
+                        }
+                        div {
+                            label[for="signature-text"] {
+                                "Gescannter Inhalt:"
+                            }
+                            " "
+                            label {
+                                textarea[id="signature-text", rows=3] {}
+                                button[type="button", id="signature-verify"] {
+                                    "Signatur prüfen"
+                                }
+                                " "
+                                output[id="signature-result"] {}
+                            }
+                        }
+                    }
+                    p.hint {
+                        "Der geheime Schlüssel liegt in der Konfigurationsdatei und verlässt diesen \
+                         Rechner nicht. Den öffentlichen Schlüssel an alle weitergeben, die Codes \
+                         prüfen sollen."
+                    }
+                }
                 script[async, defer, src="./script.js"] {}
             }
         }
     }
+
+    SchemaField<'a>(kind: &'a str, field: &'a Field) {
+        @match &field.input {
+            Input::Hidden => {
+                input[
+                    type="hidden",
+                    id=format!("{kind}-{}", field.name),
+                    name=format!("{kind}[{}]", field.name),
+                ];
+            }
+            Input::Hint(lines) => {
+                p.hint[id=format!("{kind}-{}", field.name)] {
+                    @for (index, line) in lines().iter().enumerate() {
+                        @if index > 0 {
+                            br;
+                        }
+                        @line
+                    }
+                }
+            }
+            input => {
+                div {
+                    label[for=format!("{kind}-{}", field.name)] {
+                        @field.label ":"
+                    }
+                    " "
+                    label {
+                        @SchemaInput { kind, name: field.name, input }
+                    }
+                }
+                @if let Input::Animation = input {
+                    p.hint[id=format!("{kind}-{}-info", field.name)] {}
+                }
+            }
+        }
+    }
+
+    SchemaInput<'a>(kind: &'a str, name: &'a str, input: &'a Input) {
+        @match input {
+            Input::Text { kind: input, inputmode, placeholder } => {
+                input[
+                    type=input,
+                    inputmode=inputmode,
+                    autocomplete="off",
+                    spellcheck="false",
+                    placeholder=placeholder,
+                    id=format!("{kind}-{name}"),
+                    name=format!("{kind}[{name}]"),
+                ];
+            }
+            Input::Suggest { placeholder, options } => {
+                input[
+                    type="text",
+                    list=format!("{kind}-{name}-options"),
+                    autocomplete="off",
+                    spellcheck="false",
+                    placeholder=placeholder,
+                    id=format!("{kind}-{name}"),
+                    name=format!("{kind}[{name}]"),
+                ];
+                datalist[id=format!("{kind}-{name}-options")] {
+                    @for option in options() {
+                        option[value=option] {}
+                    }
+                }
+            }
+            Input::Number { value, min, max } => {
+                input[
+                    type="number",
+                    value=value,
+                    min=min,
+                    max=max,
+                    style="width: 5em",
+                    id=format!("{kind}-{name}"),
+                    name=format!("{kind}[{name}]"),
+                ];
+            }
+            Input::Generated(button) => {
+                input[
+                    type="text",
+                    autocomplete="off",
+                    spellcheck="false",
+                    id=format!("{kind}-{name}"),
+                    name=format!("{kind}[{name}]"),
+                ];
+                " "
+                button.generate[type="button", id=format!("{kind}-{name}-generate")] {
+                    @button
+                }
+            }
+            Input::ReadOnly => {
+                input[
+                    type="text",
+                    readonly,
+                    id=format!("{kind}-{name}"),
+                    name=format!("{kind}[{name}]"),
+                ];
+            }
+            Input::Hidden | Input::Hint(_) => {}
+            Input::TextArea => {
+                textarea[id=format!("{kind}-{name}"), name=format!("{kind}[{name}]"), rows=3] {}
+            }
+            Input::Select { options, default } => {
+                select[id=format!("{kind}-{name}"), name=format!("{kind}[{name}]")] {
+                    @for (value, text) in options.iter() {
+                        option[value=value, selected=value == default] { @text }
+                    }
+                }
+            }
+            Input::Checkbox(text) => {
+                input[
+                    type="checkbox",
+                    id=format!("{kind}-{name}"),
+                    name=format!("{kind}[{name}]"),
+                ];
+                " " @text
+            }
+            Input::Import { button, batch } => {
+                button.import[type="button", id=format!("{kind}-{name}")] {
+                    @button
+                }
+                " "
+                select.pick[id=format!("{kind}-{name}-pick"), hidden] {}
+                @if let Some(batch) = batch {
+                    " "
+                    a.batch[id=format!("{kind}-{name}-batch"), download=batch, hidden] {
+                        "Alle als PDF (ein Code pro Eintrag)"
+                    }
+                }
+            }
+            Input::Document { file, text } => {
+                a.document[
+                    id=format!("{kind}-{name}"),
+                    download=file,
+                    title="Klicken um das Dokument zu speichern.",
+                ] {
+                    @text
+                }
+            }
+            Input::Animation => {
+                a.animation[id=format!("{kind}-{name}"), download="uebertragung.gif"] {
+                    "Als animiertes GIF"
+                }
+                " "
+                a[id=format!("{kind}-{name}-png"), download="uebertragung.png"] {
+                    "Als animiertes PNG"
+                }
+                " "
+                button[type="button", id=format!("{kind}-{name}-fullscreen")] {
+                    "Im Vollbild zeigen"
+                }
+            }
+            Input::Output => {
+                output[id=format!("{kind}-{name}")] {}
+            }
+        }
+    }
 }
@@ -7,10 +7,33 @@
 
 use std::path::PathBuf;
 
+mod animation;
 mod assets;
+mod base45;
+mod batch;
+mod budget;
+mod clipboard;
+mod compact;
+mod config;
+mod datamatrix;
+mod decode;
+mod encode;
 mod event_loop;
+mod fountain;
+mod frames;
+mod helvetica;
 mod index_html;
+mod metadata;
+mod overlay;
+mod payload;
+mod payment_part;
+mod phishing;
+mod print_size;
+mod robustness;
+mod signature;
 mod startup;
+mod symbol;
+mod verify;
 
 #[derive(pretty_error_debug::Debug, thiserror::Error)]
 pub enum MainError {
@@ -0,0 +1,214 @@
+//! The settings of the editor embedded into exported files, so that a code can be opened and
+//! changed again later.
+//!
+//! The settings are the query string of the editor, which is percent-encoded ASCII. PNG files
+//! get an `iTXt` chunk, SVG files a `<metadata>` element, and PDF files an XMP packet.
+
+use pdf_writer::{PdfWriter, Ref};
+
+const NAMESPACE: &str = "https://github.com/Kijewski/qr-code/ns/1.0/";
+/// The keyword of the PNG chunk, and the name of the XML element.
+const SETTINGS: &str = "qrcode:settings";
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+#[derive(Debug, thiserror::Error)]
+pub enum MetadataError {
+    #[error("Die Datei wurde nicht mit diesem Programm erzeugt.")]
+    NotFound,
+    #[error("Die gespeicherten Einstellungen sind beschädigt.")]
+    Invalid,
+}
+
+/// Adds the settings and the content of the code to a PNG file, and the resolution at which the
+/// print sizes were computed.
+pub fn png(png: &mut Vec<u8>, settings: &str, text: &str, dpi: f64) {
+    // after the signature and the `IHDR` chunk, which always has 13 bytes of data
+    let mut position = PNG_SIGNATURE.len() + 8 + 13 + 4;
+    let mut insert = |kind: &[u8; 4], data: &[u8]| {
+        let mut chunk = Vec::with_capacity(data.len() + 12);
+        chunk.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        chunk.extend_from_slice(kind);
+        chunk.extend_from_slice(data);
+        chunk.extend_from_slice(&crc32fast::hash(&chunk[4..]).to_be_bytes());
+        png.splice(position..position, chunk.iter().copied());
+        position += chunk.len();
+    };
+
+    // pixels per metre in both directions
+    let pixels = ((dpi / 0.0254).round() as u32).to_be_bytes();
+    let mut data = [1; 9];
+    data[..4].copy_from_slice(&pixels);
+    data[4..8].copy_from_slice(&pixels);
+    insert(b"pHYs", &data);
+
+    for (keyword, value) in [(SETTINGS, settings), ("Description", text)] {
+        // keyword, no compression, no language tag and no translated keyword
+        let mut data = Vec::with_capacity(keyword.len() + 5 + value.len());
+        data.extend_from_slice(keyword.as_bytes());
+        data.extend_from_slice(&[0, 0, 0, 0, 0]);
+        data.extend_from_slice(value.as_bytes());
+        insert(b"iTXt", &data);
+    }
+}
+
+/// Adds the settings and the content of the code to an SVG generated by
+/// [`qrcode::render::svg`].
+pub fn svg(svg: &mut String, settings: &str, text: &str) {
+    let Some(start) = svg.find("<svg") else {
+        log::error!("could not find the svg element");
+        return;
+    };
+    let Some(end) = svg[start..].find('>') else {
+        log::error!("could not find the end of the svg element");
+        return;
+    };
+    let metadata = format!(
+        r#"<desc>{}</desc><metadata><{SETTINGS} xmlns:qrcode="{NAMESPACE}">{}</{SETTINGS}></metadata>"#,
+        escape(text),
+        escape(settings),
+    );
+    svg.insert_str(start + end + 1, &metadata);
+}
+
+/// Writes the settings and the content of the code as XMP metadata of a PDF document, and
+/// returns the next unused reference.
+pub fn pdf(writer: &mut PdfWriter, id: Ref, settings: &str, text: &str) -> Ref {
+    let xmp = format!(
+        concat!(
+            "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>",
+            r#"<x:xmpmeta xmlns:x="adobe:ns:meta/">"#,
+            r#"<rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">"#,
+            r#"<rdf:Description rdf:about="" xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:qrcode="{namespace}">"#,
+            r#"<dc:description><rdf:Alt><rdf:li xml:lang="x-default">{text}</rdf:li></rdf:Alt></dc:description>"#,
+            "<{settings_element}>{settings}</{settings_element}>",
+            "</rdf:Description></rdf:RDF></x:xmpmeta>",
+            r#"<?xpacket end="r"?>"#,
+        ),
+        namespace = NAMESPACE,
+        text = escape(text),
+        settings_element = SETTINGS,
+        settings = escape(settings),
+    );
+    writer.metadata(id, xmp.as_bytes());
+    Ref::new(id.get() + 1)
+}
+
+/// Reads the settings back from a PNG, SVG or PDF file.
+pub fn read(bytes: &[u8]) -> Result<String, MetadataError> {
+    if bytes.starts_with(PNG_SIGNATURE) {
+        return read_png(bytes);
+    }
+    // the XML of SVG files and of the uncompressed XMP stream of PDF files
+    let text = String::from_utf8_lossy(bytes);
+    let (_, rest) = text
+        .split_once(&format!("<{SETTINGS}"))
+        .ok_or(MetadataError::NotFound)?;
+    let (_, rest) = rest.split_once('>').ok_or(MetadataError::Invalid)?;
+    let (value, _) = rest
+        .split_once(&format!("</{SETTINGS}>"))
+        .ok_or(MetadataError::Invalid)?;
+    unescape(value)
+}
+
+fn read_png(bytes: &[u8]) -> Result<String, MetadataError> {
+    let mut rest = &bytes[PNG_SIGNATURE.len()..];
+    while rest.len() >= 12 {
+        let len = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+        let data = rest.get(8..8 + len).ok_or(MetadataError::Invalid)?;
+        if &rest[4..8] == b"iTXt" {
+            let mut fields = data.splitn(2, |&b| b == 0);
+            if fields.next() == Some(SETTINGS.as_bytes()) {
+                let data = fields.next().ok_or(MetadataError::Invalid)?;
+                // the compression flag and method, the language tag and the translated keyword
+                let text = match data {
+                    [0, _, rest @ ..] => rest.splitn(3, |&b| b == 0).nth(2),
+                    _ => None,
+                };
+                let text = text.ok_or(MetadataError::Invalid)?;
+                return String::from_utf8(text.to_vec()).map_err(|_| MetadataError::Invalid);
+            }
+        }
+        rest = rest.get(12 + len..).ok_or(MetadataError::Invalid)?;
+    }
+    Err(MetadataError::NotFound)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn unescape(text: &str) -> Result<String, MetadataError> {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some((before, after)) = rest.split_once('&') {
+        unescaped.push_str(before);
+        let (entity, after) = after.split_once(';').ok_or(MetadataError::Invalid)?;
+        unescaped.push(match entity {
+            "amp" => '&',
+            "lt" => '<',
+            "gt" => '>',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => return Err(MetadataError::Invalid),
+        });
+        rest = after;
+    }
+    unescaped.push_str(rest);
+    Ok(unescaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use usvg::TreeParsing;
+
+    use super::*;
+
+    const SETTINGS_QUERY: &str = "t=url&q=https%3A%2F%2Fwww.vetmed.fu-berlin.de%2F&eci=true";
+
+    #[test]
+    fn png_chunks() {
+        let image = image::GrayImage::from_pixel(3, 3, image::Luma([255]));
+        let mut png = Vec::new();
+        image::png::PngEncoder::new(&mut png)
+            .encode(image.as_raw(), 3, 3, image::ColorType::L8)
+            .unwrap();
+        assert!(matches!(read(&png), Err(MetadataError::NotFound)));
+        self::png(&mut png, SETTINGS_QUERY, "Grüße <&>", 300.0);
+        assert_eq!(read(&png).unwrap(), SETTINGS_QUERY);
+        assert!(image::load_from_memory(&png).is_ok());
+
+        // 11811 pixels per metre, right after `IHDR`
+        let start = PNG_SIGNATURE.len() + 25;
+        assert_eq!(
+            png[start..start + 21],
+            *b"\0\0\0\x09pHYs\0\0\x2e\x23\0\0\x2e\x23\x01\x78\xa5\x3f\x76"
+        );
+    }
+
+    #[test]
+    fn svg_and_pdf() {
+        let mut svg = qrcode::QrCode::new("x")
+            .unwrap()
+            .render::<qrcode::render::svg::Color<'_>>()
+            .build();
+        self::svg(&mut svg, "a=1&b=<2>", "Grüße <&>");
+        assert!(svg.contains("<desc>Grüße &lt;&amp;&gt;</desc>"));
+        assert!(usvg::Tree::from_str(&svg, &usvg::Options::default()).is_ok());
+        assert_eq!(read(svg.as_bytes()).unwrap(), "a=1&b=<2>");
+
+        let mut writer = PdfWriter::new();
+        let next = self::pdf(&mut writer, Ref::new(1), SETTINGS_QUERY, "x");
+        assert_eq!(next, Ref::new(2));
+        assert_eq!(read(&writer.finish()).unwrap(), SETTINGS_QUERY);
+
+        assert!(matches!(read(b"%PDF-1.7"), Err(MetadataError::NotFound)));
+        assert!(matches!(
+            read(b"<qrcode:settings>&bogus;</qrcode:settings>"),
+            Err(MetadataError::Invalid)
+        ));
+    }
+}
@@ -0,0 +1,118 @@
+//! Graphics drawn on top of a rendered symbol.
+
+use image::{ImageBuffer, LumaA};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overlay {
+    /// The Swiss cross in the centre of Swiss QR-bill codes, 7 mm wide in a 46 mm symbol.
+    SwissCross,
+}
+
+/// A filled rectangle in module units, relative to the top left corner of the symbol.
+#[derive(Debug, Clone, Copy)]
+struct Rect {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+    dark: bool,
+}
+
+impl Overlay {
+    fn rects(self, width: usize) -> Vec<Rect> {
+        match self {
+            Self::SwissCross => {
+                let width = width as f64;
+                let center = width / 2.0;
+                let square = |size: f64, dark| Rect {
+                    x: center - size / 2.0,
+                    y: center - size / 2.0,
+                    width: size,
+                    height: size,
+                    dark,
+                };
+                // a white border of 0.5 mm around a black square with the cross of the flag
+                let outer = width * 7.0 / 46.0;
+                let inner = outer * 6.0 / 7.0;
+                let (long, short) = (inner * 20.0 / 32.0, inner * 6.0 / 32.0);
+                vec![
+                    square(outer, false),
+                    square(inner, true),
+                    Rect {
+                        x: center - long / 2.0,
+                        y: center - short / 2.0,
+                        width: long,
+                        height: short,
+                        dark: false,
+                    },
+                    Rect {
+                        x: center - short / 2.0,
+                        y: center - long / 2.0,
+                        width: short,
+                        height: long,
+                        dark: false,
+                    },
+                ]
+            },
+        }
+    }
+
+    /// Whether the overlay hides any part of the module at `x`, `y`, whatever its colour.
+    pub fn covers(self, width: usize, x: usize, y: usize) -> bool {
+        let (x, y) = (x as f64, y as f64);
+        self.rects(width).iter().any(|rect| {
+            x + 1.0 > rect.x
+                && x < rect.x + rect.width
+                && y + 1.0 > rect.y
+                && y < rect.y + rect.height
+        })
+    }
+
+    /// Draws the overlay into an SVG generated by [`qrcode::render::svg`].
+    pub fn svg(self, svg: &mut String, width: usize, quiet_zone: u32) {
+        let size = svg
+            .split_once(" width=\"")
+            .and_then(|(_, rest)| rest.split_once('"'))
+            .and_then(|(size, _)| size.parse::<f64>().ok());
+        let (Some(size), Some(end)) = (size, svg.rfind("</svg>")) else {
+            log::error!("could not find the size of the svg");
+            return;
+        };
+        let scale = size / (width as f64 + 2.0 * f64::from(quiet_zone));
+        let mut group = format!(
+            r#"<g transform="scale({scale}) translate({quiet_zone} {quiet_zone})" shape-rendering="auto">"#
+        );
+        for rect in self.rects(width) {
+            group.push_str(&format!(
+                r##"<rect x="{}" y="{}" width="{}" height="{}" fill="{}"/>"##,
+                rect.x,
+                rect.y,
+                rect.width,
+                rect.height,
+                if rect.dark { "#000" } else { "#fff" },
+            ));
+        }
+        group.push_str("</g>");
+        svg.insert_str(end, &group);
+    }
+
+    /// Draws the overlay into an image rendered with a quiet zone of `quiet_zone` modules.
+    pub fn paint(self, image: &mut ImageBuffer<LumaA<u8>, Vec<u8>>, width: usize, quiet_zone: u32) {
+        let offset = f64::from(quiet_zone);
+        let scale = f64::from(image.width()) / (width as f64 + 2.0 * offset);
+        for rect in self.rects(width) {
+            let pixels = |start: f64, len: f64| {
+                let start = ((start + offset) * scale).round() as u32;
+                start..((len * scale).round() as u32 + start)
+            };
+            let color = if rect.dark { 0 } else { 255 };
+            for y in pixels(rect.y, rect.height) {
+                for x in pixels(rect.x, rect.width) {
+                    if x < image.width() && y < image.height() {
+                        image.put_pixel(x, y, LumaA([color, 255]));
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,221 @@
+//! Account numbers and payment references of credit transfers.
+
+/// Lengths of the IBANs of the SEPA countries and territories.
+#[rustfmt::skip]
+const IBAN_LENGTHS: &[(&str, usize)] = &[
+    ("AD", 24), ("AT", 20), ("BE", 16), ("BG", 22), ("BL", 27), ("CH", 21), ("CY", 28),
+    ("CZ", 24), ("DE", 22), ("DK", 18), ("EE", 20), ("ES", 24), ("FI", 18), ("FR", 27),
+    ("GB", 22), ("GF", 27), ("GI", 23), ("GP", 27), ("GR", 27), ("HR", 21), ("HU", 28),
+    ("IE", 22), ("IS", 26), ("IT", 27), ("LI", 21), ("LT", 20), ("LU", 20), ("LV", 21),
+    ("MC", 27), ("MF", 27), ("MQ", 27), ("MT", 31), ("NL", 18), ("NO", 15), ("PL", 28),
+    ("PM", 27), ("PT", 25), ("RE", 27), ("RO", 24), ("SE", 24), ("SI", 19), ("SK", 24),
+    ("SM", 27), ("VA", 22), ("YT", 27),
+];
+
+/// Largest amount of a credit transfer in cents.
+const MAX_AMOUNT: u64 = 99_999_999_999;
+
+#[derive(Debug, thiserror::Error)]
+pub enum BankError {
+    #[error("Bitte die IBAN angeben.")]
+    MissingIban,
+    #[error("„{0}“ ist keine IBAN eines Landes im SEPA-Raum.")]
+    UnknownCountry(String),
+    #[error("Eine IBAN aus {country} hat {expected} Stellen, nicht {actual}.")]
+    IbanLength {
+        country: String,
+        expected: usize,
+        actual: usize,
+    },
+    #[error("Die IBAN darf nur Buchstaben und Ziffern enthalten.")]
+    InvalidIban,
+    #[error("Die Prüfziffern der IBAN stimmen nicht, bitte auf Tippfehler prüfen.")]
+    IbanChecksum,
+    #[error("„{0}“ ist keine gültige BIC, sie hat 8 oder 11 Stellen.")]
+    InvalidBic(String),
+    #[error("Die Prüfziffern der Referenz „{0}“ stimmen nicht.")]
+    ReferenceChecksum(String),
+    #[error("„{0}“ ist kein gültiger Betrag, bitte z.B. „1234,56“ angeben.")]
+    InvalidAmount(String),
+    #[error("Der Betrag muss zwischen 0,01 und 999.999.999,99 liegen.")]
+    AmountOutOfRange,
+}
+
+/// Validates an IBAN and returns it in its electronic format, i.e. without spaces.
+pub fn iban(iban: &str) -> Result<String, BankError> {
+    let iban = compact(iban);
+    if iban.is_empty() {
+        return Err(BankError::MissingIban);
+    }
+    if !iban.bytes().all(|b| b.is_ascii_alphanumeric()) {
+        return Err(BankError::InvalidIban);
+    }
+    let country = iban.get(..2).unwrap_or_default();
+    let (_, expected) = IBAN_LENGTHS
+        .iter()
+        .find(|(code, _)| *code == country)
+        .ok_or_else(|| BankError::UnknownCountry(iban.clone()))?;
+    if iban.len() != *expected {
+        return Err(BankError::IbanLength {
+            country: country.to_owned(),
+            expected: *expected,
+            actual: iban.len(),
+        });
+    }
+    if !iban[2..4].bytes().all(|b| b.is_ascii_digit()) || mod97(&iban[4..], &iban[..4]) != 1 {
+        return Err(BankError::IbanChecksum);
+    }
+    Ok(iban)
+}
+
+/// Validates a BIC, e.g. "BELADEBEXXX", and returns it without spaces.
+pub fn bic(bic: &str) -> Result<String, BankError> {
+    let bic = compact(bic);
+    let bytes = bic.as_bytes();
+    let valid = matches!(bic.len(), 8 | 11)
+        && bytes[..6].iter().all(u8::is_ascii_uppercase)
+        && bytes[6..].iter().all(u8::is_ascii_alphanumeric);
+    match valid {
+        true => Ok(bic),
+        false => Err(BankError::InvalidBic(bic)),
+    }
+}
+
+/// Checks if `reference` looks like an ISO 11649 creditor reference, e.g. "RF18 5390 0754 7034",
+/// i.e. "RF", two check digits and 1 to 21 letters or digits.
+pub fn is_creditor_reference(reference: &str) -> bool {
+    let reference = compact(reference);
+    let Some(rest) = reference.strip_prefix("RF") else {
+        return false;
+    };
+    let (check, body) = rest.split_at(rest.len().min(2));
+    check.len() == 2
+        && check.bytes().all(|b| b.is_ascii_digit())
+        && (1..=21).contains(&body.len())
+        && body.bytes().all(|b| b.is_ascii_alphanumeric())
+}
+
+/// Validates an ISO 11649 creditor reference and returns it without spaces.
+pub fn creditor_reference(reference: &str) -> Result<String, BankError> {
+    let reference = compact(reference);
+    match is_creditor_reference(&reference) && mod97(&reference[4..], &reference[..4]) == 1 {
+        true => Ok(reference),
+        false => Err(BankError::ReferenceChecksum(reference)),
+    }
+}
+
+/// Parses an amount like "1.234,56", "1'234.50", "1234.5" or "12" into cents.
+pub fn cents(amount: &str) -> Result<u64, BankError> {
+    let invalid = || BankError::InvalidAmount(amount.to_owned());
+    let amount = amount.trim_end_matches(['€', ' ']).trim().replace('\'', "");
+    let amount = match amount.contains(',') {
+        // German notation with optional thousands separators
+        true => amount.replace('.', "").replace(',', "."),
+        false => amount,
+    };
+    let (whole, cents) = amount.split_once('.').unwrap_or((&amount, ""));
+    if whole.is_empty()
+        || cents.len() > 2
+        || !whole
+            .bytes()
+            .chain(cents.bytes())
+            .all(|b| b.is_ascii_digit())
+        || whole.len() > 9
+    {
+        return Err(invalid());
+    }
+    let whole: u64 = whole.parse().map_err(|_| invalid())?;
+    let cents: u64 = format!("{cents:0<2}").parse().map_err(|_| invalid())?;
+    match 100 * whole + cents {
+        cents @ 1..=MAX_AMOUNT => Ok(cents),
+        _ => Err(BankError::AmountOutOfRange),
+    }
+}
+
+/// Removes spaces and upper-cases letters.
+fn compact(text: &str) -> String {
+    text.chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+/// The ISO 7064 MOD 97-10 remainder of `body` followed by `head`, with letters as 10 to 35.
+fn mod97(body: &str, head: &str) -> u32 {
+    body.chars()
+        .chain(head.chars())
+        .filter_map(|c| c.to_digit(36))
+        .fold(0, |remainder, value| match value {
+            0..=9 => (remainder * 10 + value) % 97,
+            _ => (remainder * 100 + value) % 97,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_ibans() {
+        assert_eq!(
+            iban("DE89 3704 0044 0532 0130 00").unwrap(),
+            "DE89370400440532013000"
+        );
+        assert_eq!(
+            iban("ch93 0076 2011 6238 5295 7").unwrap(),
+            "CH9300762011623852957"
+        );
+        assert_eq!(iban("NO9386011117947").unwrap(), "NO9386011117947");
+    }
+
+    #[test]
+    fn invalid_ibans() {
+        assert!(matches!(
+            iban("DE89 3704 0044 0532 0130 01"),
+            Err(BankError::IbanChecksum)
+        ));
+        assert!(matches!(
+            iban("DE89 3704 0044 0532 0130"),
+            Err(BankError::IbanLength { .. })
+        ));
+        assert!(matches!(
+            iban("US12 3456"),
+            Err(BankError::UnknownCountry(_))
+        ));
+        assert!(matches!(iban("DE89-3704"), Err(BankError::InvalidIban)));
+    }
+
+    #[test]
+    fn bics() {
+        assert_eq!(bic("bela de be").unwrap(), "BELADEBE");
+        assert!(bic("BELADEBEXXX").is_ok());
+        assert!(bic("BEL4DEBE").is_err());
+        assert!(bic("BELADEBEX").is_err());
+    }
+
+    #[test]
+    fn creditor_references() {
+        assert_eq!(
+            creditor_reference("RF18 5390 0754 7034").unwrap(),
+            "RF18539007547034"
+        );
+        assert!(creditor_reference("RF19 5390 0754 7034").is_err());
+        assert!(!is_creditor_reference("Rechnung 4711"));
+        assert!(!is_creditor_reference("RFC Workshop"));
+        assert!(!is_creditor_reference("RF18"));
+        assert!(!is_creditor_reference("RF18-4711"));
+        assert!(!is_creditor_reference("RF18 1234 5678 9012 3456 7890 12"));
+        assert!(is_creditor_reference("rf18 5390 0754 7034"));
+    }
+
+    #[test]
+    fn amounts() {
+        assert_eq!(cents("12").unwrap(), 1200);
+        assert_eq!(cents("0,01 €").unwrap(), 1);
+        assert_eq!(cents("999999999.99").unwrap(), MAX_AMOUNT);
+        assert!(cents("0").is_err());
+        assert!(cents("1,234").is_err());
+        assert!(cents("-5").is_err());
+        assert!(cents("1e3").is_err());
+    }
+}
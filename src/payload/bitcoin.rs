@@ -0,0 +1,390 @@
+//! Bitcoin payment requests as BIP21 `bitcoin:` URI, and Lightning invoices (BOLT11).
+
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use super::provider::{Field, Input};
+use super::{percent_encode, Payload};
+
+const BASE58: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+const BECH32: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+/// The checksum constants of bech32 (BIP173) and bech32m (BIP350).
+const BECH32_CONST: u32 = 1;
+const BECH32M_CONST: u32 = 0x2bc8_30a3;
+
+const SATOSHIS_PER_BITCOIN: u64 = 100_000_000;
+const MAX_SATOSHIS: u64 = 21_000_000 * SATOSHIS_PER_BITCOIN;
+
+#[derive(Debug, thiserror::Error)]
+pub enum BitcoinError {
+    #[error("Bitte eine Bitcoin-Adresse oder eine Lightning-Rechnung angeben.")]
+    Missing,
+    #[error("„{0}“ ist keine gültige Bitcoin-Adresse.")]
+    InvalidAddress(String),
+    #[error("Die Prüfsumme der Bitcoin-Adresse stimmt nicht, bitte auf Tippfehler prüfen.")]
+    AddressChecksum,
+    #[error("Die Lightning-Rechnung ist ungültig.")]
+    InvalidInvoice,
+    #[error("Die Prüfsumme der Lightning-Rechnung stimmt nicht, bitte auf Tippfehler prüfen.")]
+    InvoiceChecksum,
+    #[error("„{0}“ ist kein gültiger Betrag in Bitcoin.")]
+    InvalidAmount(String),
+    #[error("Es gibt höchstens 21 Millionen Bitcoin.")]
+    AmountOutOfRange,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Form {
+    pub address: String,
+    /// In bitcoin, e.g. `0.0015`.
+    pub amount: String,
+    pub label: String,
+    pub message: String,
+    /// A BOLT11 invoice or LNURL, alone or as fallback for wallets with Lightning support.
+    pub lightning: String,
+}
+
+pub const SCHEMA: &[Field] = &[
+    Field {
+        name: "address",
+        label: "Bitcoin-Adresse",
+        input: Input::TEXT,
+    },
+    Field {
+        name: "amount",
+        label: "Betrag (BTC)",
+        input: Input::Text {
+            kind: "text",
+            inputmode: Some("decimal"),
+            placeholder: Some("beliebig"),
+        },
+    },
+    Field {
+        name: "label",
+        label: "Empfänger",
+        input: Input::TEXT,
+    },
+    Field {
+        name: "message",
+        label: "Nachricht",
+        input: Input::TEXT,
+    },
+    Field {
+        name: "lightning",
+        label: "Lightning-Rechnung",
+        input: Input::Text {
+            kind: "text",
+            inputmode: None,
+            placeholder: Some("lnbc…"),
+        },
+    },
+];
+
+impl Form {
+    pub fn payload(&self) -> Result<Payload, BitcoinError> {
+        let address = self.address.trim();
+        let lightning = match self.lightning.trim() {
+            "" => None,
+            invoice => Some(lightning_invoice(invoice)?),
+        };
+        if address.is_empty() {
+            // an invoice contains the amount and description itself
+            return match lightning {
+                Some(invoice) => Ok(Payload::text(format!("LIGHTNING:{invoice}"))),
+                None => Err(BitcoinError::Missing),
+            };
+        }
+
+        let mut text = format!("bitcoin:{}", address_for_uri(address)?);
+        let mut params = Vec::new();
+        if !self.amount.trim().is_empty() {
+            params.push(format!("amount={}", format_amount(satoshis(&self.amount)?)));
+        }
+        for (name, value) in [("label", &self.label), ("message", &self.message)] {
+            if !value.trim().is_empty() {
+                params.push(format!("{name}={}", percent_encode(value.trim(), "")));
+            }
+        }
+        if let Some(invoice) = lightning {
+            params.push(format!("lightning={invoice}"));
+        }
+        if !params.is_empty() {
+            text.push('?');
+            text.push_str(&params.join("&"));
+        }
+        Ok(Payload::text(text))
+    }
+}
+
+/// Validates an address, bech32 addresses are uppercased to fit the alphanumeric mode.
+fn address_for_uri(address: &str) -> Result<String, BitcoinError> {
+    let invalid = || BitcoinError::InvalidAddress(address.to_owned());
+    match address.get(..3).map(str::to_ascii_lowercase).as_deref() {
+        Some("bc1" | "tb1") => {
+            let (hrp, data, constant) = bech32_decode(address).ok_or_else(invalid)?;
+            if constant == 0 {
+                return Err(BitcoinError::AddressChecksum);
+            }
+            let (&version, program) = data.split_first().ok_or_else(invalid)?;
+            let program = convert_bits(program, 5, 8).ok_or_else(invalid)?;
+            let valid = match version {
+                0 => constant == BECH32_CONST && matches!(program.len(), 20 | 32),
+                1..=16 => constant == BECH32M_CONST && (2..=40).contains(&program.len()),
+                _ => false,
+            };
+            match valid && matches!(hrp.as_str(), "bc" | "tb") {
+                true => Ok(address.to_ascii_uppercase()),
+                false => Err(invalid()),
+            }
+        },
+        _ => {
+            let data = base58_decode(address).ok_or_else(invalid)?;
+            if data.len() != 25 || !matches!(data[0], 0x00 | 0x05 | 0x6f | 0xc4) {
+                return Err(invalid());
+            }
+            let (payload, checksum) = data.split_at(21);
+            match Sha256::digest(Sha256::digest(payload))[..4] == *checksum {
+                true => Ok(address.to_owned()),
+                false => Err(BitcoinError::AddressChecksum),
+            }
+        },
+    }
+}
+
+/// Validates a Lightning invoice or LNURL, which is uppercased to fit the alphanumeric mode.
+fn lightning_invoice(invoice: &str) -> Result<String, BitcoinError> {
+    let invoice = match invoice.get(..10) {
+        Some(scheme) if scheme.eq_ignore_ascii_case("lightning:") => &invoice[10..],
+        _ => invoice,
+    };
+    let Some((hrp, _, constant)) = bech32_decode(invoice) else {
+        return Err(BitcoinError::InvalidInvoice);
+    };
+    if !hrp.starts_with("ln") {
+        return Err(BitcoinError::InvalidInvoice);
+    }
+    match constant {
+        BECH32_CONST => Ok(invoice.to_ascii_uppercase()),
+        _ => Err(BitcoinError::InvoiceChecksum),
+    }
+}
+
+/// Parses an amount in bitcoin, accepting a decimal comma.
+fn satoshis(amount: &str) -> Result<u64, BitcoinError> {
+    let invalid = || BitcoinError::InvalidAmount(amount.to_owned());
+    let value = amount
+        .trim()
+        .trim_end_matches("BTC")
+        .trim_end()
+        .replace(',', ".");
+    let (whole, fraction) = value.split_once('.').unwrap_or((&value, ""));
+    let digits = |text: &str| text.bytes().all(|b| b.is_ascii_digit());
+    if (whole.is_empty() && fraction.is_empty())
+        || fraction.len() > 8
+        || !digits(whole)
+        || !digits(fraction)
+    {
+        return Err(invalid());
+    }
+    let whole: u64 = match whole {
+        "" => 0,
+        whole => whole.parse().map_err(|_| BitcoinError::AmountOutOfRange)?,
+    };
+    let fraction: u64 = format!("{fraction:0<8}").parse().map_err(|_| invalid())?;
+    let satoshis = whole
+        .checked_mul(SATOSHIS_PER_BITCOIN)
+        .and_then(|s| s.checked_add(fraction))
+        .filter(|&s| s <= MAX_SATOSHIS)
+        .ok_or(BitcoinError::AmountOutOfRange)?;
+    match satoshis {
+        0 => Err(invalid()),
+        satoshis => Ok(satoshis),
+    }
+}
+
+fn format_amount(satoshis: u64) -> String {
+    let whole = satoshis / SATOSHIS_PER_BITCOIN;
+    match satoshis % SATOSHIS_PER_BITCOIN {
+        0 => whole.to_string(),
+        fraction => {
+            let fraction = format!("{fraction:08}");
+            format!("{whole}.{}", fraction.trim_end_matches('0'))
+        },
+    }
+}
+
+fn base58_decode(text: &str) -> Option<Vec<u8>> {
+    let mut number: Vec<u8> = Vec::new();
+    for c in text.bytes() {
+        let mut carry = BASE58.iter().position(|&b| b == c)? as u32;
+        for byte in number.iter_mut().rev() {
+            carry += u32::from(*byte) * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            number.insert(0, carry as u8);
+            carry >>= 8;
+        }
+    }
+    let zeros = text.bytes().take_while(|&c| c == b'1').count();
+    let mut data = vec![0; zeros];
+    data.extend(number);
+    Some(data)
+}
+
+/// Splits a bech32 string into its human readable part, the data without checksum, and the
+/// checksum constant, which is [`BECH32_CONST`] or [`BECH32M_CONST`] if the checksum is valid.
+fn bech32_decode(text: &str) -> Option<(String, Vec<u8>, u32)> {
+    if text.bytes().any(|b| b.is_ascii_lowercase()) && text.bytes().any(|b| b.is_ascii_uppercase())
+    {
+        return None;
+    }
+    let text = text.to_ascii_lowercase();
+    let (hrp, data) = text.rsplit_once('1')?;
+    if hrp.is_empty() || data.len() < 6 || !hrp.bytes().all(|b| (33..=126).contains(&b)) {
+        return None;
+    }
+    let data = data
+        .bytes()
+        .map(|c| BECH32.iter().position(|&b| b == c).map(|v| v as u8))
+        .collect::<Option<Vec<u8>>>()?;
+
+    let mut values: Vec<u8> = hrp.bytes().map(|b| b >> 5).collect();
+    values.push(0);
+    values.extend(hrp.bytes().map(|b| b & 31));
+    values.extend(&data);
+    let constant = match polymod(&values) {
+        constant @ (BECH32_CONST | BECH32M_CONST) => constant,
+        _ => 0,
+    };
+    Some((hrp.to_owned(), data[..data.len() - 6].to_vec(), constant))
+}
+
+fn polymod(values: &[u8]) -> u32 {
+    const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+    let mut checksum = 1u32;
+    for &value in values {
+        let top = checksum >> 25;
+        checksum = (checksum & 0x1ff_ffff) << 5 ^ u32::from(value);
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    }
+    checksum
+}
+
+/// Regroups bits without padding, failing if the left-over bits are not zero.
+fn convert_bits(data: &[u8], from: u32, to: u32) -> Option<Vec<u8>> {
+    let (mut acc, mut bits) = (0u32, 0);
+    let mut result = Vec::new();
+    for &value in data {
+        acc = (acc << from) | u32::from(value);
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            result.push((acc >> bits) as u8 & ((1 << to) - 1) as u8);
+        }
+    }
+    match bits < from && (acc << (to - bits)) & ((1 << to) - 1) == 0 {
+        true => Some(result),
+        false => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INVOICE: &str =
+        "lnbc10u1qqqsyqcyq5rqwzqfpg9scrgwpugpzysnzs23v9ccrydpk8qarc0jqgfzyvjz2f389q5j52ev\
+                           95hz7vp3xgengdfkxuurjw3m5fnmkx";
+
+    fn form(address: &str, amount: &str, lightning: &str) -> Form {
+        Form {
+            address: address.to_owned(),
+            amount: amount.to_owned(),
+            label: "Fachschaft Vetmed".to_owned(),
+            message: "Spende für das Sommerfest".to_owned(),
+            lightning: lightning.to_owned(),
+        }
+    }
+
+    #[test]
+    fn bip21() {
+        let text = form("1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH", "0,0015", "")
+            .payload()
+            .unwrap()
+            .text;
+        assert_eq!(
+            text,
+            "bitcoin:1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH?amount=0.0015&label=Fachschaft%20Vetmed&\
+             message=Spende%20f%C3%BCr%20das%20Sommerfest"
+        );
+        let text = form("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4", "", INVOICE)
+            .payload()
+            .unwrap()
+            .text;
+        assert!(text.starts_with("bitcoin:BC1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4?label="));
+        assert!(text.ends_with(&format!("&lightning={}", INVOICE.to_ascii_uppercase())));
+    }
+
+    #[test]
+    fn addresses() {
+        for address in [
+            "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH",
+            "3CNHUhP3uyB9EUtRLsmvFUmvGdjGdkTxJw",
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+            "TB1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KXPJZSX",
+            "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0",
+        ] {
+            assert!(address_for_uri(address).is_ok(), "{address}");
+        }
+        let checksum =
+            |address| matches!(address_for_uri(address), Err(BitcoinError::AddressChecksum));
+        assert!(checksum("1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMJ"));
+        assert!(checksum("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t5"));
+        // a witness version 1 program with a bech32 instead of a bech32m checksum
+        let bech32 = "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqh2y7hd";
+        assert!(matches!(
+            address_for_uri(bech32),
+            Err(BitcoinError::InvalidAddress(_))
+        ));
+        assert!(address_for_uri("bc1qW508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4").is_err());
+        assert!(address_for_uri("0BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH").is_err());
+    }
+
+    #[test]
+    fn lightning() {
+        assert_eq!(
+            form("", "", &format!("lightning:{INVOICE}"))
+                .payload()
+                .unwrap()
+                .text,
+            format!("LIGHTNING:{}", INVOICE.to_ascii_uppercase()),
+        );
+        let typo = INVOICE.replace("lnbc10u", "lnbc20u");
+        assert!(matches!(
+            lightning_invoice(&typo),
+            Err(BitcoinError::InvoiceChecksum)
+        ));
+        assert!(lightning_invoice("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4").is_err());
+        assert!(form("", "", "").payload().is_err());
+    }
+
+    #[test]
+    fn amounts() {
+        assert_eq!(satoshis("1").unwrap(), SATOSHIS_PER_BITCOIN);
+        assert_eq!(satoshis(",5 BTC").unwrap(), SATOSHIS_PER_BITCOIN / 2);
+        assert_eq!(format_amount(satoshis("0.00000001").unwrap()), "0.00000001");
+        assert_eq!(format_amount(satoshis("21000000").unwrap()), "21000000");
+        assert!(satoshis("21000000.00000001").is_err());
+        assert!(satoshis("0.000000001").is_err());
+        assert!(satoshis("0").is_err());
+        assert!(satoshis("1.2.3").is_err());
+    }
+}
@@ -0,0 +1,637 @@
+//! Contacts as vCard 3.0 (RFC 2426), vCard 4.0 (RFC 6350) or the more compact MeCard.
+
+use serde::{Deserialize, Serialize};
+
+use super::email::is_valid_address;
+use super::provider::{self, Field, Import, Input};
+use super::tel::{self, TelError};
+use super::{backslash_escape, components, content_lines, escape_text, fold, Payload};
+
+/// Characters to escape in MeCard values.
+const MECARD_SPECIAL: &str = "\\;:,";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ContactError {
+    #[error("Bitte einen Namen oder eine Organisation angeben.")]
+    MissingName,
+    #[error(transparent)]
+    Tel(#[from] TelError),
+    #[error("„{0}“ ist keine gültige E-Mail-Adresse.")]
+    InvalidEmail(String),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    #[default]
+    Vcard3,
+    Vcard4,
+    /// MeCard has no field for the job title, which is left out.
+    Mecard,
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Form {
+    /// Chosen once for all contacts, so imported contacts leave it alone.
+    #[serde(skip_serializing)]
+    pub format: Format,
+    pub given: String,
+    pub family: String,
+    pub org: String,
+    pub title: String,
+    pub phone: String,
+    pub mobile: String,
+    pub email: String,
+    pub url: String,
+    pub street: String,
+    pub zip: String,
+    pub city: String,
+    pub country: String,
+    pub note: String,
+}
+
+pub const SCHEMA: &[Field] = &[
+    Field {
+        name: "format",
+        label: "Format",
+        input: Input::Select {
+            options: &[
+                ("vcard3", "vCard 3.0"),
+                ("vcard4", "vCard 4.0"),
+                ("mecard", "MeCard (kompakter, ohne Position)"),
+            ],
+            default: "vcard3",
+        },
+    },
+    Field {
+        name: "given",
+        label: "Vorname",
+        input: Input::TEXT,
+    },
+    Field {
+        name: "family",
+        label: "Nachname",
+        input: Input::TEXT,
+    },
+    Field {
+        name: "org",
+        label: "Organisation",
+        input: Input::TEXT,
+    },
+    Field {
+        name: "title",
+        label: "Position",
+        input: Input::TEXT,
+    },
+    Field {
+        name: "phone",
+        label: "Telefon",
+        input: Input::Text {
+            kind: "tel",
+            inputmode: None,
+            placeholder: None,
+        },
+    },
+    Field {
+        name: "mobile",
+        label: "Mobiltelefon",
+        input: Input::Text {
+            kind: "tel",
+            inputmode: None,
+            placeholder: None,
+        },
+    },
+    Field {
+        name: "email",
+        label: "E-Mail",
+        input: Input::Text {
+            kind: "email",
+            inputmode: None,
+            placeholder: None,
+        },
+    },
+    Field {
+        name: "url",
+        label: "Webseite",
+        input: Input::Text {
+            kind: "url",
+            inputmode: None,
+            placeholder: None,
+        },
+    },
+    Field {
+        name: "street",
+        label: "Straße",
+        input: Input::TEXT,
+    },
+    Field {
+        name: "zip",
+        label: "Postleitzahl",
+        input: Input::TEXT,
+    },
+    Field {
+        name: "city",
+        label: "Ort",
+        input: Input::TEXT,
+    },
+    Field {
+        name: "country",
+        label: "Land",
+        input: Input::TEXT,
+    },
+    Field {
+        name: "note",
+        label: "Notiz",
+        input: Input::TextArea,
+    },
+    Field {
+        name: "import",
+        label: "Importieren",
+        input: Input::Import {
+            button: "Kontakte aus .vcf-Datei laden …",
+            batch: Some("kontakte.pdf"),
+        },
+    },
+];
+
+pub const IMPORT: Import = Import {
+    title: "Kontakte importieren",
+    filter: Some((&["*.vcf", "*.vcard"], "vCard-Dateien")),
+    read: |_, bytes| {
+        let contacts = import(&String::from_utf8_lossy(bytes));
+        provider::items(contacts, "Die Datei enthält keine Kontakte.")
+    },
+};
+
+/// The validated and normalized fields of a [`Form`].
+struct Contact<'a> {
+    form: &'a Form,
+    name: String,
+    phone: Option<String>,
+    mobile: Option<String>,
+    email: Option<&'a str>,
+}
+
+impl Form {
+    pub fn payload(&self) -> Result<Payload, ContactError> {
+        let contact = self.validate()?;
+        let text = match self.format {
+            Format::Vcard3 | Format::Vcard4 => contact.vcard(self.format),
+            Format::Mecard => contact.mecard(),
+        };
+        Ok(Payload {
+            label: Some(contact.name),
+            ..Payload::text(text)
+        })
+    }
+
+    /// The name to display, falling back to the organisation.
+    pub fn name(&self) -> Option<String> {
+        match (field(&self.given), field(&self.family), field(&self.org)) {
+            (Some(given), Some(family), _) => Some(format!("{given} {family}")),
+            (Some(name), None, _) | (None, Some(name), _) | (None, None, Some(name)) => {
+                Some(name.to_owned())
+            },
+            (None, None, None) => None,
+        }
+    }
+
+    fn validate(&self) -> Result<Contact<'_>, ContactError> {
+        let name = self.name().ok_or(ContactError::MissingName)?;
+        let email = field(&self.email);
+        if let Some(email) = email.filter(|email| !is_valid_address(email)) {
+            return Err(ContactError::InvalidEmail(email.to_owned()));
+        }
+        Ok(Contact {
+            form: self,
+            name,
+            phone: field(&self.phone).map(tel::normalize).transpose()?,
+            mobile: field(&self.mobile).map(tel::normalize).transpose()?,
+            email,
+        })
+    }
+}
+
+impl Contact<'_> {
+    fn vcard(&self, format: Format) -> String {
+        let form = self.form;
+        let v4 = format == Format::Vcard4;
+        let mut lines = vec![
+            "BEGIN:VCARD".to_owned(),
+            format!("VERSION:{}", if v4 { "4.0" } else { "3.0" }),
+            format!("N:{}", structured(&[&form.family, &form.given, "", "", ""])),
+            format!("FN:{}", escape_text(&self.name)),
+        ];
+        if let Some(org) = field(&form.org) {
+            lines.push(format!("ORG:{}", escape_text(org)));
+        }
+        if let Some(title) = field(&form.title) {
+            lines.push(format!("TITLE:{}", escape_text(title)));
+        }
+        let phones = [(&self.phone, "WORK,VOICE"), (&self.mobile, "CELL")];
+        for (number, kind) in phones {
+            if let Some(number) = number {
+                lines.push(match v4 {
+                    true => format!(
+                        "TEL;VALUE=uri;TYPE=\"{}\":tel:{number}",
+                        kind.to_lowercase()
+                    ),
+                    false => format!("TEL;TYPE={kind}:{number}"),
+                });
+            }
+        }
+        if let Some(email) = self.email {
+            let kind = if v4 { "work" } else { "INTERNET" };
+            lines.push(format!("EMAIL;TYPE={kind}:{email}"));
+        }
+        if let Some(url) = field(&form.url) {
+            lines.push(format!("URL:{url}"));
+        }
+        let address = [&form.street, &form.city, &form.zip, &form.country];
+        if address.iter().any(|part| field(part).is_some()) {
+            let kind = if v4 { "work" } else { "WORK" };
+            let [street, city, zip, country] = address;
+            let value = structured(&["", "", street, city, "", zip, country]);
+            lines.push(format!("ADR;TYPE={kind}:{value}"));
+        }
+        if let Some(note) = field(&form.note) {
+            lines.push(format!("NOTE:{}", escape_text(note)));
+        }
+        lines.push("END:VCARD".to_owned());
+
+        let mut text = String::new();
+        for line in &lines {
+            fold(&mut text, line);
+            text.push_str("\r\n");
+        }
+        text
+    }
+
+    fn mecard(&self) -> String {
+        let form = self.form;
+        let name = match (field(&form.family), field(&form.given)) {
+            (Some(family), Some(given)) => {
+                format!("{},{}", mecard_text(family), mecard_text(given))
+            },
+            _ => mecard_text(&self.name),
+        };
+        let address = [
+            field(&form.street).map(str::to_owned),
+            match (field(&form.zip), field(&form.city)) {
+                (Some(zip), Some(city)) => Some(format!("{zip} {city}")),
+                (zip, city) => zip.or(city).map(str::to_owned),
+            },
+            field(&form.country).map(str::to_owned),
+        ];
+        let address: Vec<_> = address.into_iter().flatten().collect();
+
+        let mut text = format!("MECARD:N:{name};");
+        let fields = [
+            ("ORG", field(&form.org).map(mecard_text)),
+            ("TEL", self.phone.as_deref().map(mecard_text)),
+            ("TEL", self.mobile.as_deref().map(mecard_text)),
+            ("EMAIL", self.email.map(mecard_text)),
+            ("URL", field(&form.url).map(mecard_text)),
+            (
+                "ADR",
+                Some(mecard_text(&address.join(", "))).filter(|a| !a.is_empty()),
+            ),
+            ("NOTE", field(&form.note).map(mecard_text)),
+        ];
+        for (name, value) in fields {
+            if let Some(value) = value {
+                text.push_str(&format!("{name}:{value};"));
+            }
+        }
+        text.push(';');
+        text
+    }
+}
+
+/// Reads the contacts of a vCard file (versions 2.1 to 4.0), keeping only the first phone
+/// numbers, e-mail address, website and address; notes, photos and the like are dropped to keep
+/// the codes small.
+pub fn import(text: &str) -> Vec<Form> {
+    let mut contacts = Vec::new();
+    let mut contact: Option<(Form, String)> = None;
+    for line in content_lines(text) {
+        let value = line.decoded_value();
+        match (line.name.as_str(), &mut contact) {
+            ("BEGIN", _) if value.eq_ignore_ascii_case("VCARD") => {
+                contact = Some((Form::default(), String::new()));
+            },
+            ("END", Some(_)) if value.eq_ignore_ascii_case("VCARD") => {
+                let (mut form, formatted_name) = contact.take().unwrap();
+                if form.name().is_none() {
+                    form.given = formatted_name;
+                }
+                if form.name().is_some() {
+                    contacts.push(form);
+                }
+            },
+            ("N", Some((form, _))) => {
+                let mut parts = components(&value).into_iter();
+                form.family = parts.next().unwrap_or_default().trim().to_owned();
+                form.given = parts.next().unwrap_or_default().trim().to_owned();
+            },
+            ("FN", Some((_, name))) => *name = components(&value).concat().trim().to_owned(),
+            ("ORG", Some((form, _))) => {
+                form.org = components(&value).swap_remove(0).trim().to_owned();
+            },
+            ("TITLE", Some((form, _))) => set_once(&mut form.title, &components(&value)[0]),
+            ("TEL", Some((form, _))) => {
+                let number = value.trim().trim_start_matches("tel:");
+                match line.has_type("CELL") {
+                    true => set_once(&mut form.mobile, number),
+                    false if line.has_type("FAX") => {},
+                    false => set_once(&mut form.phone, number),
+                }
+            },
+            ("EMAIL", Some((form, _))) => set_once(&mut form.email, value.trim()),
+            ("URL", Some((form, _))) => set_once(&mut form.url, value.trim()),
+            ("ADR", Some((form, _))) if form.street.is_empty() && form.city.is_empty() => {
+                let parts = components(&value);
+                let part = |i: usize| parts.get(i).map_or("", |part| part.trim()).to_owned();
+                form.street = part(2);
+                form.city = part(3);
+                form.zip = part(5);
+                form.country = part(6);
+            },
+            _ => {},
+        }
+    }
+    contacts
+}
+
+/// Keeps the first of repeated properties.
+fn set_once(field: &mut String, value: &str) {
+    if field.is_empty() {
+        value.trim().clone_into(field);
+    }
+}
+
+/// The trimmed value of a field, if it is not empty.
+fn field(value: &str) -> Option<&str> {
+    Some(value.trim()).filter(|value| !value.is_empty())
+}
+
+/// A value with components separated by semicolons, like the name or address.
+fn structured(components: &[&str]) -> String {
+    let components: Vec<_> = components.iter().map(|c| escape_text(c)).collect();
+    components.join(";")
+}
+
+fn mecard_text(text: &str) -> String {
+    backslash_escape(text.trim(), MECARD_SPECIAL)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payload::LINE_LEN;
+
+    fn form(format: Format) -> Form {
+        Form {
+            format,
+            given: "Erika".to_owned(),
+            family: "Mustermann; geb. Gabler".to_owned(),
+            org: "Freie Universität Berlin, Fachbereich Veterinärmedizin".to_owned(),
+            title: "Dr. med. vet.".to_owned(),
+            phone: "+49 30 838-51833".to_owned(),
+            mobile: "".to_owned(),
+            email: "erika.mustermann@example.com".to_owned(),
+            url: "https://www.vetmed.fu-berlin.de/".to_owned(),
+            street: "Oertzenweg 19b".to_owned(),
+            zip: "14163".to_owned(),
+            city: "Berlin".to_owned(),
+            country: "".to_owned(),
+            note: "Sprechzeiten:\nMo–Fr 8–12 Uhr\\nach Vereinbarung".to_owned(),
+        }
+    }
+
+    /// Splits an escaped value at unescaped `separator`s and unescapes the parts.
+    fn unescape(value: &str, separator: char) -> Vec<String> {
+        let mut parts = vec![String::new()];
+        let mut chars = value.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => match chars.next().unwrap() {
+                    'n' | 'N' => parts.last_mut().unwrap().push('\n'),
+                    c => parts.last_mut().unwrap().push(c),
+                },
+                c if c == separator => parts.push(String::new()),
+                c => parts.last_mut().unwrap().push(c),
+            }
+        }
+        parts
+    }
+
+    /// Unfolds a vCard into properties with their parameters and raw values.
+    fn parse_vcard(text: &str) -> Vec<(String, String)> {
+        assert!(text.ends_with("\r\n"));
+        for line in text.split("\r\n") {
+            assert!(line.len() <= LINE_LEN, "{line:?}");
+        }
+        text.replace("\r\n ", "")
+            .lines()
+            .map(|line| {
+                let (name, value) = line.split_once(':').unwrap();
+                (name.to_owned(), value.to_owned())
+            })
+            .collect()
+    }
+
+    fn property<'a>(properties: &'a [(String, String)], name: &str) -> &'a str {
+        let (_, value) = properties.iter().find(|(n, _)| n == name).unwrap();
+        value
+    }
+
+    #[test]
+    fn vcard3_round_trip() {
+        let form = form(Format::Vcard3);
+        let properties = parse_vcard(&form.payload().unwrap().text);
+        assert_eq!(properties[0], ("BEGIN".to_owned(), "VCARD".to_owned()));
+        assert_eq!(property(&properties, "VERSION"), "3.0");
+        assert_eq!(unescape(property(&properties, "N"), ';'), [
+            form.family.as_str(),
+            form.given.as_str(),
+            "",
+            "",
+            ""
+        ]);
+        assert_eq!(unescape(property(&properties, "FN"), ';'), [format!(
+            "{} {}",
+            form.given, form.family
+        )]);
+        assert_eq!(unescape(property(&properties, "ORG"), ';'), [form
+            .org
+            .as_str()]);
+        assert_eq!(unescape(property(&properties, "TITLE"), ';'), [form
+            .title
+            .as_str()]);
+        assert_eq!(
+            property(&properties, "TEL;TYPE=WORK,VOICE"),
+            "+493083851833"
+        );
+        assert_eq!(property(&properties, "EMAIL;TYPE=INTERNET"), form.email);
+        assert_eq!(property(&properties, "URL"), form.url);
+        assert_eq!(unescape(property(&properties, "ADR;TYPE=WORK"), ';'), [
+            "",
+            "",
+            form.street.as_str(),
+            form.city.as_str(),
+            "",
+            form.zip.as_str(),
+            ""
+        ]);
+        assert_eq!(unescape(property(&properties, "NOTE"), ';'), [form
+            .note
+            .as_str()]);
+        assert_eq!(properties.last().unwrap().0, "END");
+    }
+
+    #[test]
+    fn vcard4_round_trip() {
+        let form = Form {
+            mobile: "0171 1234567".to_owned(),
+            ..form(Format::Vcard4)
+        };
+        let properties = parse_vcard(&form.payload().unwrap().text);
+        assert_eq!(property(&properties, "VERSION"), "4.0");
+        assert_eq!(
+            property(&properties, "TEL;VALUE=uri;TYPE=\"work,voice\""),
+            "tel:+493083851833"
+        );
+        assert_eq!(
+            property(&properties, "TEL;VALUE=uri;TYPE=\"cell\""),
+            "tel:01711234567"
+        );
+        assert_eq!(property(&properties, "EMAIL;TYPE=work"), form.email);
+        assert_eq!(unescape(property(&properties, "NOTE"), ';'), [form
+            .note
+            .as_str()]);
+    }
+
+    #[test]
+    fn folds_long_lines() {
+        let form = Form {
+            note: "ä".repeat(100),
+            ..form(Format::Vcard3)
+        };
+        let properties = parse_vcard(&form.payload().unwrap().text);
+        assert_eq!(property(&properties, "NOTE"), form.note);
+    }
+
+    #[test]
+    fn mecard_round_trip() {
+        let form = form(Format::Mecard);
+        let text = form.payload().unwrap().text;
+        let body = text
+            .strip_prefix("MECARD:")
+            .unwrap()
+            .strip_suffix(";;")
+            .unwrap();
+        let fields: Vec<_> = unescape(body, ';')
+            .into_iter()
+            .map(|field| {
+                let (name, value) = field.split_once(':').unwrap();
+                (name.to_owned(), value.to_owned())
+            })
+            .collect();
+        let name = &fields[0].1;
+        assert_eq!(fields, [
+            ("N".to_owned(), name.clone()),
+            ("ORG".to_owned(), form.org.clone()),
+            ("TEL".to_owned(), "+493083851833".to_owned()),
+            ("EMAIL".to_owned(), form.email.clone()),
+            ("URL".to_owned(), form.url.clone()),
+            ("ADR".to_owned(), "Oertzenweg 19b, 14163 Berlin".to_owned()),
+            ("NOTE".to_owned(), form.note.clone()),
+        ]);
+    }
+
+    #[test]
+    fn mecard_name() {
+        let form = form(Format::Mecard);
+        let text = form.payload().unwrap().text;
+        let name = text
+            .strip_prefix("MECARD:N:")
+            .unwrap()
+            .split(";ORG")
+            .next()
+            .unwrap();
+        assert_eq!(unescape(name, ','), [
+            form.family.as_str(),
+            form.given.as_str()
+        ]);
+    }
+
+    #[test]
+    fn organisation_only() {
+        let form = Form {
+            format: Format::Vcard3,
+            org: "Tierklinik".to_owned(),
+            ..Form::default()
+        };
+        let properties = parse_vcard(&form.payload().unwrap().text);
+        assert_eq!(property(&properties, "N"), ";;;;");
+        assert_eq!(property(&properties, "FN"), "Tierklinik");
+        assert!(Form::default().payload().is_err());
+    }
+
+    #[test]
+    fn import_round_trip() {
+        let form = Form {
+            mobile: "0171 1234567".to_owned(),
+            ..form(Format::Vcard4)
+        };
+        let text = form.payload().unwrap().text;
+        let contacts = import(&format!("{text}{}", form.payload().unwrap().text));
+        assert_eq!(contacts.len(), 2);
+        let contact = &contacts[0];
+        assert_eq!(contact.given, form.given);
+        assert_eq!(contact.family, form.family);
+        assert_eq!(contact.org, form.org);
+        assert_eq!(contact.title, form.title);
+        assert_eq!(contact.phone, "+493083851833");
+        assert_eq!(contact.mobile, "01711234567");
+        assert_eq!(contact.email, form.email);
+        assert_eq!(contact.url, form.url);
+        assert_eq!([&contact.street, &contact.zip, &contact.city], [
+            &form.street,
+            &form.zip,
+            &form.city
+        ]);
+        assert_eq!(contact.note, "");
+    }
+
+    #[test]
+    fn import_vcard21() {
+        let text = "BEGIN:VCARD\r\n\
+                    VERSION:2.1\r\n\
+                    N;CHARSET=ISO-8859-1;ENCODING=QUOTED-PRINTABLE:M=FCller;J=F6rg=\r\n\
+                    ;;;\r\n\
+                    TEL;WORK;VOICE:030 123\r\n\
+                    TEL;CELL:0171 456\r\n\
+                    TEL;WORK;FAX:030 789\r\n\
+                    item1.EMAIL;INTERNET:joerg@example.com\r\n\
+                    PHOTO;ENCODING=BASE64;TYPE=JPEG:/9j/4AAQ\r\n\
+                    END:VCARD\r\n\
+                    BEGIN:VCARD\r\n\
+                    VERSION:3.0\r\n\
+                    FN:Nur ein Name\r\n\
+                    END:VCARD\r\n\
+                    BEGIN:VCARD\r\n\
+                    NOTE:Ohne Namen\r\n\
+                    END:VCARD\r\n";
+        let contacts = import(text);
+        assert_eq!(contacts.len(), 2);
+        assert_eq!(contacts[0].family, "Müller");
+        assert_eq!(contacts[0].given, "Jörg");
+        assert_eq!(contacts[0].phone, "030 123");
+        assert_eq!(contacts[0].mobile, "0171 456");
+        assert_eq!(contacts[0].email, "joerg@example.com");
+        assert_eq!(contacts[1].name().unwrap(), "Nur ein Name");
+    }
+}
@@ -0,0 +1,171 @@
+//! E-mails as `mailto:` URIs (RFC 6068).
+
+use serde::Deserialize;
+
+use super::provider::{Field, Input};
+use super::{crlf, percent_encode, Payload};
+
+#[derive(Debug, thiserror::Error)]
+pub enum EmailError {
+    #[error("Bitte mindestens eine E-Mail-Adresse angeben.")]
+    Missing,
+    #[error("„{0}“ ist keine gültige E-Mail-Adresse.")]
+    Invalid(String),
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Form {
+    /// Comma separated addresses.
+    pub to: String,
+    pub cc: String,
+    pub subject: String,
+    pub body: String,
+}
+
+pub const SCHEMA: &[Field] = &[
+    Field {
+        name: "to",
+        label: "An",
+        input: Input::Text {
+            kind: "text",
+            inputmode: None,
+            placeholder: Some("name@example.com"),
+        },
+    },
+    Field {
+        name: "cc",
+        label: "Kopie an",
+        input: Input::TEXT,
+    },
+    Field {
+        name: "subject",
+        label: "Betreff",
+        input: Input::TEXT,
+    },
+    Field {
+        name: "body",
+        label: "Text",
+        input: Input::TextArea,
+    },
+];
+
+impl Form {
+    pub fn payload(&self) -> Result<Payload, EmailError> {
+        let to = addresses(&self.to)?;
+        let cc = addresses(&self.cc)?;
+        if to.is_empty() && cc.is_empty() {
+            return Err(EmailError::Missing);
+        }
+
+        let to: Vec<_> = to
+            .iter()
+            .map(|address| percent_encode(address, "@"))
+            .collect();
+        let mut uri = format!("mailto:{}", to.join(","));
+        let fields = [
+            ("cc", cc.join(",")),
+            ("subject", self.subject.trim().to_owned()),
+            ("body", crlf(&self.body)),
+        ];
+        let mut separator = '?';
+        for (name, value) in fields {
+            if !value.is_empty() {
+                uri.push(separator);
+                uri.push_str(name);
+                uri.push('=');
+                uri.push_str(&percent_encode(&value, "@"));
+                separator = '&';
+            }
+        }
+        Ok(Payload::text(uri))
+    }
+}
+
+/// Splits and validates a comma or semicolon separated list of addresses.
+fn addresses(list: &str) -> Result<Vec<&str>, EmailError> {
+    list.split([',', ';'])
+        .map(str::trim)
+        .filter(|address| !address.is_empty())
+        .map(|address| match is_valid_address(address) {
+            true => Ok(address),
+            false => Err(EmailError::Invalid(address.to_owned())),
+        })
+        .collect()
+}
+
+/// A plausibility check of an e-mail address, not a full RFC 5322 parser.
+pub fn is_valid_address(address: &str) -> bool {
+    match address.rsplit_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+                && !address
+                    .chars()
+                    .any(|c| c.is_whitespace() || "<>\"(),;".contains(c))
+        },
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::percent_decode;
+    use super::*;
+
+    /// Parses a `mailto:` URI into its addresses and header fields.
+    fn parse(uri: &str) -> (String, Vec<(String, String)>) {
+        let uri = uri.strip_prefix("mailto:").unwrap();
+        let (to, query) = uri.split_once('?').unwrap_or((uri, ""));
+        let fields = query
+            .split('&')
+            .filter(|field| !field.is_empty())
+            .map(|field| {
+                let (name, value) = field.split_once('=').unwrap();
+                (name.to_owned(), percent_decode(value))
+            })
+            .collect();
+        (percent_decode(to), fields)
+    }
+
+    #[test]
+    fn round_trip() {
+        let form = Form {
+            to: "info@example.com; Max.Muster+qr@example.org".to_owned(),
+            cc: "".to_owned(),
+            subject: "Anfrage: Öffnungszeiten & Preise?".to_owned(),
+            body: "Hallo,\nwie lange ist geöffnet?\n100% sicher = gut".to_owned(),
+        };
+        let text = form.payload().unwrap().text;
+        assert!(!text.contains(' '));
+        let (to, fields) = parse(&text);
+        assert_eq!(to, "info@example.com,Max.Muster+qr@example.org");
+        assert_eq!(fields, [
+            ("subject".to_owned(), form.subject.clone()),
+            ("body".to_owned(), form.body.replace('\n', "\r\n")),
+        ]);
+    }
+
+    #[test]
+    fn only_cc() {
+        let form = Form {
+            cc: "a@example.com".to_owned(),
+            ..Form::default()
+        };
+        let text = form.payload().unwrap().text;
+        assert_eq!(text, "mailto:?cc=a@example.com");
+    }
+
+    #[test]
+    fn rejects_invalid_addresses() {
+        for to in ["", "example.com", "a b@example.com", "a@example", "a@.com"] {
+            let form = Form {
+                to: to.to_owned(),
+                ..Form::default()
+            };
+            assert!(form.payload().is_err(), "{to:?}");
+        }
+    }
+}
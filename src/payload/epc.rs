@@ -0,0 +1,196 @@
+//! SEPA credit transfers as EPC QR code (EPC069-12), known as GiroCode.
+
+use qrcode::EcLevel;
+use serde::Deserialize;
+
+use super::bank::{self, BankError};
+use super::provider::{Field, Input};
+use super::Payload;
+
+/// Maximum size of the payload in bytes.
+const MAX_LEN: usize = 331;
+
+/// The largest allowed version, which holds [`MAX_LEN`] bytes at level M.
+const MAX_VERSION: i16 = 13;
+
+#[derive(Debug, thiserror::Error)]
+pub enum EpcError {
+    #[error("Bitte den Namen des Empfängers angeben.")]
+    MissingName,
+    #[error("{field} darf höchstens {max} Zeichen lang sein.")]
+    TooLong { field: &'static str, max: usize },
+    #[error(transparent)]
+    Bank(#[from] BankError),
+    #[error(
+        "Die Überweisungsdaten sind mit {0} Bytes zu lang für einen GiroCode (höchstens 331)."
+    )]
+    DataTooLong(usize),
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Form {
+    pub name: String,
+    pub iban: String,
+    /// Optional within the EEA.
+    pub bic: String,
+    /// Euros, with a decimal comma or point.
+    pub amount: String,
+    /// An ISO 11649 creditor reference ("RF…") or an unstructured remittance text.
+    pub reference: String,
+}
+
+pub const SCHEMA: &[Field] = &[
+    Field {
+        name: "name",
+        label: "Empfänger",
+        input: Input::TEXT,
+    },
+    Field {
+        name: "iban",
+        label: "IBAN",
+        input: Input::Text {
+            kind: "text",
+            inputmode: None,
+            placeholder: Some("DE89 3704 0044 0532 0130 00"),
+        },
+    },
+    Field {
+        name: "bic",
+        label: "BIC (optional)",
+        input: Input::TEXT,
+    },
+    Field {
+        name: "amount",
+        label: "Betrag in Euro (optional)",
+        input: Input::Text {
+            kind: "text",
+            inputmode: Some("decimal"),
+            placeholder: Some("1234,56"),
+        },
+    },
+    Field {
+        name: "reference",
+        label: "Verwendungszweck oder RF-Referenz",
+        input: Input::TEXT,
+    },
+];
+
+impl Form {
+    pub fn payload(&self) -> Result<Payload, EpcError> {
+        let name = self.name.trim();
+        if name.is_empty() {
+            return Err(EpcError::MissingName);
+        }
+        check_len("Der Name des Empfängers", name, 70)?;
+        let iban = bank::iban(&self.iban)?;
+        let bic = match self.bic.trim() {
+            "" => String::new(),
+            bic => bank::bic(bic)?,
+        };
+        let amount = match self.amount.trim() {
+            "" => String::new(),
+            amount => {
+                let cents = bank::cents(amount)?;
+                format!("EUR{}.{:02}", cents / 100, cents % 100)
+            },
+        };
+        let reference = self.reference.trim();
+        let (reference, text) = match bank::is_creditor_reference(reference) {
+            true => (bank::creditor_reference(reference)?, ""),
+            false => {
+                check_len("Der Verwendungszweck", reference, 140)?;
+                (String::new(), reference)
+            },
+        };
+
+        // service tag, version 002 with optional BIC, UTF-8, SEPA credit transfer
+        let lines = [
+            "BCD", "002", "1", "SCT", &bic, name, &iban, &amount, "", &reference, text,
+        ];
+        let text = lines.join("\n").trim_end().to_owned();
+        if text.len() > MAX_LEN {
+            return Err(EpcError::DataTooLong(text.len()));
+        }
+        Ok(Payload {
+            ec_level: EcLevel::M,
+            max_version: Some(MAX_VERSION),
+            label: Some(format!("GiroCode an {name}")),
+            ..Payload::text(text)
+        })
+    }
+}
+
+fn check_len(field: &'static str, value: &str, max: usize) -> Result<(), EpcError> {
+    match value.chars().count() <= max {
+        true => Ok(()),
+        false => Err(EpcError::TooLong { field, max }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use qrcode::Version;
+
+    use super::*;
+    use crate::encode::{self, EncodeError, Options};
+
+    fn form(amount: &str, reference: &str) -> Form {
+        Form {
+            name: "Freie Universität Berlin".to_owned(),
+            iban: "DE89 3704 0044 0532 0130 00".to_owned(),
+            bic: "".to_owned(),
+            amount: amount.to_owned(),
+            reference: reference.to_owned(),
+        }
+    }
+
+    #[test]
+    fn unstructured_remittance() {
+        let payload = form("1.234,5", "Rechnung 4711").payload().unwrap();
+        assert_eq!(payload.ec_level, EcLevel::M);
+        assert_eq!(
+            payload.text,
+            "BCD\n002\n1\nSCT\n\nFreie Universität Berlin\nDE89370400440532013000\nEUR1234.50\n\n\n\
+             Rechnung 4711"
+        );
+    }
+
+    #[test]
+    fn creditor_reference() {
+        let payload = form("", "RF18 5390 0754 7034").payload().unwrap();
+        let lines: Vec<_> = payload.text.lines().collect();
+        assert_eq!(lines[7..], ["", "", "RF18539007547034"]);
+
+        // free text that merely starts with "RF"
+        let payload = form("", "RFC Workshop").payload().unwrap();
+        let lines: Vec<_> = payload.text.lines().collect();
+        assert_eq!(lines[7..], ["", "", "", "RFC Workshop"]);
+        assert!(form("", "RF19 5390 0754 7034").payload().is_err());
+    }
+
+    #[test]
+    fn max_version() {
+        let payload = form("", &"x".repeat(140)).payload().unwrap();
+        let options = Options {
+            version: Some(MAX_VERSION + 1),
+            max_version: payload.max_version,
+            ..Options::default()
+        };
+        assert!(matches!(
+            encode::encode(&payload.text, payload.ec_level, &options),
+            Err(EncodeError::VersionTooLarge {
+                pinned: 14,
+                max: 13
+            })
+        ));
+
+        let text = "x".repeat(MAX_LEN);
+        let options = Options {
+            max_version: Some(MAX_VERSION),
+            ..Options::default()
+        };
+        let encoded = encode::encode(&text, EcLevel::M, &options).unwrap();
+        assert_eq!(encoded.symbol.version(), Version::Normal(MAX_VERSION));
+    }
+}
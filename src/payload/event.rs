@@ -0,0 +1,657 @@
+//! Calendar events as iCalendar VEVENT (RFC 5545), which calendar apps offer to add.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tz::datetime::{FoundDateTimeKind, FoundDateTimeList};
+use tz::{DateTime, TimeZone, UtcDateTime};
+
+use super::provider::{self, Field, Import, Input};
+use super::{content_lines, escape_text, fold, unescape_text, ContentLine, Payload};
+
+const PRODID: &str = "-//k6i.de//QR-Code-Generierer//DE";
+
+/// The current rules of common time zones, which need no time zone database on the system.
+pub const TIME_ZONES: &[(&str, &str)] = &[
+    ("Europe/Berlin", "CET-1CEST,M3.5.0,M10.5.0/3"),
+    ("Europe/Vienna", "CET-1CEST,M3.5.0,M10.5.0/3"),
+    ("Europe/Zurich", "CET-1CEST,M3.5.0,M10.5.0/3"),
+    ("Europe/London", "GMT0BST,M3.5.0/1,M10.5.0"),
+    ("America/New_York", "EST5EDT,M3.2.0,M11.1.0"),
+];
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+#[derive(Debug, thiserror::Error)]
+pub enum EventError {
+    #[error("Bitte einen Titel angeben.")]
+    MissingTitle,
+    #[error("Bitte den Beginn angeben.")]
+    MissingStart,
+    #[error("„{0}“ ist kein gültiger Zeitpunkt.")]
+    InvalidDateTime(String),
+    #[error(
+        "Die Zeitzone „{0}“ ist unbekannt. Bitte einen Namen wie „Europe/Berlin“ oder eine \
+         POSIX-Zeitzone wie „CET-1CEST,M3.5.0,M10.5.0/3“ angeben."
+    )]
+    UnknownTimeZone(String),
+    #[error("Den Zeitpunkt „{0}“ gibt es wegen der Zeitumstellung in dieser Zeitzone nicht.")]
+    SkippedTime(String),
+    #[error("Das Ende muss nach dem Beginn liegen.")]
+    EndBeforeStart,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Form {
+    pub title: String,
+    /// As sent by `<input type="datetime-local">`, e.g. `2023-09-01T09:00`.
+    pub start: String,
+    /// Optional, for all-day events the last day of the event.
+    pub end: String,
+    /// Only the dates of `start` and `end` are used.
+    pub all_day: bool,
+    /// An IANA name or a POSIX TZ string; without a time zone, the times are floating.
+    pub timezone: String,
+    pub location: String,
+    pub description: String,
+}
+
+pub const SCHEMA: &[Field] = &[
+    Field {
+        name: "title",
+        label: "Titel",
+        input: Input::TEXT,
+    },
+    Field {
+        name: "start",
+        label: "Beginn",
+        input: Input::Text {
+            kind: "datetime-local",
+            inputmode: None,
+            placeholder: None,
+        },
+    },
+    Field {
+        name: "end",
+        label: "Ende",
+        input: Input::Text {
+            kind: "datetime-local",
+            inputmode: None,
+            placeholder: None,
+        },
+    },
+    Field {
+        name: "all_day",
+        label: "Ganztägig",
+        input: Input::Checkbox("Nur die Tage von Beginn und Ende verwenden"),
+    },
+    Field {
+        name: "timezone",
+        label: "Zeitzone",
+        input: Input::Suggest {
+            placeholder: Some("ortsunabhängig"),
+            options: time_zone_names,
+        },
+    },
+    Field {
+        name: "location",
+        label: "Ort",
+        input: Input::TEXT,
+    },
+    Field {
+        name: "description",
+        label: "Beschreibung",
+        input: Input::TextArea,
+    },
+    Field {
+        name: "import",
+        label: "Importieren",
+        input: Input::Import {
+            button: "Termin aus .ics-Datei laden …",
+            batch: None,
+        },
+    },
+];
+
+pub const IMPORT: Import = Import {
+    title: "Termin importieren",
+    filter: Some((&["*.ics"], "iCalendar-Dateien")),
+    read: |_, bytes| {
+        let events = import(&String::from_utf8_lossy(bytes));
+        provider::items(events, "Die Datei enthält keine Termine.")
+    },
+};
+
+/// The time zones to suggest, those with embedded rules and UTC.
+fn time_zone_names() -> Vec<&'static str> {
+    let names = TIME_ZONES.iter().map(|&(name, _)| name);
+    names.chain(["UTC"]).collect()
+}
+
+impl Form {
+    pub fn payload(&self) -> Result<Payload, EventError> {
+        let title = self.title.trim();
+        if title.is_empty() {
+            return Err(EventError::MissingTitle);
+        }
+        let start = match self.start.trim() {
+            "" => return Err(EventError::MissingStart),
+            start => Local::parse(start)?,
+        };
+        let end = match self.end.trim() {
+            "" => None,
+            end => Some(Local::parse(end)?),
+        };
+
+        // the time stamp is taken from the start, so that the same form gives the same code
+        let (start, end, dtstamp) = match self.all_day {
+            true => {
+                let end = end.unwrap_or(start);
+                if end.date() < start.date() {
+                    return Err(EventError::EndBeforeStart);
+                }
+                // the end of an all-day event is exclusive
+                let end = end.add_days(1)?;
+                (
+                    format!("DTSTART;VALUE=DATE:{}", start.date()),
+                    Some(format!("DTEND;VALUE=DATE:{}", end.date())),
+                    floating_stamp(&Local {
+                        hour: 0,
+                        minute: 0,
+                        second: 0,
+                        ..start
+                    })?,
+                )
+            },
+            false => {
+                let time_zone = match self.timezone.trim() {
+                    "" => None,
+                    name => Some(time_zone(name)?),
+                };
+                // floating and UTC times of the same kind compare like their strings
+                let stamp = |local: Local| match &time_zone {
+                    Some(time_zone) => Ok(utc_stamp(&local.utc(time_zone)?)),
+                    None => Ok(local.stamp()),
+                };
+                let dtstamp = match &time_zone {
+                    Some(_) => stamp(start)?,
+                    None => floating_stamp(&start)?,
+                };
+                let start = stamp(start)?;
+                let end = end.map(stamp).transpose()?;
+                if end.as_ref().is_some_and(|end| *end <= start) {
+                    return Err(EventError::EndBeforeStart);
+                }
+                (
+                    format!("DTSTART:{start}"),
+                    end.map(|end| format!("DTEND:{end}")),
+                    dtstamp,
+                )
+            },
+        };
+
+        // the same event gets the same UID, so scanning it twice does not add a duplicate
+        let digest = Sha256::digest(format!("{title}\n{start}").as_bytes());
+        let uid: String = digest[..8].iter().map(|b| format!("{b:02x}")).collect();
+
+        let mut lines = vec![
+            "BEGIN:VCALENDAR".to_owned(),
+            "VERSION:2.0".to_owned(),
+            format!("PRODID:{PRODID}"),
+            "BEGIN:VEVENT".to_owned(),
+            format!("UID:{uid}@qr-code.k6i.de"),
+            format!("DTSTAMP:{dtstamp}"),
+            start,
+        ];
+        lines.extend(end);
+        lines.push(format!("SUMMARY:{}", escape_text(title)));
+        if !self.location.trim().is_empty() {
+            lines.push(format!("LOCATION:{}", escape_text(&self.location)));
+        }
+        if !self.description.trim().is_empty() {
+            lines.push(format!("DESCRIPTION:{}", escape_text(&self.description)));
+        }
+        lines.push("END:VEVENT".to_owned());
+        lines.push("END:VCALENDAR".to_owned());
+
+        let mut text = String::new();
+        for line in &lines {
+            fold(&mut text, line);
+            text.push_str("\r\n");
+        }
+        Ok(Payload::text(text))
+    }
+}
+
+/// Reads the events of an iCalendar file, skipping those with unreadable times.
+pub fn import(text: &str) -> Vec<Form> {
+    let mut events = Vec::new();
+    let mut components = Vec::new();
+    let mut event = Form::default();
+    let (mut start, mut end) = (None, None);
+    for line in content_lines(text) {
+        let in_event = components.last().is_some_and(|c| c == "VEVENT");
+        match line.name.as_str() {
+            "BEGIN" => {
+                let component = line.value.trim().to_ascii_uppercase();
+                if component == "VEVENT" {
+                    event = Form::default();
+                    (start, end) = (None, None);
+                }
+                components.push(component);
+            },
+            "END" => {
+                if in_event {
+                    if let Some(start) = &start {
+                        if import_times(&mut event, start, end.as_ref()).is_some() {
+                            events.push(event.clone());
+                        }
+                    }
+                }
+                components.pop();
+            },
+            _ if !in_event => {},
+            "SUMMARY" => event.title = unescape_text(&line.value),
+            "LOCATION" => event.location = unescape_text(&line.value),
+            "DESCRIPTION" => event.description = unescape_text(&line.value),
+            "DTSTART" => start = Some(line),
+            "DTEND" => end = Some(line),
+            _ => {},
+        }
+    }
+    events
+}
+
+fn import_times(event: &mut Form, start: &ContentLine, end: Option<&ContentLine>) -> Option<()> {
+    let (start_local, zone) = ical_time(start)?;
+    event.all_day = start.param("VALUE") == Some("DATE") || start.value.trim().len() == 8;
+    event.start = start_local.input();
+    event.timezone = zone.clone();
+
+    if let Some(end) = end {
+        let (mut end_local, end_zone) = ical_time(end)?;
+        if event.all_day {
+            end_local = end_local.add_days(-1).ok()?.max(start_local);
+        } else if end_zone != zone {
+            if let (Ok(from), Ok(to)) = (time_zone(&end_zone), time_zone(&zone)) {
+                let end = end_local.utc(&from).ok()?.project(to.as_ref()).ok()?;
+                end_local = Local::from_date_time(&end);
+            }
+        }
+        event.end = end_local.input();
+    }
+    Some(())
+}
+
+/// Reads a `DATE` or `DATE-TIME` value, and the name of its time zone.
+fn ical_time(line: &ContentLine) -> Option<(Local, String)> {
+    let value = line.value.trim();
+    let (value, zone) = match value.strip_suffix(['Z', 'z']) {
+        Some(value) => (value, "UTC".to_owned()),
+        None => (value, line.param("TZID").unwrap_or_default().to_owned()),
+    };
+    let (date, time) = value.split_once(['T', 't']).unwrap_or((value, "000000"));
+    if date.len() != 8
+        || time.len() != 6
+        || !date.chars().chain(time.chars()).all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+    let local = Local {
+        year: date[..4].parse().ok()?,
+        month: date[4..6].parse().ok()?,
+        day: date[6..].parse().ok()?,
+        hour: time[..2].parse().ok()?,
+        minute: time[2..4].parse().ok()?,
+        second: time[4..].parse().ok()?,
+    };
+    local.validate().ok()?;
+    Some((local, zone))
+}
+
+fn time_zone(name: &str) -> Result<TimeZone, EventError> {
+    if name.eq_ignore_ascii_case("UTC") {
+        return Ok(TimeZone::utc());
+    }
+    let name = TIME_ZONES
+        .iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(name))
+        .map_or(name, |(_, rule)| rule);
+    TimeZone::from_posix_tz(name).map_err(|_| EventError::UnknownTimeZone(name.to_owned()))
+}
+
+/// The UTC time stamp of a floating time, which is taken to be local to this computer.
+fn floating_stamp(local: &Local) -> Result<String, EventError> {
+    let time_zone = TimeZone::local().unwrap_or_else(|_| TimeZone::utc());
+    // a time skipped on this computer still needs a time stamp
+    let utc = local
+        .utc(&time_zone)
+        .or_else(|_| local.utc(&TimeZone::utc()))?;
+    Ok(utc_stamp(&utc))
+}
+
+fn utc_stamp(utc: &UtcDateTime) -> String {
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        utc.year(),
+        utc.month(),
+        utc.month_day(),
+        utc.hour(),
+        utc.minute(),
+        utc.second(),
+    )
+}
+
+/// A date and time without a time zone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Local {
+    year: i32,
+    month: u8,
+    day: u8,
+    hour: u8,
+    minute: u8,
+    second: u8,
+}
+
+impl Local {
+    /// Parses `2023-09-01T09:00`, with optional seconds, or only the date.
+    fn parse(value: &str) -> Result<Self, EventError> {
+        let invalid = || EventError::InvalidDateTime(value.to_owned());
+        let (date, time) = value.split_once(['T', ' ']).unwrap_or((value, "00:00"));
+        let mut date = date.splitn(3, '-').map(str::parse::<u16>);
+        let mut time = time.splitn(3, ':').map(str::parse::<u8>);
+        let (Some(Ok(year)), Some(Ok(month)), Some(Ok(day))) =
+            (date.next(), date.next(), date.next())
+        else {
+            return Err(invalid());
+        };
+        let (Some(Ok(hour)), Some(Ok(minute))) = (time.next(), time.next()) else {
+            return Err(invalid());
+        };
+        let Ok(second) = time.next().unwrap_or(Ok(0)) else { return Err(invalid()) };
+        let local = Local {
+            year: year.into(),
+            month: month.try_into().map_err(|_| invalid())?,
+            day: day.try_into().map_err(|_| invalid())?,
+            hour,
+            minute,
+            second,
+        };
+        local.validate().map_err(|_| invalid())?;
+        Ok(local)
+    }
+
+    fn validate(&self) -> Result<UtcDateTime, ()> {
+        if !(0..=9999).contains(&self.year) {
+            return Err(());
+        }
+        let Local {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+        } = *self;
+        UtcDateTime::new(year, month, day, hour, minute, second, 0).map_err(|_| ())
+    }
+
+    fn from_date_time(date_time: &DateTime) -> Self {
+        Local {
+            year: date_time.year(),
+            month: date_time.month(),
+            day: date_time.month_day(),
+            hour: date_time.hour(),
+            minute: date_time.minute(),
+            second: date_time.second(),
+        }
+    }
+
+    /// The instant of this local time in `time_zone`, the earlier one if the clocks were set back.
+    fn utc(&self, time_zone: &TimeZone) -> Result<UtcDateTime, EventError> {
+        let Local {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+        } = *self;
+        let found = DateTime::find(
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+            0,
+            time_zone.as_ref(),
+        );
+        let found = found.map(FoundDateTimeList::into_inner).unwrap_or_default();
+        let date_time = found
+            .into_iter()
+            .find_map(|found| match found {
+                FoundDateTimeKind::Normal(date_time) => Some(date_time),
+                FoundDateTimeKind::Skipped { .. } => None,
+            })
+            .ok_or_else(|| EventError::SkippedTime(self.input()))?;
+        UtcDateTime::from_timespec(date_time.unix_time(), 0)
+            .map_err(|_| EventError::InvalidDateTime(self.input()))
+    }
+
+    fn add_days(&self, days: i64) -> Result<Self, EventError> {
+        let invalid = || EventError::InvalidDateTime(self.input());
+        let utc = self.validate().map_err(|_| invalid())?;
+        let utc = UtcDateTime::from_timespec(utc.unix_time() + days * SECONDS_PER_DAY, 0)
+            .map_err(|_| invalid())?;
+        let local = Local {
+            year: utc.year(),
+            month: utc.month(),
+            day: utc.month_day(),
+            ..*self
+        };
+        local.validate().map_err(|_| invalid())?;
+        Ok(local)
+    }
+
+    fn date(&self) -> String {
+        format!("{:04}{:02}{:02}", self.year, self.month, self.day)
+    }
+
+    fn stamp(&self) -> String {
+        let Local {
+            hour,
+            minute,
+            second,
+            ..
+        } = *self;
+        format!("{}T{hour:02}{minute:02}{second:02}", self.date())
+    }
+
+    /// The value for `<input type="datetime-local">`.
+    fn input(&self) -> String {
+        let Local {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+        } = *self;
+        let mut input = format!("{year:04}-{month:02}-{day:02}T{hour:02}:{minute:02}");
+        if second != 0 {
+            input.push_str(&format!(":{second:02}"));
+        }
+        input
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payload::LINE_LEN;
+
+    fn form() -> Form {
+        Form {
+            title: "Tag der offenen Tür; Fachbereich, Vetmed".to_owned(),
+            start: "2023-10-29T01:30".to_owned(),
+            end: "2023-10-29T04:00".to_owned(),
+            all_day: false,
+            timezone: "Europe/Berlin".to_owned(),
+            location: "Oertzenweg 19b, 14163 Berlin".to_owned(),
+            description: "Führungen durch die Kliniken\nund Vorträge".to_owned(),
+        }
+    }
+
+    fn property<'a>(lines: &'a [ContentLine], name: &str) -> &'a str {
+        let line = lines.iter().find(|line| line.name == name).unwrap();
+        &line.value
+    }
+
+    #[test]
+    fn converts_to_utc() {
+        let text = form().payload().unwrap().text;
+        assert!(text.ends_with("END:VEVENT\r\nEND:VCALENDAR\r\n"));
+        assert!(text.split("\r\n").all(|line| line.len() <= LINE_LEN));
+        let lines = content_lines(&text);
+        // the clocks are set back at 03:00 CEST
+        assert_eq!(property(&lines, "DTSTART"), "20231028T233000Z");
+        assert_eq!(property(&lines, "DTEND"), "20231029T030000Z");
+        assert_eq!(unescape_text(property(&lines, "SUMMARY")), form().title);
+        assert_eq!(
+            unescape_text(property(&lines, "DESCRIPTION")),
+            form().description
+        );
+    }
+
+    #[test]
+    fn stable_payload() {
+        let text = form().payload().unwrap().text;
+        assert_eq!(form().payload().unwrap().text, text);
+        let lines = content_lines(&text);
+        assert_eq!(property(&lines, "DTSTAMP"), "20231028T233000Z");
+        // the first 8 bytes of the SHA-256 digest of the title and start
+        assert_eq!(property(&lines, "UID"), "f455a106e13e16f3@qr-code.k6i.de");
+
+        let other = Form {
+            title: "Lange Nacht der Wissenschaften".to_owned(),
+            ..form()
+        };
+        let other = other.payload().unwrap().text;
+        assert_ne!(
+            property(&content_lines(&other), "UID"),
+            property(&lines, "UID")
+        );
+    }
+
+    #[test]
+    fn embedded_time_zones() {
+        let berlin = time_zone("europe/berlin").unwrap();
+        let summer = Local::parse("2023-07-01T12:00").unwrap();
+        assert_eq!(utc_stamp(&summer.utc(&berlin).unwrap()), "20230701T100000Z");
+        let winter = Local::parse("2023-12-01T12:00").unwrap();
+        assert_eq!(utc_stamp(&winter.utc(&berlin).unwrap()), "20231201T110000Z");
+        let new_york = time_zone("America/New_York").unwrap();
+        assert_eq!(
+            utc_stamp(&summer.utc(&new_york).unwrap()),
+            "20230701T160000Z"
+        );
+
+        // other names are looked up in the time zone database of the system
+        assert!(matches!(
+            time_zone("Mars/Olympus_Mons"),
+            Err(EventError::UnknownTimeZone(name)) if name == "Mars/Olympus_Mons"
+        ));
+    }
+
+    #[test]
+    fn floating_and_all_day() {
+        let form = Form {
+            timezone: String::new(),
+            ..form()
+        };
+        let lines = content_lines(&form.payload().unwrap().text);
+        assert_eq!(property(&lines, "DTSTART"), "20231029T013000");
+        let start = Local::parse("2023-10-29T01:30").unwrap();
+        assert_eq!(property(&lines, "DTSTAMP"), floating_stamp(&start).unwrap());
+
+        let form = Form {
+            all_day: true,
+            end: "2023-10-31T00:00".to_owned(),
+            ..form
+        };
+        let lines = content_lines(&form.payload().unwrap().text);
+        assert_eq!(property(&lines, "DTSTART"), "20231029");
+        assert_eq!(property(&lines, "DTEND"), "20231101");
+        let midnight = Local::parse("2023-10-29").unwrap();
+        assert_eq!(
+            property(&lines, "DTSTAMP"),
+            floating_stamp(&midnight).unwrap()
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_times() {
+        let invalid = |start: &str, end: &str, timezone: &str| {
+            let form = Form {
+                start: start.to_owned(),
+                end: end.to_owned(),
+                timezone: timezone.to_owned(),
+                ..form()
+            };
+            form.payload().is_err()
+        };
+        assert!(invalid("", "", ""));
+        assert!(invalid("2023-02-29T10:00", "", ""));
+        assert!(invalid("2023-10-01T10:00", "2023-10-01T09:00", ""));
+        assert!(invalid("2023-10-01T10:00", "", "Mars/Olympus_Mons"));
+        // the clocks are set forward at 02:00 CET
+        assert!(invalid("2023-03-26T02:30", "", "Europe/Berlin"));
+    }
+
+    #[test]
+    fn round_trip() {
+        let all_day = Form {
+            all_day: true,
+            start: "2023-10-29T00:00".to_owned(),
+            end: "2023-10-31T00:00".to_owned(),
+            timezone: String::new(),
+            ..form()
+        };
+        let text = [form(), all_day.clone()]
+            .map(|form| form.payload().unwrap().text)
+            .join("");
+        let events = import(&text);
+        assert_eq!(events[0], Form {
+            start: "2023-10-28T23:30".to_owned(),
+            end: "2023-10-29T03:00".to_owned(),
+            timezone: "UTC".to_owned(),
+            ..form()
+        });
+        assert_eq!(events[1], all_day);
+    }
+
+    #[test]
+    fn imports_events() {
+        let text = "BEGIN:VCALENDAR\r\n\
+                    BEGIN:VEVENT\r\n\
+                    SUMMARY:Abflug\r\n\
+                    DTSTART;TZID=Europe/Berlin:20230901T090000\r\n\
+                    DTEND;TZID=\"America/New_York\":20230901T113000\r\n\
+                    BEGIN:VALARM\r\n\
+                    DESCRIPTION:Erinnerung\r\n\
+                    END:VALARM\r\n\
+                    END:VEVENT\r\n\
+                    BEGIN:VEVENT\r\n\
+                    SUMMARY:Ohne Beginn\r\n\
+                    END:VEVENT\r\n\
+                    END:VCALENDAR\r\n";
+        assert_eq!(import(text), [Form {
+            title: "Abflug".to_owned(),
+            start: "2023-09-01T09:00".to_owned(),
+            end: "2023-09-01T17:30".to_owned(),
+            timezone: "Europe/Berlin".to_owned(),
+            ..Form::default()
+        }]);
+    }
+}
@@ -0,0 +1,365 @@
+//! Small binary files like configuration files or keys, as raw bytes or as Base45/Base64 text.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use qrcode::bits::Bits;
+use qrcode::types::Mode;
+use qrcode::{EcLevel, Version};
+use serde::{Deserialize, Serialize};
+
+use super::provider::{self, Field, Import, Input, Transfer};
+use super::Payload;
+use crate::{animation, base45};
+
+const EC_LEVELS: [EcLevel; 4] = [EcLevel::L, EcLevel::M, EcLevel::Q, EcLevel::H];
+
+/// The largest file that can be loaded, to be sent as an animated sequence of codes.
+pub const MAX_ANIMATED_LEN: usize = 256 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum FileError {
+    #[error("Bitte eine Datei auswählen.")]
+    MissingFile,
+    #[error("Die Datei ist leer.")]
+    EmptyFile,
+    #[error("Die Dateidaten sind beschädigt, bitte die Datei erneut laden.")]
+    InvalidData,
+    #[error(
+        "Die Datei ist {size} Bytes groß, {} passen bei Fehlerkorrektur {} aber höchstens {max} \
+         Bytes in einen QR-Code ({}). Bitte eine kleinere Datei, eine niedrigere Fehlerkorrektur \
+         oder eine kompaktere Kodierung wählen, oder die Datei animiert übertragen.",
+        encoding.description(),
+        ec_level_name(*ec_level),
+        capacities(*encoding)
+    )]
+    TooLarge {
+        size: usize,
+        max: usize,
+        encoding: Encoding,
+        ec_level: EcLevel,
+    },
+    #[error(
+        "Die Datei ist {0} Bytes groß, animiert können aber höchstens {max} Bytes übertragen \
+         werden.",
+        max = MAX_ANIMATED_LEN
+    )]
+    TooLargeForAnimation(usize),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Encoding {
+    /// The bytes as they are, the most compact, but not every scanner app can handle them.
+    #[default]
+    Byte,
+    /// Text in alphanumeric mode (RFC 9285), nearly as compact as raw bytes.
+    Base45,
+    /// Text that every scanner shows, a third larger than raw bytes.
+    Base64,
+}
+
+impl Encoding {
+    fn description(self) -> &'static str {
+        match self {
+            Self::Byte => "als Rohdaten",
+            Self::Base45 => "Base45-kodiert",
+            Self::Base64 => "Base64-kodiert",
+        }
+    }
+
+    /// Number of bits needed for `len` bytes of data in a single segment of the largest version.
+    fn encoded_len(self, len: usize) -> usize {
+        let (mode, chars) = match self {
+            Self::Byte => (Mode::Byte, len),
+            Self::Base45 => (Mode::Alphanumeric, len / 2 * 3 + len % 2 * 2),
+            Self::Base64 => (Mode::Byte, len.div_ceil(3) * 4),
+        };
+        let version = Version::Normal(40);
+        version.mode_bits_count() + mode.length_bits_count(version) + mode.data_bits_count(chars)
+    }
+
+    /// The largest file that fits into a QR code at `ec_level`.
+    pub fn max_len(self, ec_level: EcLevel) -> usize {
+        let capacity = Bits::new(Version::Normal(40))
+            .max_len(ec_level)
+            .unwrap_or_default();
+        let (mut low, mut high) = (0, capacity / 8);
+        while low < high {
+            let len = (low + high).div_ceil(2);
+            match self.encoded_len(len) <= capacity {
+                true => low = len,
+                false => high = len - 1,
+            }
+        }
+        low
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    L,
+    #[default]
+    M,
+    Q,
+    H,
+}
+
+impl From<Level> for EcLevel {
+    fn from(level: Level) -> Self {
+        match level {
+            Level::L => Self::L,
+            Level::M => Self::M,
+            Level::Q => Self::Q,
+            Level::H => Self::H,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Form {
+    /// The file name, only shown to the user.
+    pub name: String,
+    /// The content in Base64, so that it can travel in the query string.
+    pub data: String,
+    #[serde(skip_serializing)]
+    pub encoding: Encoding,
+    #[serde(skip_serializing)]
+    pub ec_level: Level,
+    /// The version of each code of an animated transfer.
+    #[serde(skip_serializing)]
+    pub animation_version: Option<i16>,
+    /// Codes per second of an animated transfer.
+    #[serde(skip_serializing)]
+    pub fps: Option<u32>,
+}
+
+pub const SCHEMA: &[Field] = &[
+    Field {
+        name: "import",
+        label: "Datei",
+        input: Input::Import {
+            button: "Datei laden …",
+            batch: None,
+        },
+    },
+    Field {
+        name: "name",
+        label: "Dateiname",
+        input: Input::ReadOnly,
+    },
+    Field {
+        name: "data",
+        label: "Inhalt",
+        input: Input::Hidden,
+    },
+    Field {
+        name: "encoding",
+        label: "Kodierung",
+        input: Input::Select {
+            options: &[
+                ("byte", "Rohdaten (am kompaktesten)"),
+                ("base45", "Base45 (Text, fast so kompakt)"),
+                ("base64", "Base64 (Text, überall lesbar)"),
+            ],
+            default: "byte",
+        },
+    },
+    Field {
+        name: "ec_level",
+        label: "Fehlerkorrektur",
+        input: Input::Select {
+            options: &[
+                ("l", "L (7 %)"),
+                ("m", "M (15 %)"),
+                ("q", "Q (25 %)"),
+                ("h", "H (30 %)"),
+            ],
+            default: "m",
+        },
+    },
+    Field {
+        name: "animation_version",
+        label: "Version der animierten Codes",
+        input: Input::Number {
+            value: animation::VERSION as u32,
+            min: 1,
+            max: 40,
+        },
+    },
+    Field {
+        name: "fps",
+        label: "Codes pro Sekunde",
+        input: Input::Number {
+            value: animation::FPS,
+            min: 1,
+            max: 30,
+        },
+    },
+    Field {
+        name: "animation",
+        label: "Animiert",
+        input: Input::Animation,
+    },
+    Field {
+        name: "capacities",
+        label: "Höchstgröße",
+        input: Input::Hint(capacity_hint),
+    },
+];
+
+pub const IMPORT: Import = Import {
+    title: "Datei laden",
+    filter: None,
+    read: |name, bytes| match import(name, bytes) {
+        Ok(form) => provider::items(vec![form], ""),
+        Err(err) => Err(err.to_string()),
+    },
+};
+
+impl Form {
+    /// The content of the file.
+    pub fn bytes(&self) -> Result<Vec<u8>, FileError> {
+        if self.data.is_empty() {
+            return Err(FileError::MissingFile);
+        }
+        let data = STANDARD
+            .decode(&self.data)
+            .map_err(|_| FileError::InvalidData)?;
+        match data.is_empty() {
+            true => Err(FileError::EmptyFile),
+            false => Ok(data),
+        }
+    }
+
+    pub fn transfer(&self) -> Result<Transfer, FileError> {
+        Ok(Transfer {
+            bytes: self.bytes()?,
+            version: self.animation_version.unwrap_or(animation::VERSION),
+            ec_level: self.ec_level.into(),
+            fps: self.fps.unwrap_or(animation::FPS),
+        })
+    }
+
+    pub fn payload(&self) -> Result<Payload, FileError> {
+        let data = self.bytes()?;
+        let ec_level = self.ec_level.into();
+        check_size(data.len(), self.encoding, ec_level)?;
+        let payload = match self.encoding {
+            Encoding::Byte => Payload {
+                bytes: Some(data),
+                ..Payload::text(self.name.trim())
+            },
+            Encoding::Base45 => Payload::text(base45::encode(&data)),
+            Encoding::Base64 => Payload::text(STANDARD.encode(&data)),
+        };
+        Ok(Payload {
+            ec_level,
+            ..payload
+        })
+    }
+}
+
+/// Reads a file for the form, refusing files that would not even fit into an animated sequence
+/// of codes.
+pub fn import(name: &str, data: &[u8]) -> Result<Form, FileError> {
+    if data.is_empty() {
+        return Err(FileError::EmptyFile);
+    }
+    if data.len() > MAX_ANIMATED_LEN {
+        return Err(FileError::TooLargeForAnimation(data.len()));
+    }
+    Ok(Form {
+        name: name.to_owned(),
+        data: STANDARD.encode(data),
+        ..Form::default()
+    })
+}
+
+fn check_size(size: usize, encoding: Encoding, ec_level: EcLevel) -> Result<(), FileError> {
+    let max = encoding.max_len(ec_level);
+    match size <= max {
+        true => Ok(()),
+        false => Err(FileError::TooLarge {
+            size,
+            max,
+            encoding,
+            ec_level,
+        }),
+    }
+}
+
+/// The capacities at all error correction levels, like `L: 2953, M: 2331, …`.
+fn capacities(encoding: Encoding) -> String {
+    let capacities: Vec<_> = EC_LEVELS
+        .iter()
+        .map(|&level| format!("{}: {}", ec_level_name(level), encoding.max_len(level)))
+        .collect();
+    capacities.join(", ")
+}
+
+/// The capacities of every encoding at the error correction levels L / M / Q / H.
+fn capacity_hint() -> Vec<String> {
+    let encodings = [
+        ("Rohdaten", Encoding::Byte),
+        ("Base45", Encoding::Base45),
+        ("Base64", Encoding::Base64),
+    ];
+    let mut lines = vec!["Höchstgröße in Bytes bei Fehlerkorrektur L / M / Q / H:".to_owned()];
+    for (name, encoding) in encodings {
+        let capacities = EC_LEVELS.map(|level| encoding.max_len(level).to_string());
+        lines.push(format!("{name}: {}", capacities.join(" / ")));
+    }
+    lines
+}
+
+fn ec_level_name(ec_level: EcLevel) -> &'static str {
+    match ec_level {
+        EcLevel::L => "L",
+        EcLevel::M => "M",
+        EcLevel::Q => "Q",
+        EcLevel::H => "H",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capacity_per_ec_level() {
+        // ISO/IEC 18004, table 7, version 40
+        let bytes = EC_LEVELS.map(|level| Encoding::Byte.max_len(level));
+        assert_eq!(bytes, [2953, 2331, 1663, 1273]);
+        assert_eq!(Encoding::Base45.max_len(EcLevel::L), 2864);
+        assert_eq!(Encoding::Base64.max_len(EcLevel::L), 2214);
+    }
+
+    #[test]
+    fn refuses_large_files() {
+        let form = |len: usize, encoding: Encoding| Form {
+            name: "key.bin".to_owned(),
+            data: STANDARD.encode(vec![0xa5; len]),
+            encoding,
+            ec_level: Level::M,
+            ..Form::default()
+        };
+        let payload = form(2331, Encoding::Byte).payload().unwrap();
+        assert_eq!(payload.bytes.unwrap().len(), 2331);
+        assert_eq!(payload.ec_level, EcLevel::M);
+        assert!(matches!(
+            form(2332, Encoding::Byte).payload(),
+            Err(FileError::TooLarge { max: 2331, .. }),
+        ));
+        let text = form(3, Encoding::Base64).payload().unwrap().text;
+        assert_eq!(text, "paWl");
+        assert!(import("leer", b"").is_err());
+        assert!(import("animiert", &[0; 3000]).is_ok());
+        assert!(matches!(
+            import("gross", &[0; MAX_ANIMATED_LEN + 1]),
+            Err(FileError::TooLargeForAnimation(_)),
+        ));
+        assert_eq!(import("klein", b"ietf!").unwrap().data, "aWV0ZiE=");
+    }
+}
@@ -0,0 +1,130 @@
+//! Locations as `geo:` URIs (RFC 5870).
+
+use serde::Deserialize;
+
+use super::provider::{Field, Input};
+use super::Payload;
+
+#[derive(Debug, thiserror::Error)]
+pub enum GeoError {
+    #[error("Bitte Breiten- und Längengrad angeben.")]
+    Missing,
+    #[error("{field} „{value}“ ist keine Zahl.")]
+    NotANumber { field: &'static str, value: String },
+    #[error("{field} muss zwischen -{max} und {max} liegen.")]
+    OutOfRange { field: &'static str, max: u16 },
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Form {
+    /// Degrees, with a decimal point or comma.
+    pub latitude: String,
+    pub longitude: String,
+    /// Meters, optional.
+    pub altitude: String,
+}
+
+pub const SCHEMA: &[Field] = &[
+    Field {
+        name: "latitude",
+        label: "Breitengrad",
+        input: Input::Text {
+            kind: "text",
+            inputmode: Some("decimal"),
+            placeholder: Some("52.4531"),
+        },
+    },
+    Field {
+        name: "longitude",
+        label: "Längengrad",
+        input: Input::Text {
+            kind: "text",
+            inputmode: Some("decimal"),
+            placeholder: Some("13.2889"),
+        },
+    },
+    Field {
+        name: "altitude",
+        label: "Höhe in Metern (optional)",
+        input: Input::Text {
+            kind: "text",
+            inputmode: Some("decimal"),
+            placeholder: None,
+        },
+    },
+];
+
+impl Form {
+    pub fn payload(&self) -> Result<Payload, GeoError> {
+        if self.latitude.trim().is_empty() || self.longitude.trim().is_empty() {
+            return Err(GeoError::Missing);
+        }
+        let latitude = parse("Der Breitengrad", &self.latitude, Some(90))?;
+        let longitude = parse("Der Längengrad", &self.longitude, Some(180))?;
+        let mut uri = format!("geo:{latitude},{longitude}");
+        if !self.altitude.trim().is_empty() {
+            let altitude = parse("Die Höhe", &self.altitude, None)?;
+            uri.push_str(&format!(",{altitude}"));
+        }
+        Ok(Payload::text(uri))
+    }
+}
+
+fn parse(field: &'static str, value: &str, max: Option<u16>) -> Result<f64, GeoError> {
+    let value = value.trim();
+    let number = value
+        .replace(',', ".")
+        .parse::<f64>()
+        .ok()
+        .filter(|number| number.is_finite())
+        .ok_or_else(|| GeoError::NotANumber {
+            field,
+            value: value.to_owned(),
+        })?;
+    match max {
+        Some(max) if number.abs() > f64::from(max) => Err(GeoError::OutOfRange { field, max }),
+        // avoid "-0" in the URI
+        _ => Ok(number + 0.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn form(latitude: &str, longitude: &str, altitude: &str) -> Form {
+        Form {
+            latitude: latitude.to_owned(),
+            longitude: longitude.to_owned(),
+            altitude: altitude.to_owned(),
+        }
+    }
+
+    fn round_trip(form: &Form) -> Vec<f64> {
+        let text = form.payload().unwrap().text;
+        let coordinates = text.strip_prefix("geo:").unwrap();
+        coordinates.split(',').map(|c| c.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn coordinates() {
+        assert_eq!(round_trip(&form("52.4531", "13.2889", "")), [
+            52.4531, 13.2889
+        ]);
+        assert_eq!(round_trip(&form("52,4531", " 13,2889 ", "48")), [
+            52.4531, 13.2889, 48.0
+        ]);
+        assert_eq!(round_trip(&form("-33.8568", "151.2153", "-2.5")), [
+            -33.8568, 151.2153, -2.5
+        ]);
+    }
+
+    #[test]
+    fn rejects_invalid_coordinates() {
+        assert!(form("91", "0", "").payload().is_err());
+        assert!(form("0", "-180.5", "").payload().is_err());
+        assert!(form("abc", "0", "").payload().is_err());
+        assert!(form("1", "", "").payload().is_err());
+    }
+}
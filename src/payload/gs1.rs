@@ -0,0 +1,568 @@
+//! GS1 element strings for GS1 QR, GS1 Data Matrix and GS1 Digital Link.
+
+use serde::Deserialize;
+
+use super::provider::{Field, Input};
+use super::{percent_encode, Payload, Symbology};
+use crate::datamatrix::GS;
+
+/// The default resolver of GS1 Digital Link URLs.
+const RESOLVER: &str = "https://id.gs1.org";
+
+#[derive(Debug, Clone, Copy)]
+enum Format {
+    /// Exactly `n` digits, the last of which is a check digit if `check` is set.
+    Digits { n: usize, check: bool },
+    /// Up to `n` digits.
+    VarDigits(usize),
+    /// A date `YYMMDD`, the day may be `00` to refer to the end of the month if `end` is set.
+    Date { end: bool },
+    /// Up to `n` characters of GS1's "CSET 82".
+    Chars(usize),
+}
+
+#[derive(Debug)]
+struct Ai {
+    ai: &'static str,
+    title: &'static str,
+    format: Format,
+}
+
+#[rustfmt::skip]
+const AIS: &[Ai] = &[
+    Ai { ai: "00", title: "SSCC", format: Format::Digits { n: 18, check: true } },
+    Ai { ai: "01", title: "GTIN", format: Format::Digits { n: 14, check: true } },
+    Ai { ai: "02", title: "GTIN der enthaltenen Einheiten", format: Format::Digits { n: 14, check: true } },
+    Ai { ai: "10", title: "Charge", format: Format::Chars(20) },
+    Ai { ai: "11", title: "Herstellungsdatum", format: Format::Date { end: false } },
+    Ai { ai: "12", title: "Fälligkeitsdatum", format: Format::Date { end: false } },
+    Ai { ai: "13", title: "Verpackungsdatum", format: Format::Date { end: false } },
+    Ai { ai: "15", title: "Mindesthaltbarkeitsdatum", format: Format::Date { end: true } },
+    Ai { ai: "16", title: "Verkaufsdatum", format: Format::Date { end: true } },
+    Ai { ai: "17", title: "Verfallsdatum", format: Format::Date { end: true } },
+    Ai { ai: "20", title: "Produktvariante", format: Format::Digits { n: 2, check: false } },
+    Ai { ai: "21", title: "Seriennummer", format: Format::Chars(20) },
+    Ai { ai: "22", title: "Verbrauchsprodukt-Variante", format: Format::Chars(20) },
+    Ai { ai: "30", title: "Menge", format: Format::VarDigits(8) },
+    Ai { ai: "37", title: "Anzahl der Handelseinheiten", format: Format::VarDigits(8) },
+    Ai { ai: "3100", title: "Nettogewicht in kg", format: Format::Digits { n: 6, check: false } },
+    Ai { ai: "3101", title: "Nettogewicht in kg", format: Format::Digits { n: 6, check: false } },
+    Ai { ai: "3102", title: "Nettogewicht in kg", format: Format::Digits { n: 6, check: false } },
+    Ai { ai: "3103", title: "Nettogewicht in kg", format: Format::Digits { n: 6, check: false } },
+    Ai { ai: "400", title: "Bestellnummer", format: Format::Chars(30) },
+    Ai { ai: "410", title: "Empfänger-GLN", format: Format::Digits { n: 13, check: true } },
+    Ai { ai: "414", title: "GLN", format: Format::Digits { n: 13, check: true } },
+    Ai { ai: "710", title: "PZN", format: Format::Chars(20) },
+    Ai { ai: "8200", title: "Produkt-URL", format: Format::Chars(70) },
+    Ai { ai: "90", title: "Interne Angabe", format: Format::Chars(30) },
+    Ai { ai: "91", title: "Interne Angabe", format: Format::Chars(90) },
+    Ai { ai: "92", title: "Interne Angabe", format: Format::Chars(90) },
+    Ai { ai: "93", title: "Interne Angabe", format: Format::Chars(90) },
+    Ai { ai: "94", title: "Interne Angabe", format: Format::Chars(90) },
+    Ai { ai: "95", title: "Interne Angabe", format: Format::Chars(90) },
+    Ai { ai: "96", title: "Interne Angabe", format: Format::Chars(90) },
+    Ai { ai: "97", title: "Interne Angabe", format: Format::Chars(90) },
+    Ai { ai: "98", title: "Interne Angabe", format: Format::Chars(90) },
+    Ai { ai: "99", title: "Interne Angabe", format: Format::Chars(90) },
+];
+
+/// Prefixes of AIs with a predefined length, which need no separator after their value.
+const PREDEFINED_LENGTH: &[&str] = &[
+    "00", "01", "02", "03", "04", "11", "12", "13", "14", "15", "16", "17", "18", "19", "20", "31",
+    "32", "33", "34", "35", "36", "41",
+];
+
+/// Primary keys of GS1 Digital Link URLs and their path qualifiers in order.
+const LINK_KEYS: &[(&str, &[&str])] = &[("01", &["22", "10", "21"]), ("00", &[]), ("414", &[])];
+
+#[derive(Debug, thiserror::Error)]
+pub enum Gs1Error {
+    #[error("Bitte mindestens einen GS1-Datenbezeichner angeben.")]
+    Empty,
+    #[error("Den GS1-Datenbezeichner ({0}) kennt dieses Programm nicht.")]
+    UnknownAi(String),
+    #[error("Der GS1-Datenbezeichner ({0}) ist mehrfach angegeben.")]
+    Duplicate(&'static str),
+    #[error("Weitere Datenbezeichner bitte in der Form „(400)12345(30)10“ angeben.")]
+    Syntax,
+    #[error("({ai}) {title}: {reason}")]
+    Invalid {
+        ai: &'static str,
+        title: &'static str,
+        reason: String,
+    },
+    #[error("Ein GS1 Digital Link benötigt eine GTIN (01), SSCC (00) oder GLN (414).")]
+    MissingKey,
+    #[error("Die Adresse des Resolvers muss mit „https://“ oder „http://“ beginnen.")]
+    InvalidResolver,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Output {
+    /// GS1 QR code, i.e. the element string in FNC1 mode.
+    #[default]
+    Qr,
+    /// GS1 Data Matrix.
+    DataMatrix,
+    /// A GS1 Digital Link URL in a regular QR code.
+    Link,
+}
+
+/// The fields of the GS1 editor.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Form {
+    pub output: Output,
+    pub gtin: String,
+    pub batch: String,
+    /// Dates as `YYYY-MM-DD`, like an HTML date input produces them.
+    pub expiry: String,
+    pub best_before: String,
+    pub production: String,
+    pub serial: String,
+    /// Further elements in the human readable form "(400)12345(30)10".
+    pub extra: String,
+    pub resolver: String,
+}
+
+pub const SCHEMA: &[Field] = &[
+    Field {
+        name: "output",
+        label: "Format",
+        input: Input::Select {
+            options: &[
+                ("qr", "GS1-QR-Code"),
+                ("datamatrix", "GS1-Data-Matrix-Code"),
+                ("link", "GS1 Digital Link (URL im QR-Code)"),
+            ],
+            default: "qr",
+        },
+    },
+    Field {
+        name: "gtin",
+        label: "GTIN (01)",
+        input: Input::Text {
+            kind: "text",
+            inputmode: Some("numeric"),
+            placeholder: Some("09506000134352"),
+        },
+    },
+    Field {
+        name: "batch",
+        label: "Charge (10)",
+        input: Input::TEXT,
+    },
+    Field {
+        name: "serial",
+        label: "Seriennummer (21)",
+        input: Input::TEXT,
+    },
+    Field {
+        name: "expiry",
+        label: "Verfallsdatum (17)",
+        input: DATE,
+    },
+    Field {
+        name: "best_before",
+        label: "Mindesthaltbarkeitsdatum (15)",
+        input: DATE,
+    },
+    Field {
+        name: "production",
+        label: "Herstellungsdatum (11)",
+        input: DATE,
+    },
+    Field {
+        name: "extra",
+        label: "Weitere Datenbezeichner",
+        input: Input::Text {
+            kind: "text",
+            inputmode: None,
+            placeholder: Some("(400)12345(30)10"),
+        },
+    },
+    Field {
+        name: "resolver",
+        label: "Resolver (Digital Link)",
+        input: Input::Text {
+            kind: "url",
+            inputmode: None,
+            placeholder: Some("https://id.gs1.org"),
+        },
+    },
+];
+
+const DATE: Input = Input::Text {
+    kind: "date",
+    inputmode: None,
+    placeholder: None,
+};
+
+#[derive(Debug, Clone)]
+struct Element {
+    ai: &'static Ai,
+    value: String,
+}
+
+impl Form {
+    pub fn payload(&self) -> Result<Payload, Gs1Error> {
+        let elements = self.elements()?;
+        let label = human_readable(&elements);
+        let payload = match self.output {
+            Output::Qr => Payload {
+                fnc1: true,
+                label: Some(label),
+                ..Payload::text(element_string(&elements))
+            },
+            Output::DataMatrix => Payload {
+                symbology: Symbology::DataMatrix,
+                fnc1: true,
+                label: Some(label),
+                ..Payload::text(element_string(&elements))
+            },
+            Output::Link => Payload {
+                label: Some(label),
+                ..Payload::text(digital_link(&elements, self.resolver()?)?)
+            },
+        };
+        Ok(payload)
+    }
+
+    /// Validates the entered data and returns the elements, fixed-length elements first.
+    fn elements(&self) -> Result<Vec<Element>, Gs1Error> {
+        let mut elements = Vec::new();
+        for (ai, value) in [
+            ("01", &self.gtin),
+            ("10", &self.batch),
+            ("21", &self.serial),
+        ] {
+            if !value.trim().is_empty() {
+                elements.push(Element::new(ai, value.trim())?);
+            }
+        }
+        for (ai, value) in [
+            ("17", &self.expiry),
+            ("15", &self.best_before),
+            ("11", &self.production),
+        ] {
+            if !value.trim().is_empty() {
+                elements.push(Element::new(ai, &html_date(value.trim()))?);
+            }
+        }
+        elements.extend(parse_elements(&self.extra)?);
+
+        if elements.is_empty() {
+            return Err(Gs1Error::Empty);
+        }
+        for (index, element) in elements.iter().enumerate() {
+            if elements[..index].iter().any(|e| e.ai.ai == element.ai.ai) {
+                return Err(Gs1Error::Duplicate(element.ai.ai));
+            }
+        }
+        elements.sort_by_key(|element| !element.has_predefined_length());
+        Ok(elements)
+    }
+
+    fn resolver(&self) -> Result<&str, Gs1Error> {
+        let resolver = match self.resolver.trim().trim_end_matches('/') {
+            "" => RESOLVER,
+            resolver => resolver,
+        };
+        match resolver.starts_with("https://") || resolver.starts_with("http://") {
+            true => Ok(resolver),
+            false => Err(Gs1Error::InvalidResolver),
+        }
+    }
+}
+
+impl Element {
+    fn new(ai: &str, value: &str) -> Result<Self, Gs1Error> {
+        let ai = AIS
+            .iter()
+            .find(|spec| spec.ai == ai)
+            .ok_or_else(|| Gs1Error::UnknownAi(ai.to_owned()))?;
+        let invalid = |reason: String| Gs1Error::Invalid {
+            ai: ai.ai,
+            title: ai.title,
+            reason,
+        };
+
+        match ai.format {
+            Format::Digits { n, check } => {
+                // GTINs may be entered in their shorter forms GTIN-8, -12 or -13
+                let value = match matches!(ai.ai, "01" | "02") && value.len() < n {
+                    true => format!("{value:0>n$}"),
+                    false => value.to_owned(),
+                };
+                if value.len() != n || !value.bytes().all(|b| b.is_ascii_digit()) {
+                    return Err(invalid(format!("Es werden genau {n} Ziffern erwartet.")));
+                }
+                if check {
+                    let expected = check_digit(&value[..n - 1]);
+                    if value.as_bytes()[n - 1] != expected {
+                        return Err(invalid(format!(
+                            "Die Prüfziffer ist falsch, erwartet wird {}.",
+                            expected as char,
+                        )));
+                    }
+                }
+                Ok(Self { ai, value })
+            },
+            Format::VarDigits(n) => {
+                match (1..=n).contains(&value.len()) && value.bytes().all(|b| b.is_ascii_digit()) {
+                    true => Ok(Self {
+                        ai,
+                        value: value.to_owned(),
+                    }),
+                    false => Err(invalid(format!("Es werden 1 bis {n} Ziffern erwartet."))),
+                }
+            },
+            Format::Date { end } => match is_valid_date(value, end) {
+                true => Ok(Self {
+                    ai,
+                    value: value.to_owned(),
+                }),
+                false => Err(invalid(format!(
+                    "„{value}“ ist kein gültiges Datum (JJMMTT)."
+                ))),
+            },
+            Format::Chars(n) => {
+                if value.is_empty() || value.len() > n {
+                    return Err(invalid(format!("Es werden 1 bis {n} Zeichen erwartet.")));
+                }
+                if let Some(c) = value.chars().find(|&c| !is_cset82(c)) {
+                    return Err(invalid(format!("Das Zeichen „{c}“ ist nicht erlaubt.")));
+                }
+                Ok(Self {
+                    ai,
+                    value: value.to_owned(),
+                })
+            },
+        }
+    }
+
+    fn has_predefined_length(&self) -> bool {
+        PREDEFINED_LENGTH.contains(&&self.ai.ai[..2])
+    }
+}
+
+/// The element string with [`GS`] as separator after variable length fields.
+fn element_string(elements: &[Element]) -> String {
+    let mut result = String::new();
+    for (index, element) in elements.iter().enumerate() {
+        result.push_str(element.ai.ai);
+        result.push_str(&element.value);
+        if !element.has_predefined_length() && index + 1 < elements.len() {
+            result.push(GS);
+        }
+    }
+    result
+}
+
+/// The human readable interpretation, e.g. "(01)09506000134352(10)ABC123".
+fn human_readable(elements: &[Element]) -> String {
+    elements
+        .iter()
+        .map(|element| format!("({}){}", element.ai.ai, element.value))
+        .collect()
+}
+
+/// The GS1 Digital Link URI, e.g. "https://id.gs1.org/01/09506000134352/10/ABC123?17=201225".
+fn digital_link(elements: &[Element], resolver: &str) -> Result<String, Gs1Error> {
+    let find = |ai: &str| elements.iter().find(|element| element.ai.ai == ai);
+    let (key, qualifiers) = LINK_KEYS
+        .iter()
+        .find(|(key, _)| find(key).is_some())
+        .ok_or(Gs1Error::MissingKey)?;
+
+    let mut url = resolver.to_owned();
+    let mut in_path = vec![*key];
+    for ai in std::iter::once(key).chain(qualifiers.iter()) {
+        if let Some(element) = find(ai) {
+            url.push('/');
+            url.push_str(ai);
+            url.push('/');
+            url.push_str(&percent_encode(&element.value, ""));
+            in_path.push(ai);
+        }
+    }
+    let mut separator = '?';
+    for element in elements {
+        if !in_path.contains(&element.ai.ai) {
+            url.push(separator);
+            url.push_str(element.ai.ai);
+            url.push('=');
+            url.push_str(&percent_encode(&element.value, ""));
+            separator = '&';
+        }
+    }
+    Ok(url)
+}
+
+/// Parses elements in the form "(400)12345(30)10".
+///
+/// Values may contain parentheses, only a known AI in parentheses starts the next element.
+fn parse_elements(text: &str) -> Result<Vec<Element>, Gs1Error> {
+    let mut elements = Vec::new();
+    let mut rest = text.trim();
+    while !rest.is_empty() {
+        let (ai, tail) = rest
+            .strip_prefix('(')
+            .and_then(|rest| rest.split_once(')'))
+            .ok_or(Gs1Error::Syntax)?;
+        let end = tail
+            .match_indices('(')
+            .map(|(index, _)| index)
+            .find(|&index| next_ai(&tail[index..]).is_some())
+            .unwrap_or(tail.len());
+        elements.push(Element::new(ai.trim(), tail[..end].trim())?);
+        rest = tail[end..].trim_start();
+    }
+    Ok(elements)
+}
+
+/// The known AI in parentheses at the start of `text`.
+fn next_ai(text: &str) -> Option<&'static str> {
+    let (ai, _) = text.strip_prefix('(')?.split_once(')')?;
+    AIS.iter()
+        .map(|spec| spec.ai)
+        .find(|&known| known == ai.trim())
+}
+
+/// The GS1 mod 10 check digit of `digits`.
+fn check_digit(digits: &str) -> u8 {
+    let sum: u32 = digits
+        .bytes()
+        .rev()
+        .enumerate()
+        .map(|(index, digit)| {
+            let digit = u32::from(digit - b'0');
+            match index % 2 {
+                0 => 3 * digit,
+                _ => digit,
+            }
+        })
+        .sum();
+    b'0' + ((10 - sum % 10) % 10) as u8
+}
+
+/// Converts `YYYY-MM-DD` to `YYMMDD`, leaves other input as is.
+fn html_date(date: &str) -> String {
+    match date.split('-').collect::<Vec<_>>()[..] {
+        [year, month, day] if year.len() == 4 => format!("{}{month}{day}", &year[2..]),
+        _ => date.to_owned(),
+    }
+}
+
+fn is_valid_date(date: &str, end: bool) -> bool {
+    if date.len() != 6 || !date.bytes().all(|b| b.is_ascii_digit()) {
+        return false;
+    }
+    let number = |range: std::ops::Range<usize>| date[range].parse::<u32>().unwrap_or_default();
+    let (year, month, day) = (2000 + number(0..2), number(2..4), number(4..6));
+    let days = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if year % 4 == 0 => 29,
+        2 => 28,
+        _ => return false,
+    };
+    day <= days && (day > 0 || end)
+}
+
+/// Checks if `c` is in GS1's "CSET 82", the characters allowed in alphanumeric fields.
+fn is_cset82(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "!\"%&'()*+,-./:;<=>?_".contains(c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn form(gtin: &str, extra: &str) -> Form {
+        Form {
+            gtin: gtin.to_owned(),
+            extra: extra.to_owned(),
+            ..Form::default()
+        }
+    }
+
+    #[test]
+    fn check_digits() {
+        assert_eq!(check_digit("400638133393"), b'1');
+        assert_eq!(check_digit("0950600013435"), b'2');
+        assert_eq!(check_digit("1234567"), b'0');
+        assert!(Element::new("01", "4006381333931").is_ok());
+        assert!(Element::new("01", "09506000134352").is_ok());
+        assert!(matches!(
+            Element::new("01", "4006381333932"),
+            Err(Gs1Error::Invalid { reason, .. }) if reason.ends_with("erwartet wird 1.")
+        ));
+
+        // only GTINs are padded to 14 digits
+        assert_eq!(
+            Element::new("01", "4006381333931").unwrap().value,
+            "04006381333931"
+        );
+        assert_eq!(
+            Element::new("414", "4006381333931").unwrap().value,
+            "4006381333931"
+        );
+        assert!(Element::new("414", "006381333931").is_err());
+        assert!(Element::new("410", "400638133393").is_err());
+        assert!(Element::new("00", "12345").is_err());
+
+        assert!(Element::new("30", "10").is_ok());
+        assert!(Element::new("30", "123456789").is_err());
+        assert!(matches!(
+            parse_elements("(30)"),
+            Err(Gs1Error::Invalid { ai: "30", .. })
+        ));
+    }
+
+    #[test]
+    fn dates() {
+        assert!(is_valid_date("240229", false));
+        assert!(!is_valid_date("230229", false));
+        assert!(is_valid_date("230228", false));
+        assert!(!is_valid_date("240431", false));
+        assert!(!is_valid_date("241301", false));
+        assert!(!is_valid_date("240100", false));
+        assert!(is_valid_date("240100", true));
+        assert!(!is_valid_date("2401", true));
+        assert_eq!(html_date("2024-12-31"), "241231");
+        assert!(Element::new("17", "241231").is_ok());
+        assert!(Element::new("11", "241200").is_err());
+        assert!(Element::new("15", "241200").is_ok());
+    }
+
+    #[test]
+    fn element_strings() {
+        let elements = form("4006381333931", "(10)AB-12(17)251231(21)X(Y)1")
+            .elements()
+            .unwrap();
+        assert_eq!(
+            element_string(&elements),
+            "01040063813339311725123110AB-12\x1d21X(Y)1"
+        );
+        assert_eq!(
+            human_readable(&elements),
+            "(01)04006381333931(17)251231(10)AB-12(21)X(Y)1"
+        );
+        assert_eq!(
+            digital_link(&elements, RESOLVER).unwrap(),
+            "https://id.gs1.org/01/04006381333931/10/AB-12/21/X%28Y%291?17=251231"
+        );
+
+        // an unknown AI in parentheses is part of the value
+        let elements = parse_elements("(400)PO(123) (30)10").unwrap();
+        assert_eq!(elements[0].value, "PO(123)");
+        assert_eq!(elements[1].value, "10");
+        assert!(matches!(parse_elements("400)1"), Err(Gs1Error::Syntax)));
+        assert!(matches!(
+            form("", "(10)A(10)B").elements(),
+            Err(Gs1Error::Duplicate("10"))
+        ));
+    }
+}
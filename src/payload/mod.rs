@@ -0,0 +1,299 @@
+//! The kinds of data that can be entered, and how they end up in a symbol.
+
+pub mod bank;
+pub mod bitcoin;
+pub mod contact;
+pub mod email;
+pub mod epc;
+pub mod event;
+pub mod file;
+pub mod geo;
+pub mod gs1;
+pub mod otp;
+pub mod provider;
+pub mod sms;
+pub mod swiss;
+pub mod tel;
+pub mod wifi;
+
+use encoding_rs::{Encoding, UTF_8};
+use qrcode::EcLevel;
+
+use crate::overlay::Overlay;
+use crate::signature::SignatureError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Symbology {
+    Qr,
+    DataMatrix,
+}
+
+/// The text to encode and how to encode it.
+#[derive(Debug, Clone)]
+pub struct Payload {
+    pub text: String,
+    /// Binary data to encode instead of the text, which then only describes it.
+    pub bytes: Option<Vec<u8>>,
+    pub symbology: Symbology,
+    /// The text is a GS1 element string.
+    pub fnc1: bool,
+    /// The error correction level of QR codes, which some formats prescribe.
+    pub ec_level: EcLevel,
+    /// The largest version of QR codes, which some formats prescribe.
+    pub max_version: Option<i16>,
+    /// A graphic in the centre of QR codes, which some formats prescribe.
+    pub overlay: Option<Overlay>,
+    /// The human readable interpretation, if it differs from the text.
+    pub label: Option<String>,
+}
+
+impl Payload {
+    /// Plain text in a regular QR code.
+    pub fn text(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            bytes: None,
+            symbology: Symbology::Qr,
+            fnc1: false,
+            ec_level: EcLevel::L,
+            max_version: None,
+            overlay: None,
+            label: None,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PayloadError {
+    #[error(transparent)]
+    Wifi(#[from] wifi::WifiError),
+    #[error(transparent)]
+    Contact(#[from] contact::ContactError),
+    #[error(transparent)]
+    Email(#[from] email::EmailError),
+    #[error(transparent)]
+    Tel(#[from] tel::TelError),
+    #[error(transparent)]
+    Geo(#[from] geo::GeoError),
+    #[error(transparent)]
+    Event(#[from] event::EventError),
+    #[error(transparent)]
+    Otp(#[from] otp::OtpError),
+    #[error(transparent)]
+    File(#[from] file::FileError),
+    #[error(transparent)]
+    Epc(#[from] epc::EpcError),
+    #[error(transparent)]
+    Bitcoin(#[from] bitcoin::BitcoinError),
+    #[error(transparent)]
+    Swiss(#[from] swiss::SwissError),
+    #[error(transparent)]
+    Gs1(#[from] gs1::Gs1Error),
+    #[error(transparent)]
+    Signature(#[from] SignatureError),
+}
+
+/// Prefixes every character of `text` that is contained in `special` with a backslash.
+fn backslash_escape(text: &str, special: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        if special.contains(c) {
+            result.push('\\');
+        }
+        result.push(c);
+    }
+    result
+}
+
+/// Percent-encodes every byte of `text` except for unreserved characters and those in `keep`.
+fn percent_encode(text: &str, keep: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                result.push(byte as char)
+            },
+            byte if byte.is_ascii() && keep.contains(byte as char) => result.push(byte as char),
+            byte => result.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    result
+}
+
+/// Normalizes line breaks to CRLF, as expected in e-mails and vCards.
+fn crlf(text: &str) -> String {
+    text.lines().collect::<Vec<_>>().join("\r\n")
+}
+
+/// Characters to escape in vCard and iCalendar text values.
+const TEXT_SPECIAL: &str = "\\,;";
+
+/// Escapes a vCard or iCalendar text value, including its line breaks.
+fn escape_text(text: &str) -> String {
+    crlf(&backslash_escape(text.trim(), TEXT_SPECIAL)).replace("\r\n", "\\n")
+}
+
+/// Maximum length of a vCard or iCalendar line in bytes, longer lines are folded.
+const LINE_LEN: usize = 75;
+
+/// Appends `line` to `text`, folded into lines of at most [`LINE_LEN`] bytes.
+fn fold(text: &mut String, line: &str) {
+    let mut len = 0;
+    for c in line.chars() {
+        if len + c.len_utf8() > LINE_LEN {
+            text.push_str("\r\n ");
+            len = 1;
+        }
+        text.push(c);
+        len += c.len_utf8();
+    }
+}
+
+/// A property of a vCard or iCalendar file, like `DTSTART;TZID=Europe/Berlin:20230901T090000`.
+#[derive(Debug)]
+struct ContentLine {
+    /// The uppercased name of the property.
+    name: String,
+    /// The parameters with uppercased names and unquoted values.
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl ContentLine {
+    fn param(&self, name: &str) -> Option<&str> {
+        let mut params = self.params.iter();
+        params.find_map(|(key, value)| (key == name).then_some(value.as_str()))
+    }
+
+    /// Whether one of the `TYPE` parameters contains `kind`, e.g. `TEL;TYPE=WORK,CELL:…`.
+    fn has_type(&self, kind: &str) -> bool {
+        let mut types = self.params.iter().filter(|(key, _)| key == "TYPE");
+        types.any(|(_, value)| value.split(',').any(|t| t.eq_ignore_ascii_case(kind)))
+    }
+
+    /// The value, with the quoted-printable encoding of vCard 2.1 reverted.
+    fn decoded_value(&self) -> String {
+        let quoted_printable = self
+            .param("ENCODING")
+            .is_some_and(|encoding| encoding.eq_ignore_ascii_case("QUOTED-PRINTABLE"));
+        if !quoted_printable {
+            return self.value.clone();
+        }
+        let mut bytes = Vec::with_capacity(self.value.len());
+        let mut rest = self.value.as_bytes();
+        while let Some((&byte, tail)) = rest.split_first() {
+            let hex = tail.get(..2).and_then(|hex| std::str::from_utf8(hex).ok());
+            match hex.map(|hex| u8::from_str_radix(hex, 16)) {
+                Some(Ok(decoded)) if byte == b'=' => {
+                    bytes.push(decoded);
+                    rest = &tail[2..];
+                },
+                _ => {
+                    bytes.push(byte);
+                    rest = tail;
+                },
+            }
+        }
+        let charset = self.param("CHARSET").unwrap_or("UTF-8");
+        let encoding = Encoding::for_label(charset.as_bytes()).unwrap_or(UTF_8);
+        encoding.decode(&bytes).0.into_owned()
+    }
+}
+
+/// Unfolds the lines of a vCard or iCalendar file and splits them into their parts.
+fn content_lines(text: &str) -> Vec<ContentLine> {
+    let mut unfolded: Vec<String> = Vec::new();
+    for line in text.lines() {
+        match (line.strip_prefix([' ', '\t']), unfolded.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            // soft line breaks of quoted-printable values in vCard 2.1
+            (None, Some(last)) if is_soft_break(last) => {
+                last.pop();
+                last.push_str(line);
+            },
+            _ => unfolded.push(line.to_owned()),
+        }
+    }
+
+    let mut lines = Vec::with_capacity(unfolded.len());
+    for line in unfolded {
+        let mut quoted = false;
+        let Some(colon) = line.find(|c| {
+            quoted ^= c == '"';
+            c == ':' && !quoted
+        }) else {
+            continue;
+        };
+        let (head, value) = (&line[..colon], &line[colon + 1..]);
+        let mut parts = head.split(';');
+        let name = parts.next().unwrap_or_default().trim();
+        // groups like `item1.EMAIL` are not needed
+        let name = name.rsplit('.').next().unwrap_or(name).to_ascii_uppercase();
+        // vCard 2.1 allows types without the parameter name, like `TEL;CELL:…`
+        let params = parts
+            .map(|param| param.split_once('=').unwrap_or(("TYPE", param)))
+            .map(|(key, value)| (key.to_ascii_uppercase(), value.trim_matches('"').to_owned()))
+            .collect();
+        lines.push(ContentLine {
+            name,
+            params,
+            value: value.to_owned(),
+        });
+    }
+    lines
+}
+
+fn is_soft_break(line: &str) -> bool {
+    line.ends_with('=') && line.to_ascii_uppercase().contains("QUOTED-PRINTABLE")
+}
+
+/// Splits a structured value like `N:Mustermann;Erika;;;` at the unescaped semicolons.
+fn components(value: &str) -> Vec<String> {
+    let mut components = vec![String::new()];
+    let mut escaped = false;
+    for c in value.chars() {
+        match (c, escaped) {
+            (';', false) => components.push(String::new()),
+            (c, _) => components.last_mut().unwrap().push(c),
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    components.iter().map(|c| unescape_text(c)).collect()
+}
+
+/// Reverts the backslash escaping of vCard and iCalendar text values.
+fn unescape_text(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n' | 'N') => result.push('\n'),
+                Some(c) => result.push(c),
+                None => result.push('\\'),
+            },
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+fn percent_decode(text: &str) -> String {
+    let mut bytes = Vec::new();
+    let mut rest = text.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        match (byte, tail) {
+            (b'%', [hi, lo, tail @ ..]) => {
+                let hex = std::str::from_utf8(&[*hi, *lo]).unwrap().to_owned();
+                bytes.push(u8::from_str_radix(&hex, 16).unwrap());
+                rest = tail;
+            },
+            _ => {
+                bytes.push(byte);
+                rest = tail;
+            },
+        }
+    }
+    String::from_utf8(bytes).unwrap()
+}
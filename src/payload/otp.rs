@@ -0,0 +1,436 @@
+//! One-time password enrolment as `otpauth://` URI (HOTP RFC 4226, TOTP RFC 6238), in the
+//! format of the Google Authenticator.
+
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+
+use super::provider::{Field, Input};
+use super::{percent_encode, Payload};
+
+const BASE32: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// The minimum length of a secret in bytes, as demanded by RFC 4226.
+const MIN_SECRET_LEN: usize = 16;
+
+const DEFAULT_DIGITS: u32 = 6;
+const DEFAULT_PERIOD: u64 = 30;
+
+#[derive(Debug, thiserror::Error)]
+pub enum OtpError {
+    #[error("Bitte einen geheimen Schlüssel angeben oder erzeugen.")]
+    MissingSecret,
+    #[error("Der Schlüssel muss Base32-kodiert sein, „{0}“ ist nicht erlaubt.")]
+    InvalidSecret(char),
+    #[error("Der Schlüssel muss mindestens 128 Bit (26 Base32-Zeichen) lang sein.")]
+    ShortSecret,
+    #[error("Bitte den Namen des Kontos angeben.")]
+    MissingAccount,
+    #[error("Aussteller und Konto dürfen keinen Doppelpunkt enthalten.")]
+    InvalidLabel,
+    #[error("Der Code hat 6 bis 8 Ziffern.")]
+    InvalidDigits,
+    #[error("Die Gültigkeitsdauer muss eine ganze Zahl von Sekunden sein.")]
+    InvalidPeriod,
+    #[error("Der Zähler muss eine ganze, nicht-negative Zahl sein.")]
+    InvalidCounter,
+    #[error("Es konnte kein zufälliger Schlüssel erzeugt werden.")]
+    Random(#[source] getrandom::Error),
+    #[error("Die aktuelle Uhrzeit konnte nicht ermittelt werden.")]
+    Clock(#[source] tz::TzError),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    /// Time-based, the default of nearly all services.
+    #[default]
+    Totp,
+    /// Counter-based.
+    Hotp,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Form {
+    pub kind: Kind,
+    /// Base32, spaces and lowercase letters are accepted.
+    pub secret: String,
+    pub issuer: String,
+    pub account: String,
+    pub algorithm: Algorithm,
+    pub digits: String,
+    /// Seconds, only used for TOTP.
+    pub period: String,
+    /// The initial counter, only used for HOTP.
+    pub counter: String,
+}
+
+pub const SCHEMA: &[Field] = &[
+    Field {
+        name: "kind",
+        label: "Verfahren",
+        input: Input::Select {
+            options: &[
+                ("totp", "zeitbasiert (TOTP)"),
+                ("hotp", "zählerbasiert (HOTP)"),
+            ],
+            default: "totp",
+        },
+    },
+    Field {
+        name: "issuer",
+        label: "Aussteller",
+        input: Input::TEXT,
+    },
+    Field {
+        name: "account",
+        label: "Konto",
+        input: Input::TEXT,
+    },
+    Field {
+        name: "secret",
+        label: "Schlüssel (Base32)",
+        input: Input::Generated("Zufällig erzeugen"),
+    },
+    Field {
+        name: "algorithm",
+        label: "Algorithmus",
+        input: Input::Select {
+            options: &[
+                ("sha1", "SHA-1"),
+                ("sha256", "SHA-256"),
+                ("sha512", "SHA-512"),
+            ],
+            default: "sha1",
+        },
+    },
+    Field {
+        name: "digits",
+        label: "Ziffern",
+        input: Input::Select {
+            options: &[("6", "6"), ("7", "7"), ("8", "8")],
+            default: "6",
+        },
+    },
+    Field {
+        name: "period",
+        label: "Gültigkeit (Sekunden)",
+        input: Input::Text {
+            kind: "text",
+            inputmode: Some("numeric"),
+            placeholder: Some("30"),
+        },
+    },
+    Field {
+        name: "counter",
+        label: "Zähler",
+        input: Input::Text {
+            kind: "text",
+            inputmode: Some("numeric"),
+            placeholder: Some("0"),
+        },
+    },
+    Field {
+        name: "code",
+        label: "Aktueller Code",
+        input: Input::Output,
+    },
+];
+
+/// The validated fields of a [`Form`].
+struct Otp {
+    secret: Vec<u8>,
+    digits: u32,
+    period: u64,
+    counter: u64,
+}
+
+/// A one-time password to compare with the authenticator app.
+#[derive(Debug, Serialize)]
+pub struct Code {
+    pub code: String,
+    /// Seconds until a TOTP code expires.
+    pub remaining: Option<u64>,
+}
+
+impl Form {
+    pub fn payload(&self) -> Result<Payload, OtpError> {
+        let otp = self.validate()?;
+        let issuer = self.issuer.trim();
+        let account = self.account.trim();
+        if account.is_empty() {
+            return Err(OtpError::MissingAccount);
+        }
+        if issuer.contains(':') || account.contains(':') {
+            return Err(OtpError::InvalidLabel);
+        }
+
+        let kind = match self.kind {
+            Kind::Totp => "totp",
+            Kind::Hotp => "hotp",
+        };
+        let mut text = match issuer {
+            "" => format!("otpauth://{kind}/{}", percent_encode(account, "@")),
+            issuer => format!(
+                "otpauth://{kind}/{}:{}",
+                percent_encode(issuer, "@"),
+                percent_encode(account, "@"),
+            ),
+        };
+        text.push_str(&format!("?secret={}", base32(&otp.secret)));
+        if !issuer.is_empty() {
+            text.push_str(&format!("&issuer={}", percent_encode(issuer, "@")));
+        }
+        // only deviations from the defaults are written, as some apps ignore the parameters
+        if self.algorithm != Algorithm::Sha1 {
+            text.push_str(&format!("&algorithm={}", self.algorithm.name()));
+        }
+        if otp.digits != DEFAULT_DIGITS {
+            text.push_str(&format!("&digits={}", otp.digits));
+        }
+        match self.kind {
+            Kind::Totp if otp.period != DEFAULT_PERIOD => {
+                text.push_str(&format!("&period={}", otp.period));
+            },
+            Kind::Totp => {},
+            Kind::Hotp => text.push_str(&format!("&counter={}", otp.counter)),
+        }
+        Ok(Payload::text(text))
+    }
+
+    /// The current one-time password.
+    pub fn code(&self) -> Result<Code, OtpError> {
+        let otp = self.validate()?;
+        let (counter, remaining) = match self.kind {
+            Kind::Totp => {
+                let now = tz::UtcDateTime::now().map_err(OtpError::Clock)?;
+                let now = u64::try_from(now.unix_time()).unwrap_or_default();
+                (now / otp.period, Some(otp.period - now % otp.period))
+            },
+            Kind::Hotp => (otp.counter, None),
+        };
+        let code = hotp(self.algorithm, &otp.secret, counter, otp.digits);
+        Ok(Code { code, remaining })
+    }
+
+    fn validate(&self) -> Result<Otp, OtpError> {
+        let secret = decode_base32(&self.secret)?;
+        if secret.is_empty() {
+            return Err(OtpError::MissingSecret);
+        }
+        if secret.len() < MIN_SECRET_LEN {
+            return Err(OtpError::ShortSecret);
+        }
+        let number = |value: &str, default: u64| match value.trim() {
+            "" => Some(default),
+            value => value.parse().ok(),
+        };
+        let digits = number(&self.digits, DEFAULT_DIGITS.into())
+            .filter(|digits| (6..=8).contains(digits))
+            .ok_or(OtpError::InvalidDigits)?;
+        let period = number(&self.period, DEFAULT_PERIOD)
+            .filter(|&period| period > 0)
+            .ok_or(OtpError::InvalidPeriod)?;
+        let counter = number(&self.counter, 0).ok_or(OtpError::InvalidCounter)?;
+        Ok(Otp {
+            secret,
+            digits: digits as u32,
+            period,
+            counter,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Algorithm {
+    #[default]
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl Algorithm {
+    pub fn name(self) -> &'static str {
+        match self {
+            Algorithm::Sha1 => "SHA1",
+            Algorithm::Sha256 => "SHA256",
+            Algorithm::Sha512 => "SHA512",
+        }
+    }
+
+    /// The length of the digest in bytes.
+    pub fn output_len(self) -> usize {
+        match self {
+            Algorithm::Sha1 => 20,
+            Algorithm::Sha256 => 32,
+            Algorithm::Sha512 => 64,
+        }
+    }
+
+    fn hmac(self, key: &[u8], message: &[u8]) -> Vec<u8> {
+        fn mac<M: Mac + hmac::digest::KeyInit>(key: &[u8], message: &[u8]) -> Vec<u8> {
+            let mut mac = <M as Mac>::new_from_slice(key).expect("HMAC takes keys of any length");
+            mac.update(message);
+            mac.finalize().into_bytes().to_vec()
+        }
+
+        match self {
+            Algorithm::Sha1 => mac::<Hmac<Sha1>>(key, message),
+            Algorithm::Sha256 => mac::<Hmac<Sha256>>(key, message),
+            Algorithm::Sha512 => mac::<Hmac<Sha512>>(key, message),
+        }
+    }
+}
+
+/// A random secret as long as the output of `algorithm`, as recommended by RFC 4226.
+pub fn random_secret(algorithm: Algorithm) -> Result<String, OtpError> {
+    let mut secret = vec![0; algorithm.output_len()];
+    getrandom::getrandom(&mut secret).map_err(OtpError::Random)?;
+    Ok(base32(&secret))
+}
+
+fn hotp(algorithm: Algorithm, secret: &[u8], counter: u64, digits: u32) -> String {
+    let hash = algorithm.hmac(secret, &counter.to_be_bytes());
+    let offset = usize::from(hash[hash.len() - 1] & 0x0f);
+    let value = u32::from_be_bytes(hash[offset..offset + 4].try_into().unwrap()) & 0x7fff_ffff;
+    format!(
+        "{:0width$}",
+        value % 10u32.pow(digits),
+        width = digits as usize
+    )
+}
+
+/// Base32 without padding, as expected by authenticator apps.
+fn base32(data: &[u8]) -> String {
+    let mut text = String::with_capacity((data.len() * 8).div_ceil(5));
+    let (mut buffer, mut bits) = (0u32, 0);
+    for &byte in data {
+        buffer = (buffer << 8) | u32::from(byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            text.push(BASE32[(buffer >> bits) as usize & 31].into());
+        }
+    }
+    if bits > 0 {
+        text.push(BASE32[(buffer << (5 - bits)) as usize & 31].into());
+    }
+    text
+}
+
+fn decode_base32(text: &str) -> Result<Vec<u8>, OtpError> {
+    let mut data = Vec::with_capacity(text.len() * 5 / 8);
+    let (mut buffer, mut bits) = (0u32, 0);
+    for c in text.trim_end_matches('=').chars() {
+        if c.is_whitespace() || c == '-' {
+            continue;
+        }
+        let Some(value) = BASE32
+            .iter()
+            .position(|&b| char::from(b) == c.to_ascii_uppercase())
+        else {
+            return Err(OtpError::InvalidSecret(c));
+        };
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            data.push((buffer >> bits) as u8);
+        }
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rfc_test_vectors() {
+        // RFC 4226, appendix D
+        let secret = b"12345678901234567890";
+        let codes: Vec<_> = (0..4)
+            .map(|c| hotp(Algorithm::Sha1, secret, c, 6))
+            .collect();
+        assert_eq!(codes, ["755224", "287082", "359152", "969429"]);
+
+        // RFC 6238, appendix B, at 59 and 1111111109 seconds
+        let sha256 = b"12345678901234567890123456789012";
+        let sha512 = b"1234567890123456789012345678901234567890123456789012345678901234";
+        assert_eq!(hotp(Algorithm::Sha1, secret, 59 / 30, 8), "94287082");
+        assert_eq!(hotp(Algorithm::Sha256, sha256, 59 / 30, 8), "46119246");
+        assert_eq!(hotp(Algorithm::Sha512, sha512, 59 / 30, 8), "90693936");
+        assert_eq!(
+            hotp(Algorithm::Sha512, sha512, 1111111109 / 30, 8),
+            "25091201"
+        );
+    }
+
+    #[test]
+    fn base32_round_trip() {
+        assert_eq!(base32(b"foobar"), "MZXW6YTBOI");
+        assert_eq!(decode_base32("mzxw 6ytb oi======").unwrap(), b"foobar");
+        for len in 0..=20 {
+            let data: Vec<u8> = (0..len).map(|i: u8| i.wrapping_mul(37)).collect();
+            assert_eq!(decode_base32(&base32(&data)).unwrap(), data);
+        }
+        assert!(matches!(
+            decode_base32("ABC1"),
+            Err(OtpError::InvalidSecret('1'))
+        ));
+    }
+
+    #[test]
+    fn uri() {
+        let secret = random_secret(Algorithm::Sha1).unwrap();
+        assert_eq!(secret.len(), 32);
+        let form = Form {
+            secret: secret.clone(),
+            issuer: "FU Berlin".to_owned(),
+            account: "erika@fu-berlin.de".to_owned(),
+            ..Form::default()
+        };
+        assert_eq!(
+            form.payload().unwrap().text,
+            format!(
+                "otpauth://totp/FU%20Berlin:erika@fu-berlin.de?secret={secret}&issuer=FU%20Berlin"
+            ),
+        );
+
+        let form = Form {
+            kind: Kind::Hotp,
+            algorithm: Algorithm::Sha256,
+            digits: "8".to_owned(),
+            issuer: String::new(),
+            ..form
+        };
+        assert_eq!(
+            form.payload().unwrap().text,
+            format!(
+                "otpauth://hotp/erika@fu-berlin.de?secret={secret}&algorithm=SHA256&digits=8&\
+                 counter=0"
+            ),
+        );
+        assert_eq!(form.code().unwrap().code.len(), 8);
+    }
+
+    #[test]
+    fn rejects_invalid_data() {
+        let form = |secret: &str, account: &str, digits: &str| Form {
+            secret: secret.to_owned(),
+            account: account.to_owned(),
+            digits: digits.to_owned(),
+            ..Form::default()
+        };
+        let secret = "JBSWY3DPEHPK3PXPJBSWY3DPEHPK3PXP";
+        assert!(form(secret, "erika", "").payload().is_ok());
+        assert!(form("", "erika", "").payload().is_err());
+        assert!(form("JBSWY3DPEHPK3PXP", "erika", "").payload().is_err());
+        assert!(form(secret, "", "").payload().is_err());
+        assert!(form(secret, "a:b", "").payload().is_err());
+        assert!(form(secret, "erika", "10").payload().is_err());
+    }
+}
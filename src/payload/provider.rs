@@ -0,0 +1,577 @@
+//! The interface between the kinds of payloads and the rest of the application.
+//!
+//! A kind of payload is added by implementing [`PayloadProvider`], usually through a
+//! [`FormProvider`], and listing it in [`PROVIDERS`]. Its form is rendered from its
+//! [`PayloadProvider::schema`], and imports, documents and animated transfers are served through
+//! its hooks, so neither the page nor the protocol handler need to know the kind.
+
+use std::collections::BTreeMap;
+
+use qrcode::EcLevel;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    bitcoin, contact, email, epc, event, file, geo, gs1, otp, sms, swiss, tel, wifi, Payload,
+    PayloadError,
+};
+use crate::payment_part;
+
+pub const DEFAULT_URL: &str = "https://www.vetmed.fu-berlin.de/";
+
+pub trait PayloadProvider: Sync {
+    /// The value of `t` in the query, the prefix of the form fields, and the `data-type` of the
+    /// fieldset.
+    fn kind(&self) -> &'static str;
+
+    /// The name in the selection of kinds.
+    fn title(&self) -> &'static str;
+
+    /// The fields of the form, if it has any besides the main input field.
+    fn schema(&self) -> Option<&'static [Field]> {
+        None
+    }
+
+    /// Validates the form fields in `query` and serializes them into the text or the bytes of a
+    /// payload.
+    fn payload(&self, query: &str) -> Result<Payload, PayloadError>;
+
+    /// The lowest error correction level to use, unless the payload prescribes a higher one.
+    fn ec_level(&self) -> EcLevel {
+        EcLevel::L
+    }
+
+    /// The form holds secrets like passwords, so responses to its queries must not be cached.
+    fn is_secret(&self) -> bool {
+        false
+    }
+
+    /// A signature can be appended to the text without scanners misreading it, because the text
+    /// has no fixed structure.
+    fn is_signable(&self) -> bool {
+        false
+    }
+
+    /// Reads forms from files, for the [`Input::Import`] field.
+    fn import(&self) -> Option<&'static Import> {
+        None
+    }
+
+    /// The bytes of the form in `query` to send as an animated sequence of codes, for the
+    /// [`Input::Animation`] field.
+    fn transfer(&self, _query: &str) -> Option<Result<Transfer, PayloadError>> {
+        None
+    }
+
+    /// Lays out `svg`, the code of the form in `query` without its quiet zone, as a PDF with the
+    /// query embedded, for the [`Input::Document`] field.
+    fn document(&self, _query: &str, _svg: &str) -> Option<Result<Vec<u8>, DocumentError>> {
+        None
+    }
+}
+
+/// A form field, named `{kind}[{name}]` and with the id `{kind}-{name}`.
+#[derive(Debug)]
+pub struct Field {
+    pub name: &'static str,
+    pub label: &'static str,
+    pub input: Input,
+}
+
+#[derive(Debug)]
+pub enum Input {
+    /// A single line without spell checking, `kind` is the type of the input element like `text`
+    /// or `tel`.
+    Text {
+        kind: &'static str,
+        inputmode: Option<&'static str>,
+        placeholder: Option<&'static str>,
+    },
+    /// A single line with suggestions, like the names of time zones.
+    Suggest {
+        placeholder: Option<&'static str>,
+        options: fn() -> Vec<&'static str>,
+    },
+    /// A whole number between `min` and `max`.
+    Number {
+        value: u32,
+        min: u32,
+        max: u32,
+    },
+    /// A single line with a button next to it, whose text is given, that fills it in.
+    Generated(&'static str),
+    /// A single line that is filled in by an import, like the name of a loaded file.
+    ReadOnly,
+    /// A value that is filled in by an import and not shown.
+    Hidden,
+    TextArea,
+    /// Pairs of value and text, and the value selected by default.
+    Select {
+        options: &'static [(&'static str, &'static str)],
+        default: &'static str,
+    },
+    /// A checkbox with the text next to it.
+    Checkbox(&'static str),
+    /// A button with the given text that loads forms through [`PayloadProvider::import`]. If the
+    /// file holds several, one can be picked, and with a `batch` file name all can be downloaded
+    /// as a PDF with one code per page.
+    Import {
+        button: &'static str,
+        batch: Option<&'static str>,
+    },
+    /// A link to the PDF of [`PayloadProvider::document`].
+    Document {
+        file: &'static str,
+        text: &'static str,
+    },
+    /// Links to the animated sequence of [`PayloadProvider::transfer`] and its full-window view.
+    Animation,
+    /// Text the page keeps up to date, like the current one-time password.
+    Output,
+    /// Lines of notes below the fields.
+    Hint(fn() -> Vec<String>),
+}
+
+impl Input {
+    pub const TEXT: Self = Self::Text {
+        kind: "text",
+        inputmode: None,
+        placeholder: None,
+    };
+}
+
+/// Loads forms from a file, see [`PayloadProvider::import`].
+#[derive(Debug)]
+pub struct Import {
+    /// The title of the file dialog.
+    pub title: &'static str,
+    /// Patterns and description of the files to offer.
+    pub filter: Option<(&'static [&'static str], &'static str)>,
+    /// Reads the forms from the bytes of the file with the given name, serialized like their
+    /// form fields.
+    pub read: fn(&str, &[u8]) -> Result<Vec<serde_json::Value>, String>,
+}
+
+/// Bytes to send as an animated sequence of codes, see [`PayloadProvider::transfer`].
+#[derive(Debug)]
+pub struct Transfer {
+    pub bytes: Vec<u8>,
+    /// The version of each code.
+    pub version: i16,
+    pub ec_level: EcLevel,
+    /// Codes per second.
+    pub fps: u32,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum DocumentError {
+    #[error(transparent)]
+    Payload(#[from] PayloadError),
+    #[error("Der Code konnte nicht eingebettet werden: {0}")]
+    Svg(#[from] usvg::Error),
+}
+
+/// Takes the bytes of an animated transfer from a form.
+pub type TransferFn<F, E> = fn(&F) -> Result<Transfer, E>;
+
+/// Lays out a form with its code as SVG and embeds the query, see [`PayloadProvider::document`].
+pub type DocumentFn<F> = fn(&F, &str, &str) -> Result<Vec<u8>, DocumentError>;
+
+/// A provider whose form fields are deserialized into `F`.
+#[derive(Debug)]
+pub struct FormProvider<F, E> {
+    pub kind: &'static str,
+    pub title: &'static str,
+    pub schema: &'static [Field],
+    pub ec_level: EcLevel,
+    pub secret: bool,
+    pub payload: fn(&F) -> Result<Payload, E>,
+    pub import: Option<&'static Import>,
+    pub transfer: Option<TransferFn<F, E>>,
+    pub document: Option<DocumentFn<F>>,
+}
+
+impl<F, E> PayloadProvider for FormProvider<F, E>
+where
+    F: DeserializeOwned + Default,
+    PayloadError: From<E>,
+{
+    fn kind(&self) -> &'static str {
+        self.kind
+    }
+
+    fn title(&self) -> &'static str {
+        self.title
+    }
+
+    fn schema(&self) -> Option<&'static [Field]> {
+        Some(self.schema)
+    }
+
+    fn payload(&self, query: &str) -> Result<Payload, PayloadError> {
+        Ok((self.payload)(&form::<F>(query, self.kind))?)
+    }
+
+    fn ec_level(&self) -> EcLevel {
+        self.ec_level
+    }
+
+    fn is_secret(&self) -> bool {
+        self.secret
+    }
+
+    fn import(&self) -> Option<&'static Import> {
+        self.import
+    }
+
+    fn transfer(&self, query: &str) -> Option<Result<Transfer, PayloadError>> {
+        let transfer = self.transfer?;
+        Some(transfer(&form::<F>(query, self.kind)).map_err(PayloadError::from))
+    }
+
+    fn document(&self, query: &str, svg: &str) -> Option<Result<Vec<u8>, DocumentError>> {
+        let document = self.document?;
+        Some(document(&form::<F>(query, self.kind), svg, query))
+    }
+}
+
+/// URLs and any other text, entered in the main input field `q`.
+#[derive(Debug)]
+struct UrlProvider;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct UrlForm {
+    q: String,
+}
+
+impl PayloadProvider for UrlProvider {
+    fn kind(&self) -> &'static str {
+        "url"
+    }
+
+    fn title(&self) -> &'static str {
+        "URL"
+    }
+
+    fn payload(&self, query: &str) -> Result<Payload, PayloadError> {
+        let form: UrlForm = parse(query);
+        Ok(Payload::text(url_text(&form.q)))
+    }
+
+    fn is_signable(&self) -> bool {
+        true
+    }
+}
+
+/// The text of the main input field, or the default URL if it is empty.
+pub fn url_text(q: &str) -> &str {
+    match q.trim() {
+        "" => DEFAULT_URL,
+        q => q,
+    }
+}
+
+pub static PROVIDERS: &[&dyn PayloadProvider] = &[
+    &UrlProvider,
+    &FormProvider {
+        kind: "wifi",
+        title: "WLAN-Zugangsdaten",
+        schema: wifi::SCHEMA,
+        ec_level: EcLevel::L,
+        secret: true,
+        payload: wifi::Form::payload,
+        import: None,
+        transfer: None,
+        document: None,
+    },
+    &FormProvider {
+        kind: "contact",
+        title: "Kontakt (vCard, MeCard)",
+        schema: contact::SCHEMA,
+        ec_level: EcLevel::L,
+        secret: false,
+        payload: contact::Form::payload,
+        import: Some(&contact::IMPORT),
+        transfer: None,
+        document: None,
+    },
+    &FormProvider {
+        kind: "email",
+        title: "E-Mail",
+        schema: email::SCHEMA,
+        ec_level: EcLevel::L,
+        secret: false,
+        payload: email::Form::payload,
+        import: None,
+        transfer: None,
+        document: None,
+    },
+    &FormProvider {
+        kind: "sms",
+        title: "SMS",
+        schema: sms::SCHEMA,
+        ec_level: EcLevel::L,
+        secret: false,
+        payload: sms::Form::payload,
+        import: None,
+        transfer: None,
+        document: None,
+    },
+    &FormProvider {
+        kind: "tel",
+        title: "Telefonnummer",
+        schema: tel::SCHEMA,
+        ec_level: EcLevel::L,
+        secret: false,
+        payload: tel::Form::payload,
+        import: None,
+        transfer: None,
+        document: None,
+    },
+    &FormProvider {
+        kind: "geo",
+        title: "Ort (Koordinaten)",
+        schema: geo::SCHEMA,
+        ec_level: EcLevel::L,
+        secret: false,
+        payload: geo::Form::payload,
+        import: None,
+        transfer: None,
+        document: None,
+    },
+    &FormProvider {
+        kind: "event",
+        title: "Termin (iCalendar)",
+        schema: event::SCHEMA,
+        ec_level: EcLevel::L,
+        secret: false,
+        payload: event::Form::payload,
+        import: Some(&event::IMPORT),
+        transfer: None,
+        document: None,
+    },
+    &FormProvider {
+        kind: "otp",
+        title: "Einmalpasswort (otpauth)",
+        schema: otp::SCHEMA,
+        ec_level: EcLevel::L,
+        secret: true,
+        payload: otp::Form::payload,
+        import: None,
+        transfer: None,
+        document: None,
+    },
+    &FormProvider {
+        kind: "file",
+        title: "Datei (binär)",
+        schema: file::SCHEMA,
+        ec_level: EcLevel::L,
+        secret: true,
+        payload: file::Form::payload,
+        import: Some(&file::IMPORT),
+        transfer: Some(file::Form::transfer),
+        document: None,
+    },
+    &FormProvider {
+        kind: "epc",
+        title: "GiroCode (SEPA-Überweisung)",
+        schema: epc::SCHEMA,
+        ec_level: EcLevel::M,
+        secret: false,
+        payload: epc::Form::payload,
+        import: None,
+        transfer: None,
+        document: None,
+    },
+    &FormProvider {
+        kind: "swiss",
+        title: "QR-Rechnung (Schweiz)",
+        schema: swiss::SCHEMA,
+        ec_level: EcLevel::M,
+        secret: false,
+        payload: swiss::Form::payload,
+        import: None,
+        transfer: None,
+        document: Some(payment_part::document),
+    },
+    &FormProvider {
+        kind: "bitcoin",
+        title: "Bitcoin / Lightning",
+        schema: bitcoin::SCHEMA,
+        ec_level: EcLevel::L,
+        secret: false,
+        payload: bitcoin::Form::payload,
+        import: None,
+        transfer: None,
+        document: None,
+    },
+    &FormProvider {
+        kind: "gs1",
+        title: "GS1-Produktdaten",
+        schema: gs1::SCHEMA,
+        ec_level: EcLevel::L,
+        secret: false,
+        payload: gs1::Form::payload,
+        import: None,
+        transfer: None,
+        document: None,
+    },
+];
+
+/// The provider of a kind, falling back to the first one for unknown kinds.
+pub fn provider(kind: &str) -> &'static dyn PayloadProvider {
+    let mut providers = PROVIDERS.iter();
+    *providers
+        .find(|p| p.kind() == kind)
+        .unwrap_or(&PROVIDERS[0])
+}
+
+/// The payload of a kind, with at least the error correction level suggested by its provider.
+pub fn payload(kind: &str, query: &str) -> Result<Payload, PayloadError> {
+    let provider = provider(kind);
+    let payload = provider.payload(query)?;
+    Ok(Payload {
+        ec_level: payload.ec_level.max(provider.ec_level()),
+        ..payload
+    })
+}
+
+/// Parses a query string, falling back to the defaults if it is malformed.
+pub fn parse<T: DeserializeOwned + Default>(query: &str) -> T {
+    // not strict, so that percent-encoded brackets of nested fields are accepted
+    serde_qs::Config::new(5, false)
+        .deserialize_str(query.trim())
+        .ok()
+        .unwrap_or_default()
+}
+
+/// The fields of the form of `kind`, e.g. `ssid` from `t=wifi&wifi[ssid]=…`.
+pub fn form<F: DeserializeOwned + Default>(query: &str, kind: &str) -> F {
+    parse(&section(query, kind))
+}
+
+/// The query of an imported `item` of `kind`, taking the fields it lacks, like the format of
+/// contacts, from the form in `query`.
+pub fn item_query(query: &str, kind: &str, item: &BTreeMap<String, String>) -> String {
+    let mut item_query = form_urlencoded::Serializer::new(String::new());
+    for (key, value) in form_urlencoded::parse(query.trim().as_bytes()) {
+        let name = key
+            .strip_prefix(kind)
+            .and_then(|rest| rest.strip_prefix('['))
+            .and_then(|rest| rest.split(']').next());
+        if name.is_some_and(|name| !item.contains_key(name)) {
+            item_query.append_pair(&key, &value);
+        }
+    }
+    for (name, value) in item {
+        item_query.append_pair(&format!("{kind}[{name}]"), value);
+    }
+    item_query.finish()
+}
+
+/// Serializes the forms read by an [`Import`], or fails with `empty` if there are none.
+pub fn items<T: Serialize>(items: Vec<T>, empty: &str) -> Result<Vec<serde_json::Value>, String> {
+    if items.is_empty() {
+        return Err(empty.to_owned());
+    }
+    let items = items.into_iter().map(serde_json::to_value);
+    items
+        .collect::<Result<_, _>>()
+        .map_err(|err| err.to_string())
+}
+
+fn section(query: &str, kind: &str) -> String {
+    let mut section = form_urlencoded::Serializer::new(String::new());
+    for (key, value) in form_urlencoded::parse(query.trim().as_bytes()) {
+        let Some(rest) = key
+            .strip_prefix(kind)
+            .and_then(|rest| rest.strip_prefix('['))
+        else {
+            continue;
+        };
+        // `swiss[creditor][name]` becomes `creditor[name]`
+        if let Some((name, nested)) = rest.split_once(']') {
+            section.append_pair(&format!("{name}{nested}"), &value);
+        }
+    }
+    section.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sections() {
+        let query = "t=swiss&q=x&swiss%5Bcreditor%5D%5Bname%5D=Fa.%20M%C3%BCller&swiss[iban]=CH12\
+                     &swissx[iban]=no&wifi[ssid]=Gast";
+        assert_eq!(
+            section(query, "swiss"),
+            "creditor%5Bname%5D=Fa.+M%C3%BCller&iban=CH12"
+        );
+        let form: wifi::Form = form(query, "wifi");
+        assert_eq!(form.ssid, "Gast");
+    }
+
+    #[test]
+    fn registry() {
+        let mut kinds: Vec<_> = PROVIDERS.iter().map(|p| p.kind()).collect();
+        kinds.sort_unstable();
+        kinds.dedup();
+        assert_eq!(kinds.len(), PROVIDERS.len());
+
+        assert_eq!(provider("unbekannt").kind(), "url");
+        assert_eq!(payload("url", "q=").unwrap().text, DEFAULT_URL);
+        let payload = payload("tel", "t=tel&tel[number]=030%20838-0").unwrap();
+        assert_eq!(payload.text, "tel:0308380");
+        let epc = provider("epc");
+        assert_eq!(epc.ec_level(), EcLevel::M);
+        assert!(!epc.is_secret());
+        assert!(["wifi", "otp", "file"]
+            .iter()
+            .all(|&kind| provider(kind).is_secret()));
+        assert!(provider("url").is_signable());
+        assert!(PROVIDERS[1..].iter().all(|p| !p.is_signable()));
+    }
+
+    #[test]
+    fn schemas() {
+        assert!(provider("url").schema().is_none());
+        for provider in &PROVIDERS[1..] {
+            let schema = provider.schema().unwrap();
+            let mut names: Vec<_> = schema.iter().map(|field| field.name).collect();
+            names.sort_unstable();
+            names.dedup();
+            assert_eq!(names.len(), schema.len(), "{}", provider.kind());
+            for field in schema {
+                match field.input {
+                    Input::Import { .. } => assert!(provider.import().is_some()),
+                    Input::Select { options, default } => {
+                        assert!(options.iter().any(|&(value, _)| value == default));
+                    },
+                    _ => {},
+                }
+            }
+        }
+        assert!(provider("swiss").document("t=swiss", "").is_some());
+        assert!(provider("file").transfer("t=file").is_some());
+        assert!(provider("epc").transfer("t=epc").is_none());
+    }
+
+    #[test]
+    fn item_queries() {
+        let item = BTreeMap::from([
+            ("given".to_owned(), "Anna".to_owned()),
+            ("family".to_owned(), String::new()),
+        ]);
+        let query = "t=contact&contact%5Bformat%5D=mecard&contact%5Bfamily%5D=Berg&q=x";
+        assert_eq!(
+            item_query(query, "contact", &item),
+            "contact%5Bformat%5D=mecard&contact%5Bfamily%5D=&contact%5Bgiven%5D=Anna"
+        );
+        let read = (contact::IMPORT.read)("a.vcf", b"BEGIN:VCARD\r\nFN:Anna\r\nEND:VCARD");
+        assert_eq!(read.unwrap()[0]["given"], "Anna");
+        assert!((contact::IMPORT.read)("a.vcf", b"").is_err());
+    }
+}
@@ -0,0 +1,73 @@
+//! Text messages as `sms:` URIs (RFC 5724).
+
+use serde::Deserialize;
+
+use super::provider::{Field, Input};
+use super::tel::{self, TelError};
+use super::{percent_encode, Payload};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Form {
+    pub number: String,
+    pub body: String,
+}
+
+pub const SCHEMA: &[Field] = &[
+    Field {
+        name: "number",
+        label: "Telefonnummer",
+        input: Input::Text {
+            kind: "tel",
+            inputmode: None,
+            placeholder: None,
+        },
+    },
+    Field {
+        name: "body",
+        label: "Text",
+        input: Input::TextArea,
+    },
+];
+
+impl Form {
+    pub fn payload(&self) -> Result<Payload, TelError> {
+        let mut uri = format!("sms:{}", tel::normalize(&self.number)?);
+        if !self.body.trim().is_empty() {
+            uri.push_str("?body=");
+            uri.push_str(&percent_encode(self.body.trim(), ""));
+        }
+        Ok(Payload::text(uri))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::percent_decode;
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let form = Form {
+            number: "+49 171 1234567".to_owned(),
+            body: "Bin gleich da & bringe 2 Brötchen mit?\nGruß".to_owned(),
+        };
+        let text = form.payload().unwrap().text;
+        let (number, body) = text
+            .strip_prefix("sms:")
+            .unwrap()
+            .split_once("?body=")
+            .unwrap();
+        assert_eq!(number, "+491711234567");
+        assert_eq!(percent_decode(body), form.body);
+    }
+
+    #[test]
+    fn without_body() {
+        let form = Form {
+            number: "030 12345".to_owned(),
+            body: " ".to_owned(),
+        };
+        assert_eq!(form.payload().unwrap().text, "sms:03012345");
+    }
+}
@@ -0,0 +1,530 @@
+//! Swiss QR-bills, following the Swiss Implementation Guidelines for the QR-bill, version 2.2.
+
+use qrcode::EcLevel;
+use serde::Deserialize;
+
+use super::bank::{self, BankError};
+use super::provider::{Field, Input};
+use super::Payload;
+use crate::overlay::Overlay;
+
+/// Maximum size of the payload in characters.
+const MAX_LEN: usize = 997;
+
+/// Weights of the recursive modulo 10 check digit of QR references.
+const MOD10: [u32; 10] = [0, 9, 4, 6, 8, 2, 7, 1, 3, 5];
+
+#[derive(Debug, thiserror::Error)]
+pub enum SwissError {
+    #[error(transparent)]
+    Bank(#[from] BankError),
+    #[error("Für eine QR-Rechnung wird eine IBAN aus der Schweiz oder Liechtenstein benötigt.")]
+    NotSwiss,
+    #[error("Bitte den Namen des {0} angeben.")]
+    MissingName(&'static str),
+    #[error("Bitte Postleitzahl und Ort des {0} angeben.")]
+    MissingTown(&'static str),
+    #[error("{field} darf höchstens {max} Zeichen lang sein.")]
+    TooLong { field: &'static str, max: usize },
+    #[error("Das Zeichen „{0}“ ist in einer QR-Rechnung nicht erlaubt.")]
+    InvalidCharacter(char),
+    #[error("„{0}“ ist kein Ländercode wie „CH“.")]
+    InvalidCountry(String),
+    #[error("Eine QR-IBAN benötigt eine QR-Referenz mit 27 Ziffern.")]
+    MissingQrReference,
+    #[error("Die QR-Referenz muss aus 27 Ziffern bestehen, deren letzte die Prüfziffer ist.")]
+    InvalidQrReference,
+    #[error("Die Prüfziffer der QR-Referenz stimmt nicht, erwartet wird {0}.")]
+    QrReferenceChecksum(u32),
+    #[error("Mit einer normalen IBAN ist nur eine Creditor Reference („RF…“) möglich.")]
+    QrReferenceWithoutQrIban,
+    #[error("Die Rechnungsdaten sind mit {0} Zeichen zu lang für eine QR-Rechnung.")]
+    DataTooLong(usize),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Currency {
+    #[default]
+    Chf,
+    Eur,
+}
+
+impl Currency {
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::Chf => "CHF",
+            Self::Eur => "EUR",
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Form {
+    pub iban: String,
+    pub name: String,
+    pub street: String,
+    pub building: String,
+    pub zip: String,
+    pub town: String,
+    pub country: String,
+    /// Optional, the payer fills it in otherwise.
+    pub amount: String,
+    pub currency: Currency,
+    pub debtor_name: String,
+    pub debtor_street: String,
+    pub debtor_building: String,
+    pub debtor_zip: String,
+    pub debtor_town: String,
+    pub debtor_country: String,
+    /// A QR reference for QR-IBANs, or an optional creditor reference ("RF…").
+    pub reference: String,
+    pub message: String,
+}
+
+pub const SCHEMA: &[Field] = &[
+    Field {
+        name: "iban",
+        label: "IBAN oder QR-IBAN",
+        input: Input::TEXT,
+    },
+    Field {
+        name: "name",
+        label: "Zahlungsempfänger",
+        input: Input::TEXT,
+    },
+    Field {
+        name: "street",
+        label: "Straße",
+        input: Input::TEXT,
+    },
+    Field {
+        name: "building",
+        label: "Hausnummer",
+        input: Input::TEXT,
+    },
+    Field {
+        name: "zip",
+        label: "Postleitzahl",
+        input: Input::TEXT,
+    },
+    Field {
+        name: "town",
+        label: "Ort",
+        input: Input::TEXT,
+    },
+    Field {
+        name: "country",
+        label: "Land",
+        input: COUNTRY,
+    },
+    Field {
+        name: "amount",
+        label: "Betrag",
+        input: Input::Text {
+            kind: "text",
+            inputmode: Some("decimal"),
+            placeholder: None,
+        },
+    },
+    Field {
+        name: "currency",
+        label: "Währung",
+        input: Input::Select {
+            options: &[("chf", "CHF"), ("eur", "EUR")],
+            default: "chf",
+        },
+    },
+    Field {
+        name: "reference",
+        label: "Referenz",
+        input: Input::TEXT,
+    },
+    Field {
+        name: "message",
+        label: "Mitteilung",
+        input: Input::TEXT,
+    },
+    Field {
+        name: "debtor_name",
+        label: "Zahlungspflichtiger",
+        input: Input::TEXT,
+    },
+    Field {
+        name: "debtor_street",
+        label: "Straße",
+        input: Input::TEXT,
+    },
+    Field {
+        name: "debtor_building",
+        label: "Hausnummer",
+        input: Input::TEXT,
+    },
+    Field {
+        name: "debtor_zip",
+        label: "Postleitzahl",
+        input: Input::TEXT,
+    },
+    Field {
+        name: "debtor_town",
+        label: "Ort",
+        input: Input::TEXT,
+    },
+    Field {
+        name: "debtor_country",
+        label: "Land",
+        input: COUNTRY,
+    },
+    Field {
+        name: "bill",
+        label: "Zahlteil",
+        input: Input::Document {
+            file: "zahlteil.pdf",
+            text: "Zahlteil als PDF (A6)",
+        },
+    },
+];
+
+const COUNTRY: Input = Input::Text {
+    kind: "text",
+    inputmode: None,
+    placeholder: Some("CH"),
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Address {
+    pub name: String,
+    pub street: String,
+    pub building: String,
+    pub zip: String,
+    pub town: String,
+    pub country: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reference {
+    /// QR reference, for QR-IBANs only.
+    Qrr,
+    /// ISO 11649 creditor reference.
+    Scor,
+    Non,
+}
+
+/// The validated content of a QR-bill, as shown in the payment part.
+#[derive(Debug, Clone)]
+pub struct Bill {
+    pub iban: String,
+    pub creditor: Address,
+    pub amount: Option<u64>,
+    pub currency: Currency,
+    pub debtor: Option<Address>,
+    pub reference_type: Reference,
+    pub reference: String,
+    pub message: String,
+}
+
+impl Form {
+    pub fn payload(&self) -> Result<Payload, SwissError> {
+        let bill = self.bill()?;
+        let text = bill.text();
+        if text.chars().count() > MAX_LEN {
+            return Err(SwissError::DataTooLong(text.chars().count()));
+        }
+        Ok(Payload {
+            ec_level: EcLevel::M,
+            overlay: Some(Overlay::SwissCross),
+            label: Some(format!("QR-Rechnung an {}", bill.creditor.name)),
+            ..Payload::text(text)
+        })
+    }
+
+    pub fn bill(&self) -> Result<Bill, SwissError> {
+        let iban = bank::iban(&self.iban)?;
+        if !iban.starts_with("CH") && !iban.starts_with("LI") {
+            return Err(SwissError::NotSwiss);
+        }
+        let creditor = address(
+            [
+                &self.name,
+                &self.street,
+                &self.building,
+                &self.zip,
+                &self.town,
+                &self.country,
+            ],
+            "Zahlungsempfängers",
+        )?
+        .ok_or(SwissError::MissingName("Zahlungsempfängers"))?;
+        let debtor = address(
+            [
+                &self.debtor_name,
+                &self.debtor_street,
+                &self.debtor_building,
+                &self.debtor_zip,
+                &self.debtor_town,
+                &self.debtor_country,
+            ],
+            "Zahlungspflichtigen",
+        )?;
+        let amount = match self.amount.trim() {
+            "" => None,
+            amount => Some(bank::cents(amount)?),
+        };
+
+        let reference = self.reference.trim();
+        let (reference_type, reference) = match (is_qr_iban(&iban), reference) {
+            (true, "") => return Err(SwissError::MissingQrReference),
+            (true, reference) => (Reference::Qrr, qr_reference(reference)?),
+            (false, "") => (Reference::Non, String::new()),
+            (false, reference) if bank::is_creditor_reference(reference) => {
+                (Reference::Scor, bank::creditor_reference(reference)?)
+            },
+            (false, _) => return Err(SwissError::QrReferenceWithoutQrIban),
+        };
+        let message = self.message.trim().to_owned();
+        check("Die Mitteilung", &message, 140)?;
+
+        Ok(Bill {
+            iban,
+            creditor,
+            amount,
+            currency: self.currency,
+            debtor,
+            reference_type,
+            reference,
+            message,
+        })
+    }
+}
+
+impl Bill {
+    /// The Swiss Payments Code in the QR code.
+    fn text(&self) -> String {
+        let mut lines = vec!["SPC", "0200", "1", &self.iban];
+        push_address(&mut lines, Some(&self.creditor));
+        push_address(&mut lines, None);
+        let amount = self.amount.map(format_amount).unwrap_or_default();
+        lines.extend([amount.as_str(), self.currency.code()]);
+        push_address(&mut lines, self.debtor.as_ref());
+        let reference_type = match self.reference_type {
+            Reference::Qrr => "QRR",
+            Reference::Scor => "SCOR",
+            Reference::Non => "NON",
+        };
+        lines.extend([reference_type, &self.reference, &self.message, "EPD"]);
+        lines.join("\n")
+    }
+}
+
+impl Address {
+    /// The lines of the address in the payment part.
+    pub fn lines(&self) -> Vec<String> {
+        let street = format!("{} {}", self.street, self.building);
+        let town = format!("{}-{} {}", self.country, self.zip, self.town);
+        [self.name.clone(), street.trim().to_owned(), town]
+            .into_iter()
+            .filter(|line| !line.is_empty())
+            .collect()
+    }
+}
+
+fn push_address<'a>(lines: &mut Vec<&'a str>, address: Option<&'a Address>) {
+    match address {
+        Some(address) => lines.extend([
+            "S",
+            &address.name,
+            &address.street,
+            &address.building,
+            &address.zip,
+            &address.town,
+            &address.country,
+        ]),
+        None => lines.extend([""; 7]),
+    }
+}
+
+/// Validates an address, which is either complete except for the street, or entirely empty.
+///
+/// `party` is the genitive of the role of the address in error messages.
+fn address(fields: [&String; 6], party: &'static str) -> Result<Option<Address>, SwissError> {
+    let [name, street, building, zip, town, country] = fields.map(|field| field.trim());
+    if [name, street, building, zip, town, country]
+        .iter()
+        .all(|f| f.is_empty())
+    {
+        return Ok(None);
+    }
+    if name.is_empty() {
+        return Err(SwissError::MissingName(party));
+    }
+    if zip.is_empty() || town.is_empty() {
+        return Err(SwissError::MissingTown(party));
+    }
+    check("Der Name", name, 70)?;
+    check("Die Strasse", street, 70)?;
+    check("Die Hausnummer", building, 16)?;
+    check("Die Postleitzahl", zip, 16)?;
+    check("Der Ort", town, 35)?;
+    let country = match country.to_ascii_uppercase() {
+        country if country.is_empty() => "CH".to_owned(),
+        country if country.len() == 2 && country.bytes().all(|b| b.is_ascii_uppercase()) => country,
+        _ => return Err(SwissError::InvalidCountry(country.to_owned())),
+    };
+    Ok(Some(Address {
+        name: name.to_owned(),
+        street: street.to_owned(),
+        building: building.to_owned(),
+        zip: zip.to_owned(),
+        town: town.to_owned(),
+        country,
+    }))
+}
+
+/// Checks the length of a field and that it contains only characters of the permitted Latin set.
+fn check(field: &'static str, value: &str, max: usize) -> Result<(), SwissError> {
+    if let Some(c) = value.chars().find(|&c| !is_permitted(c)) {
+        return Err(SwissError::InvalidCharacter(c));
+    }
+    match value.chars().count() <= max {
+        true => Ok(()),
+        false => Err(SwissError::TooLong { field, max }),
+    }
+}
+
+fn is_permitted(c: char) -> bool {
+    matches!(c, ' '..='~' | '\u{a0}'..='\u{17f}' | 'Ș' | 'ș' | 'Ț' | 'ț' | '€')
+}
+
+/// QR-IBANs have an institution identification between 30000 and 31999.
+fn is_qr_iban(iban: &str) -> bool {
+    matches!(
+        iban.get(4..9).and_then(|iid| iid.parse().ok()),
+        Some(30000..=31999)
+    )
+}
+
+/// Validates a QR reference, leading zeros may be omitted.
+fn qr_reference(reference: &str) -> Result<String, SwissError> {
+    let digits: String = reference.chars().filter(|c| !c.is_whitespace()).collect();
+    if digits.is_empty() || digits.len() > 27 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(SwissError::InvalidQrReference);
+    }
+    let digits = format!("{digits:0>27}");
+    let expected = mod10(&digits[..26]);
+    match digits[26..].parse() == Ok(expected) {
+        true => Ok(digits),
+        false => Err(SwissError::QrReferenceChecksum(expected)),
+    }
+}
+
+/// The recursive modulo 10 check digit of `digits`.
+fn mod10(digits: &str) -> u32 {
+    let carry = digits
+        .chars()
+        .filter_map(|c| c.to_digit(10))
+        .fold(0, |carry, digit| MOD10[((carry + digit) % 10) as usize]);
+    (10 - carry) % 10
+}
+
+/// Formats cents like "1949.75", as required in the QR code.
+pub fn format_amount(cents: u64) -> String {
+    format!("{}.{:02}", cents / 100, cents % 100)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn form() -> Form {
+        Form {
+            iban: "CH44 3199 9123 0008 8901 2".to_owned(),
+            name: "Robert Schneider AG".to_owned(),
+            street: "Rue du Lac".to_owned(),
+            building: "1268".to_owned(),
+            zip: "2501".to_owned(),
+            town: "Biel".to_owned(),
+            amount: "1'949.75".to_owned(),
+            reference: "21 00000 00003 13947 14300 09017".to_owned(),
+            message: "Auftrag vom 15.06.2020".to_owned(),
+            ..Form::default()
+        }
+    }
+
+    #[test]
+    fn qr_reference_payload() {
+        let payload = form().payload().unwrap();
+        assert_eq!(payload.ec_level, EcLevel::M);
+        assert_eq!(payload.overlay, Some(Overlay::SwissCross));
+        let lines: Vec<_> = payload.text.split('\n').collect();
+        assert_eq!(lines.len(), 31);
+        assert_eq!(lines[..4], ["SPC", "0200", "1", "CH4431999123000889012"]);
+        assert_eq!(lines[4..11], [
+            "S",
+            "Robert Schneider AG",
+            "Rue du Lac",
+            "1268",
+            "2501",
+            "Biel",
+            "CH"
+        ]);
+        assert_eq!(lines[11..18], [""; 7]);
+        assert_eq!(lines[18..20], ["1949.75", "CHF"]);
+        assert_eq!(lines[20..27], [""; 7]);
+        assert_eq!(lines[27..], [
+            "QRR",
+            "210000000003139471430009017",
+            "Auftrag vom 15.06.2020",
+            "EPD"
+        ]);
+    }
+
+    #[test]
+    fn references() {
+        assert_eq!(mod10("21000000000313947143000901"), 7);
+        let wrong = Form {
+            reference: "21 00000 00003 13947 14300 09018".to_owned(),
+            ..form()
+        };
+        assert!(matches!(
+            wrong.bill(),
+            Err(SwissError::QrReferenceChecksum(7))
+        ));
+        let regular_iban = Form {
+            iban: "CH93 0076 2011 6238 5295 7".to_owned(),
+            ..form()
+        };
+        assert!(matches!(
+            regular_iban.bill(),
+            Err(SwissError::QrReferenceWithoutQrIban)
+        ));
+        let scor = Form {
+            reference: "RF18 5390 0754 7034".to_owned(),
+            ..regular_iban
+        };
+        let bill = scor.bill().unwrap();
+        assert_eq!(bill.reference_type, Reference::Scor);
+    }
+
+    #[test]
+    fn rejects_invalid_data() {
+        let german = Form {
+            iban: "DE89 3704 0044 0532 0130 00".to_owned(),
+            ..form()
+        };
+        assert!(matches!(german.bill(), Err(SwissError::NotSwiss)));
+        let emoji = Form {
+            message: "Danke 🙂".to_owned(),
+            ..form()
+        };
+        assert!(matches!(
+            emoji.bill(),
+            Err(SwissError::InvalidCharacter('🙂'))
+        ));
+        let no_town = Form {
+            town: "".to_owned(),
+            ..form()
+        };
+        assert!(no_town.bill().is_err());
+    }
+}
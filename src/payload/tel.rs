@@ -0,0 +1,87 @@
+//! Phone numbers as `tel:` URIs (RFC 3966).
+
+use serde::Deserialize;
+
+use super::provider::{Field, Input};
+use super::Payload;
+
+#[derive(Debug, thiserror::Error)]
+pub enum TelError {
+    #[error("Bitte eine Telefonnummer angeben.")]
+    Missing,
+    #[error("„{0}“ ist keine gültige Telefonnummer.")]
+    Invalid(String),
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Form {
+    pub number: String,
+}
+
+pub const SCHEMA: &[Field] = &[Field {
+    name: "number",
+    label: "Telefonnummer",
+    input: Input::Text {
+        kind: "tel",
+        inputmode: None,
+        placeholder: Some("+49 30 838-0"),
+    },
+}];
+
+impl Form {
+    pub fn payload(&self) -> Result<Payload, TelError> {
+        Ok(Payload::text(format!("tel:{}", normalize(&self.number)?)))
+    }
+}
+
+/// Removes spaces and visual separators like in "+49 (30) 838-51833" from a phone number.
+pub fn normalize(number: &str) -> Result<String, TelError> {
+    let number = number.trim();
+    if number.is_empty() {
+        return Err(TelError::Missing);
+    }
+    let (plus, digits) = match number.strip_prefix('+') {
+        Some(digits) => ("+", digits),
+        None => ("", number),
+    };
+    let mut result = plus.to_owned();
+    for c in digits.chars() {
+        match c {
+            '0'..='9' => result.push(c),
+            '-' | '.' | '/' | '(' | ')' => {},
+            c if c.is_whitespace() => {},
+            _ => return Err(TelError::Invalid(number.to_owned())),
+        }
+    }
+    match result.len() - plus.len() {
+        3..=20 => Ok(result),
+        _ => Err(TelError::Invalid(number.to_owned())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(number: &str) -> String {
+        let form = Form {
+            number: number.to_owned(),
+        };
+        let text = form.payload().unwrap().text;
+        text.strip_prefix("tel:").unwrap().to_owned()
+    }
+
+    #[test]
+    fn keeps_digits() {
+        assert_eq!(round_trip("+49 (30) 838-51833"), "+493083851833");
+        assert_eq!(round_trip("030 / 838 518 33"), "03083851833");
+    }
+
+    #[test]
+    fn rejects_letters() {
+        assert!(normalize("0800 FLOWERS").is_err());
+        assert!(normalize("  ").is_err());
+        assert!(normalize("+1").is_err());
+    }
+}
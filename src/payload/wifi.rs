@@ -0,0 +1,236 @@
+//! Wi-Fi credentials in the `WIFI:` format, which most camera apps offer to join.
+
+use serde::Deserialize;
+
+use super::provider::{Field, Input};
+use super::{backslash_escape, Payload};
+
+/// Characters with a special meaning in `WIFI:` values.
+const SPECIAL: &str = "\\;,:\"";
+
+#[derive(Debug, thiserror::Error)]
+pub enum WifiError {
+    #[error("Bitte den Namen des Netzwerks (SSID) angeben.")]
+    MissingSsid,
+    #[error("Der Name des Netzwerks darf höchstens 32 Bytes lang sein.")]
+    SsidTooLong,
+    #[error("Bitte das Passwort angeben.")]
+    MissingPassword,
+    #[error("Ein WPA-Passwort hat 8 bis 63 Zeichen oder 64 Hexadezimalziffern.")]
+    InvalidWpaPassword,
+    #[error("Ein WEP-Schlüssel hat 5 oder 13 Zeichen oder 10 oder 26 Hexadezimalziffern.")]
+    InvalidWepKey,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Security {
+    /// WPA, WPA2 or WPA3 personal.
+    #[default]
+    Wpa,
+    Wep,
+    /// An open network.
+    Nopass,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Form {
+    pub ssid: String,
+    pub security: Security,
+    pub password: String,
+    /// The network does not broadcast its SSID.
+    pub hidden: bool,
+}
+
+pub const SCHEMA: &[Field] = &[
+    Field {
+        name: "ssid",
+        label: "Netzwerkname (SSID)",
+        input: Input::TEXT,
+    },
+    Field {
+        name: "security",
+        label: "Verschlüsselung",
+        input: Input::Select {
+            options: &[
+                ("wpa", "WPA, WPA2 oder WPA3"),
+                ("wep", "WEP"),
+                ("nopass", "keine"),
+            ],
+            default: "wpa",
+        },
+    },
+    Field {
+        name: "password",
+        label: "Passwort",
+        input: Input::TEXT,
+    },
+    Field {
+        name: "hidden",
+        label: "Versteckt",
+        input: Input::Checkbox("Das Netzwerk sendet seinen Namen nicht aus"),
+    },
+];
+
+impl Form {
+    pub fn payload(&self) -> Result<Payload, WifiError> {
+        // neither SSIDs nor passwords are trimmed, as spaces are significant in both
+        if self.ssid.is_empty() {
+            return Err(WifiError::MissingSsid);
+        }
+        if self.ssid.len() > 32 {
+            return Err(WifiError::SsidTooLong);
+        }
+
+        let password = &self.password;
+        let is_hex = |len: usize| password.len() == len && is_hex(password);
+        let is_ascii = |len: usize| password.chars().count() == len && password.is_ascii();
+        // a hexadecimal key has a length that no passphrase of the same type can have
+        let is_key = match self.security {
+            Security::Wpa => is_hex(64),
+            Security::Wep => is_hex(10) || is_hex(26),
+            Security::Nopass => false,
+        };
+        let security = match self.security {
+            Security::Nopass => "nopass",
+            _ if password.is_empty() => return Err(WifiError::MissingPassword),
+            Security::Wpa => match (8..=63).contains(&password.chars().count()) || is_key {
+                true => "WPA",
+                false => return Err(WifiError::InvalidWpaPassword),
+            },
+            Security::Wep => match is_ascii(5) || is_ascii(13) || is_key {
+                true => "WEP",
+                false => return Err(WifiError::InvalidWepKey),
+            },
+        };
+
+        let mut text = format!("WIFI:T:{security};S:{};", value(&self.ssid));
+        if self.security != Security::Nopass {
+            let password = match is_key {
+                true => password.to_owned(),
+                false => value(password),
+            };
+            text.push_str(&format!("P:{password};"));
+        }
+        if self.hidden {
+            text.push_str("H:true;");
+        }
+        text.push(';');
+        Ok(Payload::text(text))
+    }
+}
+
+/// Escapes a value, and quotes it if readers could mistake it for a hexadecimal key.
+fn value(value: &str) -> String {
+    match is_hex(value) {
+        true => format!("\"{value}\""),
+        false => backslash_escape(value, SPECIAL),
+    }
+}
+
+fn is_hex(value: &str) -> bool {
+    !value.is_empty() && value.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses a `WIFI:` string into its fields, the way ZXing does.
+    fn parse(text: &str) -> Vec<(char, String)> {
+        let mut chars = text.strip_prefix("WIFI:").unwrap().chars();
+        let mut fields = Vec::new();
+        while let Some(name) = chars.next() {
+            if name == ';' {
+                break;
+            }
+            assert_eq!(chars.next(), Some(':'));
+            let mut value = String::new();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => value.push(chars.next().unwrap()),
+                    ';' => break,
+                    c => value.push(c),
+                }
+            }
+            if let Some(quoted) = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+                value = quoted.to_owned();
+            }
+            fields.push((name, value));
+        }
+        assert_eq!(chars.next(), None);
+        fields
+    }
+
+    fn form(ssid: &str, security: Security, password: &str, hidden: bool) -> Form {
+        Form {
+            ssid: ssid.to_owned(),
+            security,
+            password: password.to_owned(),
+            hidden,
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let form = form("Café; \"Gäste\"", Security::Wpa, r"p\a:ss,w;rd", true);
+        let text = form.payload().unwrap().text;
+        assert_eq!(parse(&text), [
+            ('T', "WPA".to_owned()),
+            ('S', form.ssid.clone()),
+            ('P', form.password.clone()),
+            ('H', "true".to_owned()),
+        ]);
+    }
+
+    #[test]
+    fn hex_values() {
+        // hexadecimal keys stay as they are
+        let text = form("CAFE", Security::Wep, "0123456789", false)
+            .payload()
+            .unwrap()
+            .text;
+        assert_eq!(text, r#"WIFI:T:WEP;S:"CAFE";P:0123456789;;"#);
+        assert_eq!(parse(&text), [
+            ('T', "WEP".to_owned()),
+            ('S', "CAFE".to_owned()),
+            ('P', "0123456789".to_owned()),
+        ]);
+        let psk = "0123456789abcdef".repeat(4);
+        let text = form("x", Security::Wpa, &psk, false)
+            .payload()
+            .unwrap()
+            .text;
+        assert_eq!(text, format!("WIFI:T:WPA;S:x;P:{psk};;"));
+        let key = "0123456789ABCDEF0123456789";
+        let text = form("x", Security::Wep, key, false).payload().unwrap().text;
+        assert_eq!(text, format!("WIFI:T:WEP;S:x;P:{key};;"));
+
+        // but passphrases that look hexadecimal are quoted
+        let text = form("x", Security::Wpa, "12345678", false)
+            .payload()
+            .unwrap()
+            .text;
+        assert_eq!(text, r#"WIFI:T:WPA;S:x;P:"12345678";;"#);
+        let text = form("x", Security::Wep, "ABCDE", false)
+            .payload()
+            .unwrap()
+            .text;
+        assert_eq!(text, r#"WIFI:T:WEP;S:x;P:"ABCDE";;"#);
+    }
+
+    #[test]
+    fn open_network() {
+        let form = form("Freifunk", Security::Nopass, "ignored", false);
+        assert_eq!(form.payload().unwrap().text, "WIFI:T:nopass;S:Freifunk;;");
+    }
+
+    #[test]
+    fn rejects_invalid_passwords() {
+        assert!(form("x", Security::Wpa, "short", false).payload().is_err());
+        assert!(form("x", Security::Wpa, "", false).payload().is_err());
+        assert!(form("x", Security::Wep, "123456", false).payload().is_err());
+        assert!(form("", Security::Nopass, "", false).payload().is_err());
+    }
+}
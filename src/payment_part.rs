@@ -0,0 +1,280 @@
+//! The payment part of a Swiss QR-bill as A6 PDF, following the QR-bill style guide.
+
+use pdf_writer::{Content, Finish, Name, PdfWriter, Rect, Ref, Str};
+use usvg::TreeParsing;
+
+use crate::helvetica::{win_ansi, wrap};
+use crate::metadata;
+use crate::payload::provider::DocumentError;
+use crate::payload::swiss::{self, Bill, Reference};
+use crate::payload::PayloadError;
+
+/// Points per millimeter.
+const MM: f32 = 72.0 / 25.4;
+
+const WIDTH: f32 = 148.0;
+const HEIGHT: f32 = 105.0;
+
+/// Left edge of the section with the account, reference and further information.
+const INFO_X: f32 = 56.0;
+
+const REGULAR: Name<'static> = Name(b"F1");
+const BOLD: Name<'static> = Name(b"F2");
+const QR_CODE: Name<'static> = Name(b"Q1");
+
+/// The payment part of the form, the document of the `swiss` provider.
+pub fn document(form: &swiss::Form, svg: &str, settings: &str) -> Result<Vec<u8>, DocumentError> {
+    let bill = form.bill().map_err(PayloadError::from)?;
+    let payload = form.payload().map_err(PayloadError::from)?;
+    Ok(pdf(&bill, svg, settings, &payload.text)?)
+}
+
+/// Lays out the payment part with the QR code `svg`, which must not have a quiet zone, and
+/// embeds the editor settings and the content of the code.
+pub fn pdf(bill: &Bill, svg: &str, settings: &str, text: &str) -> Result<Vec<u8>, usvg::Error> {
+    let tree = usvg::Tree::from_str(svg, &usvg::Options::default())?;
+
+    let catalog_id = Ref::new(1);
+    let page_tree_id = Ref::new(2);
+    let page_id = Ref::new(3);
+    let regular_id = Ref::new(4);
+    let bold_id = Ref::new(5);
+    let content_id = Ref::new(6);
+    let metadata_id = Ref::new(7);
+    // followed by the objects of the converted SVG
+    let qr_code_id = Ref::new(8);
+
+    let mut writer = PdfWriter::new();
+    writer
+        .catalog(catalog_id)
+        .pages(page_tree_id)
+        .metadata(metadata_id);
+    metadata::pdf(&mut writer, metadata_id, settings, text);
+    writer.pages(page_tree_id).kids([page_id]).count(1);
+    let mut page = writer.page(page_id);
+    page.media_box(Rect::new(0.0, 0.0, WIDTH * MM, HEIGHT * MM));
+    page.parent(page_tree_id);
+    page.contents(content_id);
+    let mut resources = page.resources();
+    resources.x_objects().pair(QR_CODE, qr_code_id);
+    resources
+        .fonts()
+        .pair(REGULAR, regular_id)
+        .pair(BOLD, bold_id);
+    resources.finish();
+    page.finish();
+    for (id, font) in [(regular_id, "Helvetica"), (bold_id, "Helvetica-Bold")] {
+        writer
+            .type1_font(id)
+            .base_font(Name(font.as_bytes()))
+            .encoding_predefined(Name(b"WinAnsiEncoding"));
+    }
+    svg2pdf::convert_tree_into(&tree, svg2pdf::Options::default(), &mut writer, qr_code_id);
+
+    let mut layout = Layout {
+        content: Content::new(),
+    };
+    layout.text(BOLD, 11.0, 5.0, 5.0, "Zahlteil");
+    layout
+        .content
+        .save_state()
+        .transform([
+            46.0 * MM,
+            0.0,
+            0.0,
+            46.0 * MM,
+            5.0 * MM,
+            (HEIGHT - 17.0 - 46.0) * MM,
+        ])
+        .x_object(QR_CODE)
+        .restore_state();
+
+    layout.text(BOLD, 8.0, 5.0, 68.0, "Währung");
+    layout.text(REGULAR, 10.0, 5.0, 72.0, bill.currency.code());
+    layout.text(BOLD, 8.0, 15.0, 68.0, "Betrag");
+    match bill.amount {
+        Some(amount) => layout.text(REGULAR, 10.0, 15.0, 72.0, &format_amount(amount)),
+        None => layout.corners(15.0, 71.5, 40.0, 15.0),
+    }
+
+    let mut y = 5.0;
+    let mut account = vec![format_groups(&bill.iban, 4, 4)];
+    account.extend(bill.creditor.lines());
+    layout.section(&mut y, "Konto / Zahlbar an", &account);
+    match bill.reference_type {
+        Reference::Qrr => {
+            let reference = format_groups(&bill.reference, 2, 5);
+            layout.section(&mut y, "Referenz", &[reference]);
+        },
+        Reference::Scor => {
+            let reference = format_groups(&bill.reference, 4, 4);
+            layout.section(&mut y, "Referenz", &[reference]);
+        },
+        Reference::Non => {},
+    }
+    if !bill.message.is_empty() {
+        layout.section(
+            &mut y,
+            "Zusätzliche Informationen",
+            std::slice::from_ref(&bill.message),
+        );
+    }
+    match &bill.debtor {
+        Some(debtor) => layout.section(&mut y, "Zahlbar durch", &debtor.lines()),
+        None => {
+            layout.text(BOLD, 8.0, INFO_X, y, "Zahlbar durch (Name/Adresse)");
+            layout.corners(INFO_X, y + 1.5, 65.0, 25.0);
+        },
+    }
+
+    writer.stream(content_id, &layout.content.finish());
+    Ok(writer.finish())
+}
+
+struct Layout {
+    content: Content,
+}
+
+impl Layout {
+    /// Writes a line of text with its top at `y` millimeters from the top edge.
+    fn text(&mut self, font: Name<'_>, size: f32, x: f32, y: f32, text: &str) {
+        let baseline = HEIGHT * MM - y * MM - size * 0.8;
+        self.content
+            .begin_text()
+            .set_font(font, size)
+            .next_line(x * MM, baseline)
+            .show(Str(&win_ansi(text)))
+            .end_text();
+    }
+
+    /// Writes a heading and its value lines, which are wrapped to the width of the section.
+    fn section(&mut self, y: &mut f32, heading: &str, lines: &[String]) {
+        self.text(BOLD, 8.0, INFO_X, *y, heading);
+        *y += 9.0 / MM;
+        for line in lines {
+            for line in wrap(line, (WIDTH - INFO_X - 5.0) * MM, 10.0) {
+                self.text(REGULAR, 10.0, INFO_X, *y, &line);
+                *y += 11.0 / MM;
+            }
+        }
+        *y += 9.0 / MM;
+    }
+
+    /// Draws the corner marks of a field to fill in by hand.
+    fn corners(&mut self, x: f32, y: f32, width: f32, height: f32) {
+        let len = 3.0 * MM;
+        let (left, top) = (x * MM, (HEIGHT - y) * MM);
+        let (right, bottom) = (left + width * MM, top - height * MM);
+        self.content.set_line_width(0.75);
+        for (x, y, dx, dy) in [
+            (left, top, len, -len),
+            (right, top, -len, -len),
+            (left, bottom, len, len),
+            (right, bottom, -len, len),
+        ] {
+            self.content
+                .move_to(x + dx, y)
+                .line_to(x, y)
+                .line_to(x, y + dy);
+        }
+        self.content.stroke();
+    }
+}
+
+/// Groups `text` for readability, e.g. "21 00000 00003" for `first` = 2 and `size` = 5.
+fn format_groups(text: &str, first: usize, size: usize) -> String {
+    let mut result = String::from(&text[..first.min(text.len())]);
+    for (index, c) in text.chars().skip(first).enumerate() {
+        if index % size == 0 {
+            result.push(' ');
+        }
+        result.push(c);
+    }
+    result
+}
+
+/// Formats cents like "1 949.75", as required in the payment part.
+fn format_amount(cents: u64) -> String {
+    let amount = swiss::format_amount(cents);
+    let (whole, fraction) = amount.split_once('.').unwrap_or((&amount, "00"));
+    let mut result = String::new();
+    for (index, c) in whole.chars().enumerate() {
+        if index > 0 && (whole.len() - index) % 3 == 0 {
+            result.push(' ');
+        }
+        result.push(c);
+    }
+    format!("{result}.{fraction}")
+}
+
+#[cfg(test)]
+mod tests {
+    use qrcode::render::svg;
+
+    use super::*;
+
+    #[test]
+    fn formatting() {
+        assert_eq!(format_amount(194_975), "1 949.75");
+        assert_eq!(format_amount(100_000_000), "1 000 000.00");
+        assert_eq!(format_amount(5), "0.05");
+        assert_eq!(
+            format_groups("210000000003139471430009017", 2, 5),
+            "21 00000 00003 13947 14300 09017"
+        );
+        assert_eq!(
+            format_groups("CH4431999123000889012", 4, 4),
+            "CH44 3199 9123 0008 8901 2"
+        );
+        assert_eq!(format_groups("RF18", 4, 4), "RF18");
+    }
+
+    #[test]
+    fn a6_page() {
+        let form = swiss::Form {
+            iban: "CH44 3199 9123 0008 8901 2".to_owned(),
+            name: "Robert Schneider AG".to_owned(),
+            zip: "2501".to_owned(),
+            town: "Biel".to_owned(),
+            amount: "1949.75".to_owned(),
+            reference: "21 00000 00003 13947 14300 09017".to_owned(),
+            message: "Auftrag vom 15.06.2020".to_owned(),
+            ..swiss::Form::default()
+        };
+        let svg = qrcode::QrCode::new("SPC")
+            .unwrap()
+            .render::<svg::Color<'_>>()
+            .quiet_zone(false)
+            .build();
+        let pdf = pdf(&form.bill().unwrap(), &svg, "t=swiss", "SPC").unwrap();
+        assert_eq!(metadata::read(&pdf).unwrap(), "t=swiss");
+
+        // every object is where the cross-reference table says it is
+        let text = String::from_utf8_lossy(&pdf);
+        let (_, start) = text.rsplit_once("startxref").unwrap();
+        let start: usize = start
+            .trim()
+            .trim_end_matches("%%EOF")
+            .trim()
+            .parse()
+            .unwrap();
+        let xref = std::str::from_utf8(&pdf[start..]).unwrap();
+        let mut xref = xref.lines().skip(1);
+        let (_, count) = xref.next().unwrap().split_once(' ').unwrap();
+        let count: usize = count.parse().unwrap();
+        for (id, entry) in xref.take(count).enumerate().skip(1) {
+            let offset: usize = entry[..10].parse().unwrap();
+            assert!(pdf[offset..].starts_with(format!("{id} 0 obj").as_bytes()));
+        }
+
+        let (_, media_box) = text.split_once("/MediaBox [").unwrap();
+        let (media_box, _) = media_box.split_once(']').unwrap();
+        let size: Vec<f32> = media_box
+            .split(' ')
+            .map(|number| number.parse().unwrap())
+            .collect();
+        assert_eq!(size[..2], [0.0, 0.0]);
+        assert!((size[2] / MM - 148.0).abs() < 0.01);
+        assert!((size[3] / MM - 105.0).abs() < 0.01);
+    }
+}
@@ -0,0 +1,107 @@
+//! The smallest printed size at which a camera can still tell the modules of a code apart.
+
+use serde::Serialize;
+
+/// What the scanning camera sees.
+#[derive(Debug, Clone, Copy)]
+pub struct Camera {
+    /// The horizontal angle of view in degrees.
+    pub field_of_view: f64,
+    /// The width of the analysed frames in pixels.
+    pub resolution: f64,
+    /// The pixels that a module has to cover to be recognized.
+    pub pixels_per_module: f64,
+}
+
+impl Default for Camera {
+    /// A phone camera with a normal lens, whose scanner app looks at 720p frames.
+    fn default() -> Self {
+        Self {
+            field_of_view: 65.0,
+            resolution: 1280.0,
+            pixels_per_module: 3.0,
+        }
+    }
+}
+
+impl Camera {
+    /// The smallest width of a module in millimetres at a distance in millimetres.
+    pub fn module_size(&self, distance: f64) -> f64 {
+        let visible = 2.0 * distance * (self.field_of_view.to_radians() / 2.0).tan();
+        visible / self.resolution * self.pixels_per_module
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Export {
+    pub format: &'static str,
+    /// The width of the symbol including the quiet zone in millimetres.
+    pub width: f64,
+    pub too_small: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PrintSize {
+    /// The smallest width of a module in millimetres.
+    pub module: f64,
+    /// The smallest width of the symbol including the quiet zone in millimetres.
+    pub symbol: f64,
+    pub exports: Vec<Export>,
+}
+
+impl PrintSize {
+    /// Compares the printed widths of the exports in millimetres to the smallest readable width
+    /// of a symbol that is `modules` wide including the quiet zone.
+    pub fn new(
+        modules: usize,
+        distance: f64,
+        camera: &Camera,
+        exports: &[(&'static str, f64)],
+    ) -> Self {
+        let module = camera.module_size(distance);
+        let symbol = module * modules as f64;
+        let exports = exports
+            .iter()
+            .map(|&(format, width)| Export {
+                format,
+                width,
+                too_small: width < symbol,
+            })
+            .collect();
+        Self {
+            module,
+            symbol,
+            exports,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grows_with_distance() {
+        let camera = Camera::default();
+        let near = camera.module_size(300.0);
+        assert!((0.85..0.95).contains(&near), "{near}");
+        assert!((camera.module_size(3000.0) - 10.0 * near).abs() < 1e-9);
+
+        let sharp = Camera {
+            resolution: 2560.0,
+            ..camera
+        };
+        assert!((sharp.module_size(300.0) - near / 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn flags_small_exports() {
+        let size = PrintSize::new(33, 1000.0, &Camera::default(), &[
+            ("pdf", 85.0),
+            ("png", 210.0),
+        ]);
+        assert!((size.symbol - 33.0 * size.module).abs() < 1e-9);
+        assert!(size.exports[0].too_small);
+        assert!(!size.exports[1].too_small);
+    }
+}
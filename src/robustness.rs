@@ -0,0 +1,276 @@
+//! Estimating how much abuse a printed code tolerates, by decoding ever more distorted copies of
+//! its image.
+
+use image::{GrayImage, Luma};
+use serde::Serialize;
+
+use crate::decode::Perspective;
+use crate::payload::Payload;
+use crate::verify::verify;
+
+/// Pixels per module of the analysed image.
+pub const SCALE: u32 = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Distortion {
+    /// A Gaussian blur, the standard deviation in modules.
+    Blur,
+    /// Gaussian noise, the standard deviation in grey levels.
+    Noise,
+    /// A perspective view from below, the percentage by which the top edge is shorter.
+    Skew,
+    /// Randomly inverted modules outside the finder patterns, in percent of those modules.
+    Damage,
+}
+
+impl Distortion {
+    const ALL: [Self; 4] = [Self::Blur, Self::Noise, Self::Skew, Self::Damage];
+
+    /// The levels of severity, in increasing order.
+    fn levels(self) -> Vec<f64> {
+        let steps = |count: u32, step: f64| (1..=count).map(|i| f64::from(i) * step).collect();
+        match self {
+            Self::Blur => steps(15, 0.1),
+            Self::Noise => steps(16, 10.0),
+            Self::Skew => steps(12, 5.0),
+            Self::Damage => steps(20, 1.0),
+        }
+    }
+
+    /// Distorts an image of a symbol with a quiet zone of `quiet_zone` modules.
+    fn apply(self, image: &GrayImage, quiet_zone: u32, level: f64, rng: &mut Rng) -> GrayImage {
+        match self {
+            Self::Blur => blur(image, level * f64::from(SCALE)),
+            Self::Noise => {
+                let mut image = image.clone();
+                for pixel in image.pixels_mut() {
+                    let value = f64::from(pixel.0[0]) + level * rng.gaussian();
+                    pixel.0[0] = value.round().clamp(0.0, 255.0) as u8;
+                }
+                image
+            },
+            Self::Skew => {
+                let (w, h) = (f64::from(image.width()), f64::from(image.height()));
+                let inset = w * level / 200.0;
+                let corners = [(0.0, 0.0), (w, 0.0), (w, h), (0.0, h)];
+                let skewed = [(inset, 0.0), (w - inset, 0.0), (w, h), (0.0, h)];
+                let back = Perspective::quad_to_quad(skewed, corners);
+                GrayImage::from_fn(image.width(), image.height(), |x, y| {
+                    let (sx, sy) = back.apply(f64::from(x) + 0.5, f64::from(y) + 0.5);
+                    match sx >= 0.0 && sy >= 0.0 && sx < w && sy < h {
+                        true => *image.get_pixel(sx as u32, sy as u32),
+                        false => Luma([255]),
+                    }
+                })
+            },
+            Self::Damage => {
+                let mut image = image.clone();
+                let width = image.width() / SCALE - 2 * quiet_zone;
+                // a damaged finder pattern hides the whole code, which is not what is measured here
+                let finder =
+                    |x: u32, y: u32| (x < 8 || x >= width - 8) && y < 8 || x < 8 && y >= width - 8;
+                let mut modules: Vec<(u32, u32)> = (0..width * width)
+                    .map(|i| (i % width, i / width))
+                    .filter(|&(x, y)| !finder(x, y))
+                    .collect();
+                let count = (modules.len() as f64 * level / 100.0).round() as usize;
+                for i in 0..count {
+                    let j = i + rng.below(modules.len() - i);
+                    modules.swap(i, j);
+                    let (x, y) = (modules[i].0 + quiet_zone, modules[i].1 + quiet_zone);
+                    for py in y * SCALE..(y + 1) * SCALE {
+                        for px in x * SCALE..(x + 1) * SCALE {
+                            let pixel = image.get_pixel_mut(px, py);
+                            pixel.0[0] = 255 - pixel.0[0];
+                        }
+                    }
+                }
+                image
+            },
+        }
+    }
+}
+
+/// Approximates a Gaussian blur by three box blurs in each direction, which is fast even for
+/// wide kernels.
+fn blur(image: &GrayImage, sigma: f64) -> GrayImage {
+    const PASSES: f64 = 3.0;
+    // the widths of the boxes, some `narrow` and the others two pixels wider
+    let ideal = (12.0 * sigma * sigma / PASSES + 1.0).sqrt();
+    let narrow = match ideal.floor() as usize {
+        width if width % 2 == 0 => width.max(2) - 1,
+        width => width,
+    };
+    let n = narrow as f64;
+    let narrow_passes = ((12.0 * sigma * sigma - PASSES * n * n - 4.0 * PASSES * n - 3.0 * PASSES)
+        / (-4.0 * n - 4.0))
+        .round() as usize;
+
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let mut pixels: Vec<f64> = image.pixels().map(|p| f64::from(p.0[0])).collect();
+    let mut buffer = vec![0.0; pixels.len()];
+    for pass in 0..PASSES as usize {
+        let radius = match pass < narrow_passes {
+            true => narrow / 2,
+            false => narrow / 2 + 1,
+        };
+        box_blur(&pixels, &mut buffer, width, height, radius, true);
+        box_blur(&buffer, &mut pixels, width, height, radius, false);
+    }
+    GrayImage::from_fn(image.width(), image.height(), |x, y| {
+        Luma([pixels[y as usize * width + x as usize].round() as u8])
+    })
+}
+
+/// The mean of `2 * radius + 1` pixels in a row or column, repeating the pixels at the edges.
+fn box_blur(
+    source: &[f64],
+    target: &mut [f64],
+    width: usize,
+    height: usize,
+    radius: usize,
+    horizontal: bool,
+) {
+    let (lines, len) = if horizontal {
+        (height, width)
+    } else {
+        (width, height)
+    };
+    let index = |line: usize, i: usize| match horizontal {
+        true => line * width + i,
+        false => i * width + line,
+    };
+    let size = (2 * radius + 1) as f64;
+    for line in 0..lines {
+        let at = |i: isize| source[index(line, i.clamp(0, len as isize - 1) as usize)];
+        let r = radius as isize;
+        let mut sum: f64 = (-r..=r).map(at).sum();
+        for i in 0..len {
+            target[index(line, i)] = sum / size;
+            sum += at(i as isize + r + 1) - at(i as isize - r);
+        }
+    }
+}
+
+/// The result of one kind of distortion.
+#[derive(Debug, Serialize)]
+pub struct Tolerance {
+    pub distortion: Distortion,
+    pub levels: usize,
+    /// Number of levels that could still be read.
+    pub passed: usize,
+    /// The highest level that could be read.
+    pub tolerated: Option<f64>,
+    /// The lowest level that could not be read.
+    pub failed: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Robustness {
+    /// The share of the passed levels, from 0 to 100.
+    pub score: u32,
+    pub tolerances: Vec<Tolerance>,
+}
+
+/// Distorts an image of a symbol, `SCALE` pixels per module, until it can no longer be read.
+pub fn analyse(image: &GrayImage, quiet_zone: u32, payload: &Payload) -> Robustness {
+    let tolerances: Vec<_> = Distortion::ALL
+        .into_iter()
+        .map(|distortion| {
+            let levels = distortion.levels();
+            let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+            let failed = levels.iter().position(|&level| {
+                let distorted = distortion.apply(image, quiet_zone, level, &mut rng);
+                verify(&distorted, payload).is_err()
+            });
+            let passed = failed.unwrap_or(levels.len());
+            Tolerance {
+                distortion,
+                levels: levels.len(),
+                passed,
+                tolerated: passed.checked_sub(1).map(|i| levels[i]),
+                failed: failed.map(|i| levels[i]),
+            }
+        })
+        .collect();
+    let share: f64 = tolerances
+        .iter()
+        .map(|t| t.passed as f64 / t.levels as f64)
+        .sum();
+    Robustness {
+        score: (100.0 * share / tolerances.len() as f64).round() as u32,
+        tolerances,
+    }
+}
+
+/// A small deterministic pseudo-random generator (xorshift64*), so that the analysis of the same
+/// code always gives the same result.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Uniformly distributed in `0..n`.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    /// Uniformly distributed in `(0, 1]`.
+    fn unit(&mut self) -> f64 {
+        ((self.next() >> 11) + 1) as f64 / (1_u64 << 53) as f64
+    }
+
+    /// Normally distributed with the standard deviation 1, by the Box-Muller transform.
+    fn gaussian(&mut self) -> f64 {
+        let (u, v) = (self.unit(), self.unit());
+        (-2.0 * u.ln()).sqrt() * (std::f64::consts::TAU * v).cos()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use qrcode::{EcLevel, QrCode};
+
+    use super::*;
+
+    fn image(text: &str, ec_level: EcLevel) -> GrayImage {
+        QrCode::with_error_correction_level(text, ec_level)
+            .unwrap()
+            .render::<Luma<u8>>()
+            .module_dimensions(SCALE, SCALE)
+            .build()
+    }
+
+    #[test]
+    fn higher_levels_tolerate_more_damage() {
+        let payload = Payload::text("https://www.vetmed.fu-berlin.de/");
+        let low = analyse(&image(&payload.text, EcLevel::L), 4, &payload);
+        let high = analyse(&image(&payload.text, EcLevel::H), 4, &payload);
+        for robustness in [&low, &high] {
+            assert!(robustness.score > 0 && robustness.score < 100);
+            let distortions = &robustness.tolerances[..3];
+            assert!(distortions
+                .iter()
+                .all(|t| t.passed > 0 && t.failed.is_some()));
+        }
+        let damage = |r: &Robustness| r.tolerances[3].passed;
+        assert!(damage(&high) > damage(&low));
+    }
+
+    #[test]
+    fn blur_keeps_the_mean() {
+        let mut image = GrayImage::from_pixel(40, 30, Luma([255]));
+        image.put_pixel(20, 15, Luma([0]));
+        let blurred = blur(&image, 2.0);
+        let sum: u32 = blurred.pixels().map(|p| 255 - u32::from(p.0[0])).sum();
+        assert!((250..=260).contains(&sum));
+        assert!(blurred.get_pixel(20, 15).0[0] > 240);
+        assert_eq!(blurred.get_pixel(0, 0).0[0], 255);
+    }
+}
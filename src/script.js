@@ -1,17 +1,89 @@
 (function () {
     "use strict";
 
+    const form = document.querySelector("#form");
+    const fieldsets_typed = form.querySelectorAll("fieldset[data-type]");
+    const select_type = document.querySelector("#type");
     const input_url = document.querySelector("#url");
+    const select_charset = document.querySelector("#charset");
+    const p_info = document.querySelector("#info");
+    const p_url_warnings = document.querySelector("#url-warnings");
+    const span_url_warnings = p_url_warnings.querySelector("span");
+    const input_url_confirm = p_url_warnings.querySelector("input");
+    const p_compact = document.querySelector("#compact");
+    const span_compact = p_compact.querySelector("span");
+    const button_compact = p_compact.querySelector("button");
+    const p_verify = document.querySelector("#verify");
+    const span_verify = p_verify.querySelector("span");
+    const label_verify_override = p_verify.querySelector("label");
+    const input_verify_override = p_verify.querySelector("input");
+    const p_print_size = document.querySelector("#print-size");
+    const p_budget = document.querySelector("#budget");
+    const input_budget = p_budget.querySelector("input");
+    const [span_budget, span_budget_legend] = p_budget.querySelectorAll(":scope > span");
+    const p_robustness = document.querySelector("#robustness");
+    const span_robustness = p_robustness.querySelector("span");
+    const button_robustness = p_robustness.querySelector("button");
     const a_svg = document.querySelector("#svg");
     const a_pdf = document.querySelector("#pdf");
     const a_png = document.querySelector("#png");
+    const a_documents = form.querySelectorAll("a.document");
+    const a_batches = form.querySelectorAll("a.batch");
+    const a_animations = form.querySelectorAll("a.animation");
+    const a_animations_png = [...a_animations].map(a => document.getElementById(`${a.id}-png`));
+    const div_animation = document.querySelector("#animation");
+    const img_animation = document.querySelector("#animation img");
+    const button_scan_open = document.querySelector("#scan-open");
+    const button_scan_clipboard = document.querySelector("#scan-clipboard");
+    const button_export_open = document.querySelector("#export-open");
+    const button_scan_frames = document.querySelector("#scan-frames");
+    const div_scan = document.querySelector("#scan");
+    const input_otp_secret = document.querySelector("#otp-secret");
+    const select_otp_algorithm = document.querySelector("#otp-algorithm");
+    const button_otp_generate = document.querySelector("#otp-secret-generate");
+    const output_otp_code = document.querySelector("#otp-code");
+    const details_signature = document.querySelector("#signature");
+    const output_signature_public_key = document.querySelector("#signature-public-key");
+    const button_signature_generate = document.querySelector("#signature-generate");
+    const output_signature_trusted = document.querySelector("#signature-trusted");
+    const input_signature_name = document.querySelector("#signature-name");
+    const input_signature_key = document.querySelector("#signature-key");
+    const button_signature_trust = document.querySelector("#signature-trust");
+    const textarea_signature_text = document.querySelector("#signature-text");
+    const button_signature_verify = document.querySelector("#signature-verify");
+    const output_signature_result = document.querySelector("#signature-result");
     const img_svg = a_svg.querySelector("img");
     const img_pdf = a_pdf.querySelector("img");
     const img_png = a_png.querySelector("img");
 
     const NO_TIMEOUT = {};
     let timeout = NO_TIMEOUT;
-    let old_url = input_url.value;
+    let old_query = null;
+    const verify_failed = {
+        svg: false,
+        png: false,
+        batch: false,
+        animation: false,
+    };
+    // until verify.json answered for the current query
+    let verify_pending = true;
+    let url_warned = false;
+    // until url-check.json answered for the current query
+    let url_pending = true;
+    // the items of the last import of each kind
+    const imported = {};
+
+    const MODE_NAMES = {
+        numeric: "Numerisch",
+        alphanumeric: "Alphanumerisch",
+        byte: "Byte",
+        kanji: "Kanji",
+    };
+
+    const ECI_NAMES = {
+        20: "Shift-JIS",
+        26: "UTF-8",
+    };
 
     function prevent_default (ev) {
         ev.preventDefault();
@@ -26,16 +98,680 @@
         timeout = setTimeout(onchange_do, 500);
     };
 
+    function query () {
+        const params = new URLSearchParams();
+        for (let element of form.elements) {
+            if (!element.name || element.closest("fieldset").hidden) {
+                continue;
+            }
+            if (element.type === "checkbox") {
+                params.set(element.name, String(element.checked));
+            } else {
+                params.set(element.name, element.value);
+            }
+        }
+        return params.toString();
+    }
+
+    function show_type () {
+        for (let fieldset of fieldsets_typed) {
+            fieldset.hidden = fieldset.dataset.type !== select_type.value;
+        }
+    }
+
+    function show_info (info) {
+        p_info.textContent = "";
+        if (info.error !== undefined) {
+            p_info.classList.add("error");
+            p_info.textContent = info.error;
+            return;
+        }
+        p_info.classList.remove("error");
+
+        if (info.label !== null) {
+            p_info.append(`Klartext: ${info.label}. `);
+        }
+        if (info.symbology === "datamatrix") {
+            p_info.append(
+                `Data-Matrix-Code mit ${info.width}×${info.width} Modulen, ` +
+                `${info.codewords} von ${info.capacity} Codewörtern belegt.`
+            );
+            return;
+        }
+
+        let text = `Version ${info.version} (${info.width}×${info.width} Module), ` +
+            `Fehlerkorrekturstufe ${info.ec_level}, Maskenmuster ${info.mask}, ` +
+            `${info.bits} von ${info.capacity} Bits belegt`;
+        if (info.eci !== null) {
+            text += `, ECI ${info.eci} (${ECI_NAMES[info.eci]})`;
+        }
+        if (select_charset.value === "sjis" && info.charset !== "sjis") {
+            text += ". Nicht alle Zeichen sind in Shift-JIS darstellbar, daher wird UTF-8 verwendet";
+        }
+        p_info.append(`${text}. Segmente: `);
+        for (let segment of info.segments) {
+            const span = document.createElement("span");
+            span.className = "segment";
+            span.title = `${MODE_NAMES[segment.mode]}, ${segment.bits} Bits`;
+            span.textContent = segment.text.replaceAll("\x1d", "␝");
+            p_info.append(span, " ");
+        }
+    }
+
+    function block_downloads () {
+        const override = input_verify_override.checked;
+        const unconfirmed = url_pending || (url_warned && !input_url_confirm.checked);
+        const blocked = check => verify_pending || (verify_failed[check] && !override);
+        for (let a of [a_svg, a_pdf, ...a_documents]) {
+            a.classList.toggle("blocked", blocked("svg") || unconfirmed);
+        }
+        a_png.classList.toggle("blocked", blocked("png") || unconfirmed);
+        for (let a of a_batches) {
+            a.classList.toggle("blocked", blocked("batch"));
+        }
+        for (let a of [...a_animations, ...a_animations_png]) {
+            a.classList.toggle("blocked", blocked("animation"));
+        }
+        label_verify_override.hidden =
+            verify_pending || !Object.values(verify_failed).some(failed => failed);
+    }
+
+    function show_verification (verification) {
+        verify_pending = false;
+        input_verify_override.checked = false;
+        const failed = check => check !== undefined && check.error !== undefined;
+        for (let check of Object.keys(verify_failed)) {
+            verify_failed[check] = failed(verification[check]);
+        }
+
+        const errors = [];
+        if (verify_failed.svg) {
+            errors.push(`SVG und PDF: ${verification.svg.error}`);
+        }
+        if (verify_failed.png) {
+            errors.push(`PNG: ${verification.png.error}`);
+        }
+        if (verify_failed.batch) {
+            errors.push(`PDF aller Einträge: ${verification.batch.error}`);
+        }
+        if (verify_failed.animation) {
+            errors.push(`Animation: ${verification.animation.error}`);
+        }
+        p_verify.classList.toggle("error", errors.length > 0);
+        if (errors.length > 0) {
+            span_verify.textContent = `Der Code konnte nicht korrekt gegengelesen werden! ${errors.join(" ")}`;
+        } else if (verification.svg === undefined) {
+            // nothing to download, or nothing we can check
+            span_verify.textContent = verification.skipped ?? "";
+        } else {
+            const corrected = Math.max(verification.svg.corrected, verification.png.corrected);
+            let text = `Gegengelesen: SVG, PNG und PDF enthalten „${verification.svg.text}“`;
+            if (corrected > 0) {
+                text += `, dabei wurden ${corrected} Codewörter korrigiert`;
+            }
+            span_verify.textContent = `${text}.`;
+        }
+        block_downloads();
+    }
+
+    function show_verification_failure (err) {
+        console.error(err);
+        verify_pending = false;
+        input_verify_override.checked = false;
+        p_verify.classList.add("error");
+        for (let check of Object.keys(verify_failed)) {
+            verify_failed[check] = true;
+        }
+        span_verify.textContent = `Der Code konnte nicht gegengelesen werden: ${err}`;
+        block_downloads();
+    }
+
+    function show_print_size (print_size) {
+        p_print_size.classList.toggle("error", print_size.error !== undefined);
+        if (print_size.error !== undefined) {
+            p_print_size.textContent = print_size.error;
+            return;
+        }
+        const length = mm => mm < 10
+            ? `${number(Math.round(mm * 10) / 10)} mm`
+            : `${number(Math.round(mm / 10 * 10) / 10)} cm`;
+        const exports = print_size.exports
+            .map(e => `${e.format.toUpperCase()} ${length(e.width)}`)
+            .join(", ");
+        let text = `Zum Scannen aus diesem Abstand braucht ein Modul mindestens ` +
+            `${length(print_size.module)}, der ganze Code mit Ruhezone ${length(print_size.symbol)}. ` +
+            `Gedruckt bei 300 dpi: ${exports}.`;
+        const too_small = print_size.exports.filter(e => e.too_small);
+        if (too_small.length > 0) {
+            const formats = too_small.map(e => e.format.toUpperCase()).join(" und ");
+            text += ` Achtung: ${formats} ${too_small.length === 1 ? "ist" : "sind"} zu klein!`;
+        }
+        p_print_size.classList.toggle("error", too_small.length > 0);
+        p_print_size.textContent = text;
+    }
+
+    function show_budget (budget) {
+        span_budget_legend.hidden = budget.blocks === undefined;
+        if (budget.blocks === undefined) {
+            p_budget.classList.toggle("error", budget.error !== undefined);
+            span_budget.textContent = budget.skipped ?? budget.error;
+            return;
+        }
+        const range = values => {
+            const [min, max] = [Math.min(...values), Math.max(...values)];
+            return min === max ? `${min}` : `${min}–${max}`;
+        };
+        const count = budget.blocks.length;
+        const correctable = range(budget.blocks.map(block => block.correctable));
+        let text = `${count} ${count === 1 ? "Block" : "Blöcke"} mit ` +
+            `${range(budget.blocks.map(block => block.data))} Daten- und ` +
+            `${range(budget.blocks.map(block => block.ec))} Korrekturcodewörtern, ` +
+            `je Block dürfen ${correctable} Codewörter unlesbar sein.`;
+        const covered = budget.blocks.reduce((sum, block) => sum + block.covered, 0);
+        const exceeded = budget.blocks.some(block => block.covered > block.correctable);
+        if (covered > 0) {
+            const worst = budget.blocks.reduce((worst, block) =>
+                block.covered / block.correctable > worst.covered / worst.correctable ? block : worst);
+            text += ` Die Grafik verdeckt ${covered} Codewörter, im ungünstigsten Block ` +
+                `${worst.covered} von ${worst.correctable}.`;
+        }
+        if (exceeded) {
+            text += " Der Code ist so nicht lesbar!";
+        }
+        p_budget.classList.toggle("error", exceeded);
+        span_budget.textContent = text;
+    }
+
+    function toggle_budget () {
+        const budget_query = query();
+        img_svg.src = input_budget.checked ? `./budget.svg?${budget_query}` : `./kurzlink.svg?${budget_query}`;
+        p_budget.classList.remove("error");
+        span_budget.textContent = "";
+        span_budget_legend.hidden = true;
+        if (input_budget.checked) {
+            fetch(`./budget.json?${budget_query}`)
+                .then(response => response.json())
+                .then(show_budget)
+                .catch(err => console.error(err));
+        }
+    }
+
+    const DISTORTIONS = {
+        blur: ["Unschärfe", tolerated => `${number(tolerated)} Module`],
+        noise: ["Rauschen", tolerated => `${number(tolerated)} Graustufen`],
+        skew: ["Schrägansicht", tolerated => `${number(tolerated)} %`],
+        damage: ["Beschädigung", tolerated => `${number(tolerated)} % der Module`],
+    };
+
+    function number (value) {
+        return value.toLocaleString("de-DE");
+    }
+
+    function analyse_robustness () {
+        const analysed_query = query();
+        button_robustness.disabled = true;
+        p_robustness.classList.remove("error");
+        span_robustness.textContent = "Wird analysiert …";
+        fetch(`./robustness.json?${analysed_query}`)
+            .then(response => response.json())
+            .then(function (robustness) {
+                if (analysed_query === query()) {
+                    show_robustness(robustness);
+                }
+            })
+            .catch(err => console.error(err))
+            .finally(() => button_robustness.disabled = false);
+    }
+
+    function show_robustness (robustness) {
+        if (robustness.score === undefined) {
+            p_robustness.classList.toggle("error", robustness.error !== undefined);
+            span_robustness.textContent = robustness.skipped ?? robustness.error;
+            return;
+        }
+        const parts = robustness.tolerances.map(function (tolerance) {
+            const [name, unit] = DISTORTIONS[tolerance.distortion];
+            const tolerated = tolerance.tolerated === null ? "gar nicht" : `bis ${unit(tolerance.tolerated)}`;
+            const failed = tolerance.failed === null ? "" : `, unlesbar ab ${unit(tolerance.failed)}`;
+            return `${name} ${tolerated}${failed}`;
+        });
+        span_robustness.textContent =
+            `Robustheit ${robustness.score} von 100. Lesbar bei ${parts.join("; ")}.`;
+    }
+
+    function prevent_blocked_download (ev) {
+        if (ev.currentTarget.classList.contains("blocked")) {
+            ev.preventDefault();
+        }
+    }
+
+    function show_url_warnings (warnings) {
+        url_pending = false;
+        input_url_confirm.checked = false;
+        url_warned = warnings.length > 0;
+        p_url_warnings.hidden = !url_warned;
+        span_url_warnings.textContent = `Vorsicht: ${warnings.join(" ")}`;
+        block_downloads();
+    }
+
+    function show_compact (compact) {
+        p_compact.hidden = compact === null;
+        if (compact === null) {
+            return;
+        }
+        span_compact.textContent =
+            `In Großbuchstaben („${compact.url}“) wird der QR-Code kleiner: Version ` +
+            `${compact.version_after} (${compact.width_after}×${compact.width_after} Module) statt ` +
+            `${compact.version_before} (${compact.width_before}×${compact.width_before} Module).`;
+        button_compact.onclick = function () {
+            input_url.value = compact.url;
+            onchange_do();
+        };
+    }
+
+    function fill_fields (prefix, values) {
+        for (let [key, value] of Object.entries(values)) {
+            const element = form.elements[`${prefix}[${key}]`];
+            if (element === undefined) {
+                continue;
+            } else if (element.type === "checkbox") {
+                element.checked = value;
+            } else {
+                element.value = value;
+            }
+        }
+        onchange();
+    }
+
+    function show_import_error (result) {
+        if (result.error === undefined) {
+            return false;
+        }
+        p_info.classList.add("error");
+        p_info.textContent = result.error;
+        return true;
+    }
+
+    // how imported items are listed for picking, by kind, or else by their first value
+    const DESCRIBE = {
+        event: event => `${event.start.replace("T", " ")} ${event.title}`,
+        contact: function (contact) {
+            const name = [contact.given, contact.family].filter(part => (part ?? "") !== "").join(" ");
+            return name || contact.org;
+        },
+    };
+
+    function describe (kind, item) {
+        if (DESCRIBE[kind] !== undefined) {
+            return DESCRIBE[kind](item);
+        }
+        return Object.values(item).find(value => typeof value === "string" && value !== "") ?? "";
+    }
+
+    function show_imported (kind, items) {
+        imported[kind] = items;
+        // the PDF of all items and its check depend on them, even if the form stays the same
+        old_query = null;
+        const select_pick = form.querySelector(`fieldset[data-type="${kind}"] select.pick`);
+        if (select_pick === null) {
+            return;
+        }
+        select_pick.textContent = "";
+        for (let [index, item] of items.entries()) {
+            const option = document.createElement("option");
+            option.value = index;
+            option.textContent = describe(kind, item);
+            select_pick.append(option);
+        }
+        select_pick.hidden = items.length < 2;
+    }
+
+    function import_items (kind) {
+        fetch(`./import/form.json?t=${kind}`)
+            .then(response => response.json())
+            .then(function (result) {
+                if (show_import_error(result) || result.items.length === 0) {
+                    return;
+                }
+                show_imported(kind, result.items);
+                fill_fields(kind, result.items[0]);
+            })
+            .catch(err => console.error(err));
+    }
+
+    let animation_fps = 0;
+    let animation_interval = null;
+
+    function show_animation_info (a_animation, result) {
+        const a_animation_png = document.getElementById(`${a_animation.id}-png`);
+        const button_fullscreen = document.getElementById(`${a_animation.id}-fullscreen`);
+        const p_animation_info = document.getElementById(`${a_animation.id}-info`);
+        const failed = result.error !== undefined;
+        p_animation_info.classList.toggle("error", failed);
+        a_animation.hidden = a_animation_png.hidden = button_fullscreen.hidden = failed;
+        if (failed) {
+            p_animation_info.textContent = result.error;
+            return;
+        }
+        animation_fps = result.fps;
+        p_animation_info.textContent =
+            `Animiert in ${number(result.blocks)} Blöcken: GIF und PNG zeigen ${number(result.frames)} ` +
+            `Codes in ${number(Math.ceil(result.frames / result.fps))} Sekunden und beginnen dann ` +
+            "von vorn, im Vollbild folgen ohne Ende neue Codes.";
+    }
+
+    function start_animation () {
+        const animation_query = query();
+        let frame = 0;
+        const next_frame = function () {
+            img_animation.src = `./animation/frame.svg?${animation_query}&frame=${frame++}`;
+        };
+        next_frame();
+        animation_interval = setInterval(next_frame, 1000 / animation_fps);
+        div_animation.hidden = false;
+        div_animation.requestFullscreen().catch(err => console.error(err));
+    }
+
+    function stop_animation () {
+        clearInterval(animation_interval);
+        div_animation.hidden = true;
+        if (document.fullscreenElement !== null) {
+            document.exitFullscreen().catch(err => console.error(err));
+        }
+    }
+
+    function regenerate (text) {
+        select_type.value = "url";
+        input_url.value = text;
+        show_type();
+        onchange();
+    }
+
+    function show_scanned (codes) {
+        div_scan.textContent = "";
+        div_scan.hidden = false;
+        if (codes.length > 1) {
+            div_scan.append(`${codes.length} Codes gefunden:`);
+        }
+        for (let code of codes) {
+            const p = document.createElement("p");
+            const span = document.createElement("span");
+            span.className = "segment";
+            span.textContent = code.text.replaceAll("\x1d", "␝");
+            const button = document.createElement("button");
+            button.type = "button";
+            button.textContent = "Neu erzeugen";
+            if (code.fnc1) {
+                button.disabled = true;
+                button.title = "GS1-Codes bitte unter „GS1-Produktdaten“ neu erfassen.";
+            } else {
+                button.title = "Den Inhalt mit den aktuellen Einstellungen neu erzeugen.";
+                button.addEventListener("click", () => regenerate(code.text));
+            }
+            p.append(span, ` (Version ${code.version}, Fehlerkorrekturstufe ${code.ec_level}) `, button);
+            div_scan.append(p);
+        }
+    }
+
+    function open_image () {
+        fetch("./import/image.json")
+            .then(response => response.json())
+            .then(function (result) {
+                if (!show_import_error(result) && result.items.length > 0) {
+                    show_scanned(result.items);
+                }
+            })
+            .catch(err => console.error(err));
+    }
+
+    function read_frames () {
+        fetch("./import/frames.json")
+            .then(response => response.json())
+            .then(function (result) {
+                if (show_import_error(result) || result.items.length === 0) {
+                    return;
+                }
+                const received = result.items[0];
+                const kind = received.kind === "fountain" ? "Fontänencode" : "Structured Append";
+                div_scan.textContent =
+                    `${number(received.len)} Bytes aus ${number(received.codes)} Codes ` +
+                    `(${kind}) in ${number(received.frames)} Bildern gespeichert unter ${received.path}.`;
+                div_scan.hidden = false;
+            })
+            .catch(err => console.error(err));
+    }
+
+    function paste_image () {
+        fetch("./import/clipboard.json")
+            .then(response => response.json())
+            .then(function (result) {
+                if (show_import_error(result)) {
+                    return;
+                }
+                show_scanned(result.items);
+                const first = result.items.find(code => !code.fnc1);
+                if (first !== undefined) {
+                    regenerate(first.text);
+                    input_url.focus();
+                }
+            })
+            .catch(err => console.error(err));
+    }
+
+    function restore_settings (settings) {
+        const items = [];
+        // exports made before imports were generic only held contacts
+        let items_kind = null;
+        for (let [key, value] of new URLSearchParams(settings)) {
+            const item = key.match(/^(items|contacts)\[(\d+)\]\[(\w+)\]$/);
+            if (item !== null) {
+                const [, list, index, field] = item;
+                items_kind = list === "contacts" ? "contact" : null;
+                items[index] ??= {};
+                items[index][field] = value;
+                continue;
+            }
+            const element = form.elements[key];
+            if (element === undefined || element.type === undefined) {
+                continue;
+            } else if (element.type === "checkbox") {
+                element.checked = value === "true";
+            } else if (key === "charset" && value === "auto") {
+                // the former default, which mixed in Kanji mode
+                element.value = "kanji";
+            } else {
+                element.value = value;
+            }
+        }
+        if (items.length > 0) {
+            show_imported(items_kind ?? select_type.value, items.filter(Boolean));
+        }
+        div_scan.hidden = true;
+        show_type();
+        onchange();
+    }
+
+    function open_export () {
+        fetch("./import/export.json")
+            .then(response => response.json())
+            .then(function (result) {
+                if (!show_import_error(result) && result.items.length > 0) {
+                    restore_settings(result.items[0].settings);
+                }
+            })
+            .catch(err => console.error(err));
+    }
+
+    function items_query () {
+        const params = new URLSearchParams();
+        for (let [index, item] of (imported[select_type.value] ?? []).entries()) {
+            for (let [key, value] of Object.entries(item)) {
+                if (value !== "") {
+                    params.append(`items[${index}][${key}]`, value);
+                }
+            }
+        }
+        return params.toString();
+    }
+
+    function random_otp_secret () {
+        fetch(`./otp/secret.json?otp[algorithm]=${select_otp_algorithm.value}`)
+            .then(response => response.json())
+            .then(function (result) {
+                if (result.error !== undefined) {
+                    output_otp_code.textContent = result.error;
+                    return;
+                }
+                input_otp_secret.value = result.secret;
+                onchange();
+            })
+            .catch(err => console.error(err));
+    }
+
+    function show_otp_code () {
+        if (select_type.value !== "otp") {
+            return;
+        }
+        fetch(`./otp/code.json?${query()}`)
+            .then(response => response.json())
+            .then(function (result) {
+                if (result.error !== undefined) {
+                    output_otp_code.textContent = "–";
+                } else if (result.remaining !== null) {
+                    output_otp_code.textContent = `${result.code} (noch ${result.remaining} s gültig)`;
+                } else {
+                    output_otp_code.textContent = result.code;
+                }
+            })
+            .catch(err => console.error(err));
+    }
+
+    function show_signature_keys (result) {
+        if (result.error !== undefined) {
+            output_signature_public_key.textContent = result.error;
+            return;
+        }
+        output_signature_public_key.textContent = result.public_key ?? "noch keiner";
+        button_signature_generate.hidden = result.public_key !== null;
+        output_signature_trusted.textContent = result.trusted_keys.join(", ") || "nur der eigene";
+    }
+
+    function signature_request (path, params) {
+        return fetch(`./signature/${path}.json?${new URLSearchParams(params)}`)
+            .then(response => response.json());
+    }
+
+    function load_signature_keys (generate) {
+        signature_request("key", { generate: String(generate) })
+            .then(show_signature_keys)
+            .catch(err => console.error(err));
+    }
+
+    function trust_signature_key () {
+        const params = { name: input_signature_name.value, key: input_signature_key.value };
+        signature_request("trust", params)
+            .then(function (result) {
+                show_signature_keys(result);
+                if (result.error === undefined) {
+                    input_signature_name.value = input_signature_key.value = "";
+                }
+            })
+            .catch(err => console.error(err));
+    }
+
+    function verify_signature () {
+        signature_request("verify", { text: textarea_signature_text.value })
+            .then(function (result) {
+                output_signature_result.classList.toggle("error", result.status !== "valid");
+                if (result.error !== undefined) {
+                    output_signature_result.textContent = result.error;
+                } else if (result.status === "valid") {
+                    output_signature_result.textContent = `Echt, signiert von: ${result.signer}`;
+                } else if (result.status === "invalid") {
+                    output_signature_result.textContent =
+                        "Ungültig: verändert oder von einem unbekannten Schlüssel signiert!";
+                } else {
+                    output_signature_result.textContent = "Der Inhalt ist nicht signiert.";
+                }
+            })
+            .catch(err => console.error(err));
+    }
+
     function onchange_do () {
         timeout = NO_TIMEOUT;
-        let new_url = input_url.value;
-        if (new_url !== old_url) {
-            old_url = null;
-            let q = encodeURIComponent(new_url);
-            img_svg.src = img_pdf.src = img_png.src = a_svg.href = `./kurzlink.svg?q=${q}`;
-            a_pdf.href = `./kurzlink.pdf?q=${q}`;
-            a_png.href = `./kurzlink.png?q=${q}`;
-            old_url = new_url;
+        let new_query = query();
+        if (new_query !== old_query) {
+            old_query = null;
+            img_svg.src = img_pdf.src = img_png.src = a_svg.href = `./kurzlink.svg?${new_query}`;
+            a_pdf.href = `./kurzlink.pdf?${new_query}`;
+            a_png.href = `./kurzlink.png?${new_query}`;
+            for (let a of a_documents) {
+                a.href = `./document.pdf?${new_query}`;
+            }
+            for (let a of a_batches) {
+                a.href = `./batch.pdf?${new_query}&${items_query()}`;
+                a.hidden = (imported[a.closest("fieldset").dataset.type] ?? []).length < 2;
+            }
+            for (let a of a_animations) {
+                a.href = `./animation.gif?${new_query}`;
+            }
+            for (let a of a_animations_png) {
+                a.href = `./animation.png?${new_query}`;
+            }
+            // a slow answer for an old query must not unblock the downloads of the current one
+            const is_current = () => old_query === new_query;
+            for (let a of a_animations) {
+                if (!a.closest("fieldset").hidden) {
+                    fetch(`./animation.json?${new_query}`)
+                        .then(response => response.json())
+                        .then(result => show_animation_info(a, result))
+                        .catch(err => console.error(err));
+                }
+            }
+            fetch(`./info.json?${new_query}`)
+                .then(response => response.json())
+                .then(show_info)
+                .catch(err => console.error(err));
+            fetch(`./print-size.json?${new_query}`)
+                .then(response => response.json())
+                .then(show_print_size)
+                .catch(err => console.error(err));
+            fetch(`./url-check.json?${new_query}`)
+                .then(response => response.json())
+                .then(warnings => {
+                    if (is_current()) {
+                        show_url_warnings(warnings);
+                    }
+                })
+                .catch(err => {
+                    console.error(err);
+                    if (is_current()) {
+                        show_url_warnings([]);
+                    }
+                });
+            fetch(`./compact.json?${new_query}`)
+                .then(response => response.json())
+                .then(show_compact)
+                .catch(err => console.error(err));
+            verify_pending = url_pending = true;
+            block_downloads();
+            span_verify.textContent = "Wird gegengelesen …";
+            p_robustness.classList.remove("error");
+            span_robustness.textContent = "";
+            toggle_budget();
+            fetch(`./verify.json?${new_query}&${items_query()}`)
+                .then(response => response.json())
+                .then(verification => {
+                    if (is_current()) {
+                        show_verification(verification);
+                    }
+                })
+                .catch(err => {
+                    if (is_current()) {
+                        show_verification_failure(err);
+                    }
+                });
+            old_query = new_query;
         }
     }
 
@@ -43,4 +779,50 @@
     for (let event of ["blur", "change", "cut", "input", "keydown", "keypress", "keyup", "paste"]) {
         input_url.addEventListener(event, onchange);
     }
+    for (let element of form.elements) {
+        element.addEventListener("change", onchange);
+        element.addEventListener("input", onchange);
+    }
+    select_type.addEventListener("change", show_type);
+    for (let a of [a_svg, a_pdf, a_png, ...a_documents, ...a_batches, ...a_animations, ...a_animations_png]) {
+        a.addEventListener("click", prevent_blocked_download);
+    }
+    input_verify_override.addEventListener("change", block_downloads);
+    input_url_confirm.addEventListener("change", block_downloads);
+    input_budget.addEventListener("change", toggle_budget);
+    button_robustness.addEventListener("click", analyse_robustness);
+    for (let button of form.querySelectorAll("button.import")) {
+        button.addEventListener("click", () => import_items(button.closest("fieldset").dataset.type));
+    }
+    for (let a of a_animations) {
+        document.getElementById(`${a.id}-fullscreen`).addEventListener("click", start_animation);
+    }
+    div_animation.addEventListener("click", stop_animation);
+    document.addEventListener("fullscreenchange", function () {
+        if (document.fullscreenElement === null && !div_animation.hidden) {
+            stop_animation();
+        }
+    });
+    button_scan_open.addEventListener("click", open_image);
+    button_scan_clipboard.addEventListener("click", paste_image);
+    button_export_open.addEventListener("click", open_export);
+    button_scan_frames.addEventListener("click", read_frames);
+    details_signature.addEventListener("toggle", function () {
+        if (details_signature.open) {
+            load_signature_keys(false);
+        }
+    });
+    button_signature_generate.addEventListener("click", () => load_signature_keys(true));
+    button_signature_trust.addEventListener("click", trust_signature_key);
+    button_signature_verify.addEventListener("click", verify_signature);
+    button_otp_generate.addEventListener("click", random_otp_secret);
+    setInterval(show_otp_code, 1000);
+    for (let select_pick of form.querySelectorAll("select.pick")) {
+        select_pick.addEventListener("change", function () {
+            const kind = select_pick.closest("fieldset").dataset.type;
+            fill_fields(kind, imported[kind][select_pick.value]);
+        });
+    }
+    show_type();
+    onchange_do();
 } ());
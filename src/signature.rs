@@ -0,0 +1,295 @@
+//! Ed25519 signatures appended to payloads, so that scanned codes can be checked for authenticity.
+//!
+//! The signature covers the UTF-8 text of the payload and is appended after a marker: URLs
+//! without a fragment get it as fragment, which browsers never send to the server, other texts
+//! on a line of their own.
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ed25519_dalek::{
+    Signature, Signer, SigningKey, VerifyingKey, PUBLIC_KEY_LENGTH, SECRET_KEY_LENGTH,
+    SIGNATURE_LENGTH,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::base45;
+use crate::config::{self, ConfigError, TrustedKey};
+use crate::payload::{Payload, Symbology};
+
+const MARKER_BASE64: &str = "ed25519=";
+/// Upper case, so that a Base45 signed text can stay in alphanumeric mode.
+const MARKER_BASE45: &str = "ED25519:";
+
+/// The name under which the own key is reported when verifying.
+const OWN_KEY: &str = "Eigener Schlüssel";
+
+#[derive(Debug, thiserror::Error)]
+pub enum SignatureError {
+    #[error("Es gibt noch keinen Signaturschlüssel, bitte zuerst einen erzeugen.")]
+    NoKey,
+    #[error("Der Signaturschlüssel in der Konfiguration ist ungültig.")]
+    InvalidKey,
+    #[error("„{0}“ ist kein gültiger öffentlicher Ed25519-Schlüssel.")]
+    InvalidPublicKey(String),
+    #[error("Bitte einen Namen für den Schlüssel angeben.")]
+    MissingName,
+    #[error("Nur URLs und freie Texte können signiert werden.")]
+    Unsupported,
+    #[error("Es konnte kein zufälliger Schlüssel erzeugt werden.")]
+    Random(#[source] getrandom::Error),
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Encoding {
+    /// 86 characters, readable by every scanner.
+    #[default]
+    Base64url,
+    /// 96 characters, but in alphanumeric mode if the payload is, too. Not used for URLs.
+    Base45,
+}
+
+/// The result of checking a scanned text.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum Verification {
+    /// There is no signature.
+    Unsigned,
+    /// The signature belongs to a trusted key.
+    Valid { signer: String, message: String },
+    /// The text was altered, or it was signed by an unknown key.
+    Invalid { message: String },
+}
+
+/// Appends a signature made with the key from the config.
+pub fn sign_payload(payload: Payload, encoding: Encoding) -> Result<Payload, SignatureError> {
+    if payload.bytes.is_some() || payload.fnc1 || payload.symbology != Symbology::Qr {
+        return Err(SignatureError::Unsupported);
+    }
+    let seed = signing_key(&config::load()?)?.ok_or(SignatureError::NoKey)?;
+    let signature = SigningKey::from_bytes(&seed)
+        .sign(payload.text.as_bytes())
+        .to_bytes();
+    Ok(Payload {
+        text: append(&payload.text, &signature, encoding),
+        ..payload
+    })
+}
+
+/// The public key of the own signing key, which is created first if there is none.
+pub fn public_key(generate: bool) -> Result<Option<String>, SignatureError> {
+    let mut config = config::load()?;
+    let seed = match (signing_key(&config)?, generate) {
+        (Some(seed), _) => seed,
+        (None, false) => return Ok(None),
+        (None, true) => {
+            let mut seed = [0; SECRET_KEY_LENGTH];
+            getrandom::getrandom(&mut seed).map_err(SignatureError::Random)?;
+            config.signing_key = Some(URL_SAFE_NO_PAD.encode(seed));
+            config::save(&config)?;
+            seed
+        },
+    };
+    Ok(Some(URL_SAFE_NO_PAD.encode(public_key_of(&seed))))
+}
+
+/// Adds a public key to the trusted ones, replacing a key of the same name.
+pub fn trust(name: &str, key: &str) -> Result<(), SignatureError> {
+    let (name, key) = (name.trim(), key.trim());
+    if name.is_empty() {
+        return Err(SignatureError::MissingName);
+    }
+    if decode_public_key(key).is_none() {
+        return Err(SignatureError::InvalidPublicKey(key.to_owned()));
+    }
+    let mut config = config::load()?;
+    config.trusted_keys.retain(|trusted| trusted.name != name);
+    config.trusted_keys.push(TrustedKey {
+        name: name.to_owned(),
+        key: key.to_owned(),
+    });
+    Ok(config::save(&config)?)
+}
+
+/// Checks a scanned text against the trusted keys and the own one.
+pub fn verify(text: &str) -> Result<Verification, SignatureError> {
+    let config = config::load()?;
+    let mut keys = config.trusted_keys.clone();
+    if let Some(seed) = signing_key(&config)? {
+        keys.push(TrustedKey {
+            name: OWN_KEY.to_owned(),
+            key: URL_SAFE_NO_PAD.encode(public_key_of(&seed)),
+        });
+    }
+    Ok(verify_with(text, &keys))
+}
+
+fn verify_with(text: &str, keys: &[TrustedKey]) -> Verification {
+    let Some((message, signature)) = split(text) else {
+        return Verification::Unsigned;
+    };
+    let signer = keys.iter().find(|trusted| {
+        decode_public_key(&trusted.key)
+            .is_some_and(|key| key.verify_strict(message.as_bytes(), &signature).is_ok())
+    });
+    let message = message.to_owned();
+    match signer {
+        Some(trusted) => Verification::Valid {
+            signer: trusted.name.clone(),
+            message,
+        },
+        None => Verification::Invalid { message },
+    }
+}
+
+fn signing_key(config: &config::Config) -> Result<Option<[u8; SECRET_KEY_LENGTH]>, SignatureError> {
+    let Some(key) = &config.signing_key else {
+        return Ok(None);
+    };
+    let seed = URL_SAFE_NO_PAD.decode(key.trim()).ok();
+    let seed = seed.and_then(|seed| seed.try_into().ok());
+    seed.map(Some).ok_or(SignatureError::InvalidKey)
+}
+
+fn public_key_of(seed: &[u8; SECRET_KEY_LENGTH]) -> [u8; PUBLIC_KEY_LENGTH] {
+    SigningKey::from_bytes(seed).verifying_key().to_bytes()
+}
+
+fn decode_public_key(key: &str) -> Option<VerifyingKey> {
+    let key = URL_SAFE_NO_PAD.decode(key.trim()).ok()?.try_into().ok()?;
+    VerifyingKey::from_bytes(&key).ok()
+}
+
+fn append(text: &str, signature: &[u8; SIGNATURE_LENGTH], encoding: Encoding) -> String {
+    let is_url = ["http://", "https://"].iter().any(|scheme| {
+        text.get(..scheme.len())
+            .is_some_and(|s| s.eq_ignore_ascii_case(scheme))
+    });
+    let (separator, encoding) = match is_url && !text.contains('#') {
+        // Base45 contains spaces and percent signs, which do not belong in a URL
+        true => ('#', Encoding::Base64url),
+        false => ('\n', encoding),
+    };
+    match encoding {
+        Encoding::Base64url => {
+            format!(
+                "{text}{separator}{MARKER_BASE64}{}",
+                URL_SAFE_NO_PAD.encode(signature)
+            )
+        },
+        Encoding::Base45 => {
+            format!(
+                "{text}{separator}{MARKER_BASE45}{}",
+                base45::encode(signature)
+            )
+        },
+    }
+}
+
+/// Splits a signed text into the signed message and the signature.
+fn split(text: &str) -> Option<(&str, Signature)> {
+    // neither Base45 nor Base64url use these separators
+    let text = text.trim_end_matches(['\r', '\n']);
+    let index = text.rfind(['#', '\n'])?;
+    let (message, suffix) = (&text[..index], &text[index + 1..]);
+    let signature = match (
+        suffix.strip_prefix(MARKER_BASE64),
+        suffix.strip_prefix(MARKER_BASE45),
+    ) {
+        (Some(encoded), _) => URL_SAFE_NO_PAD.decode(encoded).ok()?,
+        (_, Some(encoded)) => base45::decode(encoded)?,
+        (None, None) => return None,
+    };
+    let message = message.strip_suffix('\r').unwrap_or(message);
+    Some((message, Signature::from_slice(&signature).ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys() -> ([u8; SECRET_KEY_LENGTH], Vec<TrustedKey>) {
+        let seed = [42; SECRET_KEY_LENGTH];
+        let trusted = TrustedKey {
+            name: "Pressestelle".to_owned(),
+            key: URL_SAFE_NO_PAD.encode(public_key_of(&seed)),
+        };
+        (seed, vec![trusted])
+    }
+
+    fn sign(seed: &[u8; SECRET_KEY_LENGTH], message: &[u8]) -> [u8; SIGNATURE_LENGTH] {
+        SigningKey::from_bytes(seed).sign(message).to_bytes()
+    }
+
+    #[test]
+    fn rfc_8032_test_vector() {
+        // section 7.1, test 1: the config stores the seed, not the expanded key
+        let seed = URL_SAFE_NO_PAD
+            .decode("nWGxne_9WmC6hEr0kuwsxERJxWl7MmkZcDusAxyuf2A")
+            .unwrap();
+        let seed = seed.try_into().unwrap();
+        assert_eq!(
+            URL_SAFE_NO_PAD.encode(public_key_of(&seed)),
+            "11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo"
+        );
+        assert_eq!(
+            URL_SAFE_NO_PAD.encode(sign(&seed, b"")),
+            "5VZDAMNgrHKQhuLMgG6CioSHfx645dl02HPgZSJJAVVfuIIVkKM7rMYeOXAc-bRr0lv18FlbviRlUUFDjnoQCw"
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let (seed, keys) = keys();
+        for text in [
+            "https://www.fu-berlin.de/",
+            "WIFI:S:Gast;T:nopass;;",
+            "HTTPS://FU.DE#TOP",
+        ] {
+            let signature = sign(&seed, text.as_bytes());
+            for encoding in [Encoding::Base64url, Encoding::Base45] {
+                let signed = append(text, &signature, encoding);
+                assert_eq!(verify_with(&signed, &keys), Verification::Valid {
+                    signer: "Pressestelle".to_owned(),
+                    message: text.to_owned(),
+                });
+            }
+        }
+        let signed = append(
+            "https://fu-berlin.de/",
+            &sign(&seed, b"https://fu-berlin.de/"),
+            Encoding::Base64url,
+        );
+        assert!(signed.starts_with("https://fu-berlin.de/#ed25519="));
+        assert_eq!(signed.len(), "https://fu-berlin.de/#ed25519=".len() + 86);
+
+        let signature = sign(&seed, b"HTTPS://FU.DE/");
+        let signed = append("HTTPS://FU.DE/", &signature, Encoding::Base45);
+        assert_eq!(
+            signed,
+            append("HTTPS://FU.DE/", &signature, Encoding::Base64url)
+        );
+        let signed = append("HTTPS://FU.DE/#TOP", &signature, Encoding::Base45);
+        assert!(signed.starts_with("HTTPS://FU.DE/#TOP\nED25519:"));
+    }
+
+    #[test]
+    fn detects_tampering() {
+        let (seed, keys) = keys();
+        let signature = sign(&seed, b"https://www.fu-berlin.de/");
+        let signed = append("https://www.fu-berlin.de/", &signature, Encoding::Base64url);
+        let forged = signed.replace("fu-berlin.de", "fu-berlln.de");
+        assert_eq!(verify_with(&forged, &keys), Verification::Invalid {
+            message: "https://www.fu-berlln.de/".to_owned(),
+        });
+        assert_eq!(verify_with(&signed, &[]), Verification::Invalid {
+            message: "https://www.fu-berlin.de/".to_owned(),
+        });
+        assert_eq!(
+            verify_with("https://www.fu-berlin.de/", &keys),
+            Verification::Unsigned
+        );
+    }
+}
@@ -0,0 +1,232 @@
+use std::ops::Range;
+
+use qrcode::canvas::{Canvas, MaskPattern};
+use qrcode::render::{Pixel, Renderer};
+use qrcode::types::QrResult;
+use qrcode::{ec, Color, EcLevel, Version};
+
+/// The mask patterns, indexed by their reference number.
+const MASK_PATTERNS: [MaskPattern; 8] = [
+    MaskPattern::Checkerboard,
+    MaskPattern::HorizontalLines,
+    MaskPattern::VerticalLines,
+    MaskPattern::DiagonalLines,
+    MaskPattern::LargeCheckerboard,
+    MaskPattern::Fields,
+    MaskPattern::Diamonds,
+    MaskPattern::Meadow,
+];
+
+/// The 1:1:3:1:1 pattern of a finder pattern's center line.
+const FINDER: [Color; 7] = {
+    use Color::{Dark, Light};
+    [Dark, Light, Dark, Dark, Dark, Light, Dark]
+};
+
+pub const MASK_COUNT: u8 = MASK_PATTERNS.len() as u8;
+
+/// Width of the quiet zone in modules.
+pub const QUIET_ZONE: u32 = 4;
+
+/// A QR code symbol, like [`qrcode::QrCode`], but with a selectable mask pattern.
+#[derive(Clone)]
+pub struct Symbol {
+    content: Vec<Color>,
+    version: Version,
+    ec_level: EcLevel,
+    mask: u8,
+}
+
+impl Symbol {
+    /// Draws the data codewords `data` into a new symbol.
+    ///
+    /// If no `mask` is given, the pattern with the lowest penalty score is chosen.
+    pub fn new(
+        data: &[u8],
+        version: Version,
+        ec_level: EcLevel,
+        mask: Option<u8>,
+    ) -> QrResult<Self> {
+        let (data, ec_data) = ec::construct_codewords(data, version, ec_level)?;
+        let mut canvas = Canvas::new(version, ec_level);
+        canvas.draw_all_functional_patterns();
+        canvas.draw_data(&data, &ec_data);
+
+        let masked = |mask: u8| {
+            let mut canvas = canvas.clone();
+            canvas.apply_mask(MASK_PATTERNS[usize::from(mask)]);
+            canvas.into_colors()
+        };
+        let (mask, content) = match mask {
+            Some(mask) => (mask, masked(mask)),
+            None => (0..MASK_COUNT)
+                .map(|mask| (mask, masked(mask)))
+                .min_by_key(|(_, content)| penalty(content, version.width()))
+                .expect("there are mask patterns"),
+        };
+        Ok(Self {
+            content,
+            version,
+            ec_level,
+            mask,
+        })
+    }
+
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    pub fn error_correction_level(&self) -> EcLevel {
+        self.ec_level
+    }
+
+    pub fn mask(&self) -> u8 {
+        self.mask
+    }
+
+    /// Number of modules per side, without the quiet zone.
+    pub fn width(&self) -> usize {
+        self.version.width() as usize
+    }
+
+    pub fn is_dark(&self, x: usize, y: usize) -> bool {
+        self.content[y * self.width() + x] == Color::Dark
+    }
+
+    pub fn render<P: Pixel>(&self) -> Renderer<'_, P> {
+        Renderer::new(&self.content, self.width(), QUIET_ZONE)
+    }
+}
+
+/// The penalty score of a masked symbol according to ISO/IEC 18004, section 7.8.3, as computed
+/// by [`Canvas::apply_best_mask`], which does not tell which mask it chose.
+fn penalty(content: &[Color], width: i16) -> u32 {
+    let width = width as usize;
+    let row = |y: usize| -> Vec<Color> { content[y * width..(y + 1) * width].to_vec() };
+    let column = |x: usize| -> Vec<Color> { (0..width).map(|y| content[y * width + x]).collect() };
+    let lines: Vec<_> = (0..width).map(row).chain((0..width).map(column)).collect();
+
+    let mut score = 0;
+    for line in &lines {
+        // runs of five or more modules of the same color
+        for run in line.chunk_by(|a, b| a == b) {
+            if run.len() >= 5 {
+                score += run.len() as u32 - 2;
+            }
+        }
+        // patterns like the finder pattern, unless they have four light modules on both sides
+        for start in 0..width.saturating_sub(6) {
+            let light = |range: Range<usize>| line[range].iter().all(|&c| c == Color::Light);
+            if line[start..start + 7] == FINDER
+                && (light(start.saturating_sub(4)..start)
+                    || light(start + 7..width.min(start + 11)))
+            {
+                score += 40;
+            }
+        }
+    }
+    // blocks of 2×2 modules of the same color
+    for y in 0..width - 1 {
+        for x in 0..width - 1 {
+            let color = content[y * width + x];
+            let block = [(x + 1, y), (x, y + 1), (x + 1, y + 1)];
+            if block.iter().all(|&(x, y)| content[y * width + x] == color) {
+                score += 3;
+            }
+        }
+    }
+    // the deviation from equally many dark and light modules
+    let dark = content.iter().filter(|&&c| c == Color::Dark).count();
+    score += (dark * 200 / content.len()).abs_diff(100) as u32;
+    score
+}
+
+impl std::fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Symbol")
+            .field("version", &self.version)
+            .field("ec_level", &self.ec_level)
+            .field("mask", &self.mask)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use qrcode::bits::Bits;
+
+    use super::*;
+    use crate::decode::{self, Matrix};
+    use crate::encode::{self, Options, Pin};
+
+    fn data(text: &str, version: Version, ec_level: EcLevel) -> Vec<u8> {
+        let mut bits = Bits::new(version);
+        bits.push_optimal_data(text.as_bytes()).unwrap();
+        bits.push_terminator(ec_level).unwrap();
+        bits.into_bytes()
+    }
+
+    #[test]
+    fn chooses_the_best_mask() {
+        for (text, version, ec_level) in [
+            ("https://www.fu-berlin.de/", 2, EcLevel::M),
+            ("0123456789", 1, EcLevel::H),
+            (
+                "Freie Universität Berlin, Kaiserswerther Str. 16-18",
+                7,
+                EcLevel::Q,
+            ),
+            ("", 12, EcLevel::L),
+        ] {
+            let version = Version::Normal(version);
+            let data = data(text, version, ec_level);
+            let symbol = Symbol::new(&data, version, ec_level, None).unwrap();
+
+            let (data, ec_data) = ec::construct_codewords(&data, version, ec_level).unwrap();
+            let mut canvas = Canvas::new(version, ec_level);
+            canvas.draw_all_functional_patterns();
+            canvas.draw_data(&data, &ec_data);
+            assert_eq!(
+                symbol.content,
+                canvas.apply_best_mask().into_colors(),
+                "{text}"
+            );
+        }
+    }
+
+    #[test]
+    fn pinned_version_and_mask() {
+        for mask in 0..MASK_COUNT {
+            let options = Options {
+                version: Some(6),
+                pin: Pin::Exact,
+                mask: Some(mask),
+                ..Options::default()
+            };
+            let symbol = encode::encode("HELLO", EcLevel::Q, &options)
+                .unwrap()
+                .symbol;
+            assert_eq!(symbol.version(), Version::Normal(6));
+            assert_eq!(symbol.mask(), mask);
+
+            let matrix = Matrix {
+                width: symbol.width(),
+                modules: (0..symbol.width() * symbol.width())
+                    .map(|i| symbol.is_dark(i % symbol.width(), i / symbol.width()))
+                    .collect(),
+            };
+            let decoded = decode::decode_matrix(&matrix).unwrap();
+            assert_eq!(decoded.text, "HELLO");
+            assert_eq!((decoded.version, decoded.mask), (6, mask));
+        }
+
+        let options = Options {
+            version: Some(3),
+            ..Options::default()
+        };
+        let symbol = encode::encode("HELLO", EcLevel::Q, &options)
+            .unwrap()
+            .symbol;
+        assert_eq!(symbol.version(), Version::Normal(3));
+    }
+}
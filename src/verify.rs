@@ -0,0 +1,222 @@
+//! Reading rendered codes back in, to make sure that they contain what they should.
+
+use image::{GrayImage, Luma};
+use usvg::{FillRule, NodeExt, NodeKind, Paint, PathSegment, Transform, TreeParsing};
+
+use crate::decode::{decode_image, DecodeError, Decoded};
+use crate::payload::Payload;
+
+/// Pixels per module when rasterizing an SVG.
+const SCALE: f64 = 4.0;
+/// Line segments per Bézier curve.
+const CURVE_STEPS: usize = 8;
+
+/// A line segment from one point to another.
+type Edge = ((f64, f64), (f64, f64));
+
+#[derive(Debug, thiserror::Error)]
+pub enum VerifyError {
+    #[error(transparent)]
+    Decode(#[from] DecodeError),
+    #[error("Statt einem wurden {0} Codes gelesen.")]
+    Ambiguous(usize),
+    #[error("Der gelesene Inhalt weicht ab: {0:?}.")]
+    Mismatch(String),
+    #[error("Die SVG-Grafik ist ungültig: {0}.")]
+    Svg(#[from] usvg::Error),
+}
+
+/// Decodes the only code in an image and compares it to the payload it was generated from.
+pub fn verify(image: &GrayImage, payload: &Payload) -> Result<Decoded, VerifyError> {
+    let mut decoded = decode_image(image)?;
+    if decoded.len() != 1 {
+        return Err(VerifyError::Ambiguous(decoded.len()));
+    }
+    let decoded = decoded.remove(0);
+    let matches = match &payload.bytes {
+        Some(bytes) => decoded.data == *bytes,
+        None => decoded.text == payload.text,
+    };
+    match matches {
+        true => Ok(decoded),
+        false => Err(VerifyError::Mismatch(decoded.text)),
+    }
+}
+
+/// Verifies an SVG whose symbol is `modules` wide including the quiet zone.
+pub fn verify_svg(svg: &str, modules: usize, payload: &Payload) -> Result<Decoded, VerifyError> {
+    let size = (modules as f64 * SCALE) as u32;
+    verify(&rasterize(svg, size)?, payload)
+}
+
+/// Rasterizes the filled paths of an SVG onto a white square, which suffices for the paths and
+/// rectangles of our codes. Strokes, gradients, images and text are ignored.
+fn rasterize(svg: &str, size: u32) -> Result<GrayImage, usvg::Error> {
+    let tree = usvg::Tree::from_str(svg, &usvg::Options::default())?;
+    let view_box = usvg::utils::view_box_to_transform(
+        tree.view_box.rect,
+        tree.view_box.aspect,
+        usvg::Size::new(f64::from(size), f64::from(size)).ok_or(usvg::Error::InvalidSize)?,
+    );
+    let mut canvas = vec![255.0_f64; (size * size) as usize];
+    for node in tree.root.descendants() {
+        let NodeKind::Path(ref path) = *node.borrow() else { continue };
+        let Some(fill) = &path.fill else { continue };
+        let Paint::Color(color) = fill.paint else { continue };
+        if path.visibility != usvg::Visibility::Visible {
+            continue;
+        }
+        let mut opacity = fill.opacity.get();
+        for ancestor in node.ancestors() {
+            if let NodeKind::Group(ref group) = *ancestor.borrow() {
+                opacity *= group.opacity.get();
+            }
+        }
+        let luma = 0.299 * f64::from(color.red)
+            + 0.587 * f64::from(color.green)
+            + 0.114 * f64::from(color.blue);
+
+        let mut transform = view_box;
+        transform.append(&node.abs_transform());
+        let crossings = crossings(&edges(&path.data, &transform), size as usize);
+        for (row, crossings) in canvas.chunks_mut(size as usize).zip(crossings) {
+            for (from, to) in spans(crossings, fill.rule) {
+                let from = (from - 0.5).ceil().max(0.0) as usize;
+                let to = ((to - 0.5).floor() + 1.0).clamp(0.0, f64::from(size)) as usize;
+                for pixel in row.iter_mut().take(to).skip(from) {
+                    *pixel = *pixel * (1.0 - opacity) + luma * opacity;
+                }
+            }
+        }
+    }
+    Ok(GrayImage::from_fn(size, size, |x, y| {
+        Luma([canvas[(y * size + x) as usize].round() as u8])
+    }))
+}
+
+/// The outline of a path as closed polygons of line segments.
+fn edges(data: &usvg::PathData, transform: &Transform) -> Vec<Edge> {
+    let mut edges = Vec::new();
+    let (mut start, mut current) = ((0.0, 0.0), (0.0, 0.0));
+    let mut line = |from: &mut (f64, f64), to: (f64, f64)| {
+        edges.push((transform.apply(from.0, from.1), transform.apply(to.0, to.1)));
+        *from = to;
+    };
+    for segment in data.segments() {
+        match segment {
+            PathSegment::MoveTo { x, y } => {
+                if current != start {
+                    line(&mut current, start);
+                }
+                (start, current) = ((x, y), (x, y));
+            },
+            PathSegment::LineTo { x, y } => line(&mut current, (x, y)),
+            PathSegment::CurveTo {
+                x1,
+                y1,
+                x2,
+                y2,
+                x,
+                y,
+            } => {
+                let (x0, y0) = current;
+                for step in 1..=CURVE_STEPS {
+                    let t = step as f64 / CURVE_STEPS as f64;
+                    let s = 1.0 - t;
+                    let (a, b, c, d) = (s * s * s, 3.0 * s * s * t, 3.0 * s * t * t, t * t * t);
+                    let point = (
+                        a * x0 + b * x1 + c * x2 + d * x,
+                        a * y0 + b * y1 + c * y2 + d * y,
+                    );
+                    line(&mut current, point);
+                }
+            },
+            PathSegment::ClosePath => line(&mut current, start),
+        }
+    }
+    if current != start {
+        line(&mut current, start);
+    }
+    edges
+}
+
+/// The points where the edges cross the centre line of each row of pixels, with the direction
+/// of the edge.
+fn crossings(edges: &[Edge], height: usize) -> Vec<Vec<(f64, i32)>> {
+    let mut rows = vec![Vec::new(); height];
+    for &((x0, y0), (x1, y1)) in edges {
+        let (top, bottom, direction) = match y0 < y1 {
+            true => (y0, y1, 1),
+            false => (y1, y0, -1),
+        };
+        let first = (top - 0.5).ceil().max(0.0) as usize;
+        let end = ((bottom - 0.5).ceil().max(0.0) as usize).min(height);
+        for (y, row) in rows.iter_mut().enumerate().take(end).skip(first) {
+            let y = y as f64 + 0.5;
+            row.push((x0 + (y - y0) * (x1 - x0) / (y1 - y0), direction));
+        }
+    }
+    rows
+}
+
+/// The horizontal spans inside the polygons on one row.
+fn spans(mut crossings: Vec<(f64, i32)>, rule: FillRule) -> Vec<(f64, f64)> {
+    crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut spans = Vec::new();
+    let mut winding = 0;
+    for pair in crossings.windows(2) {
+        winding += pair[0].1;
+        let inside = match rule {
+            FillRule::NonZero => winding != 0,
+            FillRule::EvenOdd => winding % 2 != 0,
+        };
+        if inside && pair[0].0 < pair[1].0 {
+            spans.push((pair[0].0, pair[1].0));
+        }
+    }
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use image::LumaA;
+    use qrcode::render::svg;
+    use qrcode::{EcLevel, QrCode};
+
+    use super::*;
+    use crate::decode::flatten;
+
+    #[test]
+    fn rasterizes_svg() {
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10" viewBox="0 0 10 10">
+            <path d="M2 2h4v4h-4z M3 3v2h2v-2z" fill="#000" fill-rule="evenodd"/>
+            <rect x="8" y="0" width="2" height="10" fill="#000" fill-opacity="0.5"/>
+        </svg>"##;
+        let image = rasterize(svg, 20).unwrap();
+        assert_eq!(image.get_pixel(5, 5).0[0], 0);
+        assert_eq!(image.get_pixel(8, 8).0[0], 255);
+        assert_eq!(image.get_pixel(1, 1).0[0], 255);
+        assert_eq!(image.get_pixel(18, 3).0[0], 128);
+    }
+
+    #[test]
+    fn verifies_renders() {
+        let payload = Payload::text("https://www.vetmed.fu-berlin.de/");
+        let code = QrCode::with_error_correction_level(&payload.text, EcLevel::M).unwrap();
+        let svg = code
+            .render::<svg::Color<'_>>()
+            .min_dimensions(1000, 1000)
+            .build();
+        let decoded = verify_svg(&svg, code.width() + 8, &payload).unwrap();
+        assert_eq!(decoded.text, payload.text);
+
+        let other = Payload::text("https://www.fu-berlin.de/");
+        assert!(matches!(
+            verify_svg(&svg, code.width() + 8, &other),
+            Err(VerifyError::Mismatch(_))
+        ));
+
+        let png = code.render::<LumaA<u8>>().build();
+        assert!(verify(&flatten(&png), &payload).is_ok());
+    }
+}